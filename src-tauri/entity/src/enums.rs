@@ -162,3 +162,19 @@ pub enum ItemType {
     #[sea_orm(num_value = 2)]
     ModelInfo,
 }
+
+/// What a watch folder is scanned for
+#[derive(
+    Copy, Clone, Debug, Default, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize,
+)]
+#[serde(rename_all = "camelCase")]
+#[sea_orm(rs_type = "i8", db_type = "TinyInteger")]
+pub enum WatchFolderKind {
+    /// Draw Things project files
+    #[default]
+    #[sea_orm(num_value = 0)]
+    Projects,
+    /// Image files with embedded Draw Things metadata, indexed together as a single project
+    #[sea_orm(num_value = 1)]
+    LooseImages,
+}
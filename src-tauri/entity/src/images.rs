@@ -35,13 +35,13 @@ pub struct Model {
     pub start_width: i16,
     pub start_height: i16,
     pub seed: i64,
-    #[sea_orm(column_type = "Float")]
-    pub strength: f32,
+    #[sea_orm(column_type = "Float", nullable)]
+    pub strength: Option<f32>,
     pub steps: i16,
     #[sea_orm(column_type = "Float")]
     pub guidance_scale: f32,
-    #[sea_orm(column_type = "Float")]
-    pub shift: f32,
+    #[sea_orm(column_type = "Float", nullable)]
+    pub shift: Option<f32>,
     pub sampler: i8,
     pub hires_fix: bool,
     pub tiled_decoding: bool,
@@ -4,6 +4,7 @@ pub mod prelude;
 
 pub mod enums;
 pub mod image_controls;
+pub mod image_files;
 pub mod image_loras;
 pub mod images;
 pub mod models;
@@ -1,6 +1,7 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 2.0.0-rc.19
 
 pub use super::image_controls::Entity as ImageControls;
+pub use super::image_files::Entity as ImageFiles;
 pub use super::image_loras::Entity as ImageLoras;
 pub use super::images::Entity as Images;
 pub use super::models::Entity as Models;
@@ -15,6 +15,7 @@ pub struct Model {
     pub filesize: Option<i64>,
     pub modified: Option<i64>,
     pub excluded: bool,
+    pub compatibility: Option<Json>,
     #[sea_orm(
         belongs_to,
         from = "watchfolder_id",
@@ -25,6 +26,8 @@ pub struct Model {
     pub watchfolder: HasOne<super::watch_folders::Entity>,
     #[sea_orm(has_many)]
     pub images: HasMany<super::images::Entity>,
+    #[sea_orm(has_many)]
+    pub image_files: HasMany<super::image_files::Entity>,
 }
 
 impl ActiveModelBehavior for ActiveModel {}
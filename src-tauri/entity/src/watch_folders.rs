@@ -1,5 +1,6 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 2.0.0-rc.19
 
+use crate::enums::WatchFolderKind;
 use sea_orm::entity::prelude::*;
 use serde::Serialize;
 
@@ -15,6 +16,7 @@ pub struct Model {
     pub is_missing: bool,
     pub is_locked: bool,
     pub maint: u32,
+    pub kind: WatchFolderKind,
     #[sea_orm(has_many)]
     pub projects: HasMany<super::projects::Entity>,
 }
@@ -2,6 +2,10 @@ pub use sea_orm_migration::prelude::*;
 
 mod m20220101_000001_create_table;
 mod m20260308_105024_add_maint_column;
+mod m20261019_120000_add_watch_folder_kind;
+mod m20261019_130000_add_project_compatibility;
+mod m20261019_140000_add_image_files;
+mod m20261019_150000_nullable_strength_shift;
 
 pub struct Migrator;
 
@@ -11,6 +15,10 @@ impl MigratorTrait for Migrator {
         vec![
             Box::new(m20220101_000001_create_table::Migration),
             Box::new(m20260308_105024_add_maint_column::Migration),
+            Box::new(m20261019_120000_add_watch_folder_kind::Migration),
+            Box::new(m20261019_130000_add_project_compatibility::Migration),
+            Box::new(m20261019_140000_add_image_files::Migration),
+            Box::new(m20261019_150000_nullable_strength_shift::Migration),
         ]
     }
 }
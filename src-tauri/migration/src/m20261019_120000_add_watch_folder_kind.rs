@@ -0,0 +1,34 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // add kind column to watch_folders table, existing folders hold projects (0)
+        manager
+            .alter_table(
+                Table::alter()
+                    .table("watch_folders")
+                    .add_column(ColumnDef::new("kind").tiny_integer().not_null().default(0))
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table("watch_folders")
+                    .drop_column_if_exists("kind")
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
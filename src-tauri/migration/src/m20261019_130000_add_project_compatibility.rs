@@ -0,0 +1,34 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // add compatibility column to projects table, filled in when a project is scanned
+        manager
+            .alter_table(
+                Table::alter()
+                    .table("projects")
+                    .add_column(ColumnDef::new("compatibility").json().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table("projects")
+                    .drop_column_if_exists("compatibility")
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,88 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // the files of a loose images folder, which is indexed as a single project.
+        // each file's image uses the file's id as its node id
+        manager
+            .create_table(
+                Table::create()
+                    .table(ImageFiles::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ImageFiles::Id)
+                            .integer()
+                            .not_null()
+                            .primary_key()
+                            .auto_increment(),
+                    )
+                    .col(ColumnDef::new(ImageFiles::ProjectId).integer().not_null())
+                    .col(ColumnDef::new(ImageFiles::Path).string().not_null())
+                    .col(
+                        ColumnDef::new(ImageFiles::Filesize)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ImageFiles::Modified)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_image_files_project")
+                            .from(ImageFiles::Table, ImageFiles::ProjectId)
+                            .to(Projects::Table, Projects::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .index(
+                        Index::create()
+                            .name("idx_image_files_project_id_path")
+                            .col(ImageFiles::ProjectId)
+                            .col(ImageFiles::Path)
+                            .unique(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // loose images were indexed as a project per file, they are re-added on the next sync
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            "DELETE FROM projects WHERE watchfolder_id IN (SELECT id FROM watch_folders WHERE kind = 1);",
+        )
+        .await?;
+        db.execute_unprepared("INSERT INTO images_fts(images_fts) VALUES('rebuild');")
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ImageFiles::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(Iden)]
+enum ImageFiles {
+    Table,
+    Id,
+    ProjectId,
+    Path,
+    Filesize,
+    Modified,
+}
+
+#[derive(Iden)]
+enum Projects {
+    Table,
+    Id,
+}
@@ -0,0 +1,41 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+/// strength and shift become nullable, so images without Draw Things metadata don't report
+/// made up values. SQLite can't alter a column's constraints, so each column is replaced
+const COLUMNS: [&str; 2] = ["strength", "shift"];
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        for column in COLUMNS {
+            db.execute_unprepared(&format!(
+                "ALTER TABLE images ADD COLUMN {column}_new REAL NULL;
+                UPDATE images SET {column}_new = {column};
+                ALTER TABLE images DROP COLUMN {column};
+                ALTER TABLE images RENAME COLUMN {column}_new TO {column};"
+            ))
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        for column in COLUMNS {
+            db.execute_unprepared(&format!(
+                "ALTER TABLE images ADD COLUMN {column}_old REAL NOT NULL DEFAULT 0;
+                UPDATE images SET {column}_old = COALESCE({column}, 0);
+                ALTER TABLE images DROP COLUMN {column};
+                ALTER TABLE images RENAME COLUMN {column}_old TO {column};"
+            ))
+            .await?;
+        }
+
+        Ok(())
+    }
+}
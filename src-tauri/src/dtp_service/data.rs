@@ -8,14 +8,21 @@ use crate::{
     projects_db::{
         dt_project::{TensorHistoryNode, ThnFilter},
         dtos::{
-            clip::ClipExtra, image::ListImagesResult, model::ModelExtra, project::ProjectExtra,
-            tensor::TensorSize, watch_folder::WatchFolderDTO,
+            clip::ClipExtra, export::TensorImageFormat, image::ListImagesResult,
+            lineage::NodeLineage,
+            metadata_diff::MetadataDiff, model::ModelExtra, project::ProjectExtra,
+            stats::LibraryStats, tensor::TensorSize, text::PromptTimeline,
+            watch_folder::WatchFolderDTO,
         },
         filters::ListImagesFilter,
-        folder_cache, DecodeTensorOptions, DrawThingsMetadata, DtProjectRef,
+        folder_cache,
+        image_metadata::read_drawthings_metadata,
+        metadata_diff::diff_metadata,
+        DecodeTensorOptions, DrawThingsMetadata, DtProjectRef,
     },
 };
 use dtm_macros::dtp_commands;
+use entity::enums::WatchFolderKind;
 
 #[dtp_commands]
 impl DTPService {
@@ -84,6 +91,35 @@ impl DTPService {
         Ok(db.list_images(opts).await.map_err(anyhow::Error::msg)?)
     }
 
+    /// Usage stats for the images matching the given filters (or the whole library)
+    #[dtp_command]
+    pub async fn library_stats(
+        &self,
+        project_ids: Option<Vec<i64>>,
+        search: Option<String>,
+        filters: Option<Vec<ListImagesFilter>>,
+        show_video: Option<bool>,
+        show_image: Option<bool>,
+        show_disconnected: Option<bool>,
+        utc_offset_minutes: Option<i32>,
+    ) -> crate::TAResult<LibraryStats> {
+        let db = self.get_db().await.map_err(anyhow::Error::msg)?;
+        let opts = crate::projects_db::dtos::image::ListImagesOptions {
+            project_ids,
+            search,
+            filters,
+            show_video,
+            show_image,
+            show_disconnected,
+            ..Default::default()
+        };
+
+        Ok(db
+            .library_stats(opts, utc_offset_minutes)
+            .await
+            .map_err(anyhow::Error::msg)?)
+    }
+
     #[dtp_command]
     pub async fn find_image_from_preview_id(
         &self,
@@ -111,10 +147,11 @@ impl DTPService {
         &self,
         dt_folder: Option<bool>,
         test_override: Option<String>,
+        kind: Option<WatchFolderKind>,
     ) -> crate::TAResult<()> {
         let result = get_folder(&self.app_handle, dt_folder, test_override).await
             .map_err(anyhow::Error::msg)?;
-        self.internal_add_watch_folder(result.path, result.bookmark)
+        self.internal_add_watch_folder(result.path, result.bookmark, kind.unwrap_or_default())
             .await
             .map_err(anyhow::Error::msg)?;
         Ok(())
@@ -125,19 +162,33 @@ impl DTPService {
         path: String,
         bookmark: String,
     ) -> anyhow::Result<()> {
-        self.internal_add_watch_folder(path, bookmark).await.map_err(anyhow::Error::msg)
+        self.internal_add_watch_folder(path, bookmark, WatchFolderKind::Projects)
+            .await
+            .map_err(anyhow::Error::msg)
+    }
+
+    /// Adds a folder of image files, indexed by the Draw Things metadata embedded in them
+    pub async fn add_loose_images_folder(
+        self: &Self,
+        path: String,
+        bookmark: String,
+    ) -> anyhow::Result<()> {
+        self.internal_add_watch_folder(path, bookmark, WatchFolderKind::LooseImages)
+            .await
+            .map_err(anyhow::Error::msg)
     }
 
     async fn internal_add_watch_folder(
         &self,
         path: String,
         bookmark: String,
+        kind: WatchFolderKind,
     ) -> anyhow::Result<()> {
         let db = self.get_db().await.map_err(anyhow::Error::msg)?;
-        let folder = db.add_watch_folder(&path, &bookmark, false).await.map_err(anyhow::Error::msg)?;
+        let folder = db.add_watch_folder(&path, &bookmark, false, kind).await.map_err(anyhow::Error::msg)?;
 
         // Resolve the bookmark and update if needed
-        let resolved = folder_cache::resolve_bookmark(folder.id, &bookmark).await;
+        let resolved = db.resolve_watch_folder(&folder).await;
         if let Ok(resolved) = resolved {
             match resolved {
                 crate::bookmarks::ResolveResult::Resolved(updated_path) => {
@@ -171,7 +222,15 @@ impl DTPService {
     #[dtp_command]
     pub async fn remove_watch_folder(&self, id: i64) -> crate::TAResult<()> {
         let db = self.get_db().await.map_err(anyhow::Error::msg)?;
+        let project_ids: Vec<i64> = db
+            .list_projects(Some(id))
+            .await
+            .map_err(anyhow::Error::msg)?
+            .iter()
+            .map(|p| p.id)
+            .collect();
         db.remove_watch_folders(vec![id]).await.map_err(anyhow::Error::msg)?;
+        self.invalidate_thumbs(&project_ids).await;
 
         self.events
             .emit(crate::dtp_service::events::DTPEvent::WatchFoldersChanged);
@@ -205,19 +264,15 @@ impl DTPService {
 
     #[dtp_command]
     pub async fn get_metadata(&self, image_id: i64) -> crate::TAResult<DrawThingsMetadata> {
-        let pdb = self.get_db().await.map_err(anyhow::Error::msg)?;
-        let image = pdb.get_image(image_id).await.map_err(anyhow::Error::msg)?;
-        let dt_project = pdb.get_dt_project(DtProjectRef::Id(image.project_id)).await
-            .map_err(anyhow::Error::msg)?;
-        let nodes = dt_project
-            .get_tensor_history_nodes(Some(ThnFilter::Rowid(image.node_id)), None)
-            .await
-            .map_err(anyhow::Error::msg)?;
-        let node = nodes
-            .into_iter()
-            .next()
-            .ok_or_else(|| anyhow::anyhow!("Node not found"))?;
-        Ok(DrawThingsMetadata::try_from(&node.node_data()).map_err(anyhow::Error::msg)?)
+        Ok(self.load_metadata(image_id).await?)
+    }
+
+    /// Compares the generation settings of two images, field by field
+    #[dtp_command]
+    pub async fn diff_metadata(&self, image_a: i64, image_b: i64) -> crate::TAResult<MetadataDiff> {
+        let a = self.load_metadata(image_a).await?;
+        let b = self.load_metadata(image_b).await?;
+        Ok(diff_metadata(&a, &b))
     }
 
     #[dtp_command]
@@ -239,6 +294,7 @@ impl DTPService {
         node_id: Option<i64>,
         tensor_id: String,
         as_png: bool,
+        format: Option<TensorImageFormat>,
     ) -> crate::TAResult<tauri::ipc::Response> {
         let project = self.get_project(project_id).await.map_err(anyhow::Error::msg)?;
         let tensor = project
@@ -260,22 +316,75 @@ impl DTPService {
             DecodeTensorOptions {
                 as_png,
                 history_node: metadata,
-                size: None,
+                resize: None,
+                format: format.unwrap_or_default(),
             },
         )?;
         Ok(tauri::ipc::Response::new(buffer))
     }
 
+    /// Returns the node the given node was generated from, if any
     #[dtp_command]
     pub async fn find_predecessor(
         &self,
-        _project_id: i64,
-        _row_id: i64,
-        _lineage: i64,
-        _logical_time: i64,
+        project_id: i64,
+        row_id: i64,
     ) -> crate::TAResult<Vec<TensorHistoryNode>> {
-        // Pending rework — returns empty until find_predecessor_candidates is reimplemented.
-        Ok(vec![])
+        let project = self.get_project(project_id).await.map_err(anyhow::Error::msg)?;
+        let lineage = project
+            .get_node_lineage(row_id)
+            .await.map_err(anyhow::Error::msg)?;
+
+        match lineage.node.parent {
+            Some(parent) => Ok(project
+                .get_tensor_history_nodes(Some(ThnFilter::Rowid(parent)), None)
+                .await.map_err(anyhow::Error::msg)?),
+            None => Ok(vec![]),
+        }
+    }
+
+    /// Returns the ancestors and descendants of a node in the project's history
+    #[dtp_command]
+    pub async fn get_lineage(&self, project_id: i64, row_id: i64) -> crate::TAResult<NodeLineage> {
+        let project = self.get_project(project_id).await.map_err(anyhow::Error::msg)?;
+        Ok(project
+            .get_node_lineage(row_id)
+            .await.map_err(anyhow::Error::msg)?)
+    }
+
+    /// Returns every prompt revision in a text lineage, linked to the generations that used it
+    #[dtp_command]
+    pub async fn get_prompt_timeline(
+        &self,
+        project_id: i64,
+        text_lineage: i64,
+    ) -> crate::TAResult<PromptTimeline> {
+        let project = self.get_project(project_id).await.map_err(anyhow::Error::msg)?;
+        Ok(project
+            .get_prompt_timeline(text_lineage)
+            .await.map_err(anyhow::Error::msg)?)
+    }
+
+    // Helper method to get an image's metadata, from its node or from a loose image file
+    async fn load_metadata(&self, image_id: i64) -> anyhow::Result<DrawThingsMetadata> {
+        let pdb = self.get_db().await?;
+        let image = pdb.get_image(image_id).await?;
+        let (_, kind) = pdb.get_project_location(image.project_id).await?;
+        if kind == WatchFolderKind::LooseImages {
+            let path = pdb.get_image_file_path(image.project_id, image.node_id).await?;
+            let bytes = tokio::fs::read(&path).await?;
+            return read_drawthings_metadata(&bytes)
+                .ok_or_else(|| anyhow::anyhow!("Image has no Draw Things metadata"));
+        }
+        let dt_project = pdb.get_dt_project(DtProjectRef::Id(image.project_id)).await?;
+        let nodes = dt_project
+            .get_tensor_history_nodes(Some(ThnFilter::Rowid(image.node_id)), None)
+            .await?;
+        let node = nodes
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("Node not found"))?;
+        Ok(DrawThingsMetadata::try_from(&node.node_data())?)
     }
 
     // Helper method to get a DTProject instance
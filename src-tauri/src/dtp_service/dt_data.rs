@@ -3,7 +3,8 @@ use dtm_macros::dtp_commands;
 use crate::{
     dtp_service::DTPService,
     projects_db::{
-        dt_project::{TensorHistoryNode, ThnData, ThnFilter},
+        dt_project::{DTProjectTable, TensorHistoryNode, ThnData, ThnFilter},
+        dtos::explorer::{ProjectTable, ProjectTablePage},
         DtProjectRef,
     },
     IntoTAResult, TAResult,
@@ -25,13 +26,7 @@ impl DTPService {
         max_rowid: Option<i64>,
         select: Option<Vec<String>>,
     ) -> TAResult<Vec<TensorHistoryNode>> {
-        let project_ref = if let Some(id) = project_id {
-            DtProjectRef::Id(id)
-        } else if let Some(path) = project_path {
-            DtProjectRef::Path(path)
-        } else {
-            return anyhow::anyhow!("project_id or project_path is required").into_ta_result();
-        };
+        let project_ref = project_ref(project_id, project_path)?;
 
         let dt_project = self.get_db().await?.get_dt_project(project_ref).await?;
 
@@ -71,4 +66,42 @@ impl DTPService {
 
         Ok(rows)
     }
+
+    #[dtp_command]
+    pub async fn dt_list_tables(
+        &self,
+        project_id: Option<i64>,
+        project_path: Option<String>,
+    ) -> TAResult<Vec<ProjectTable>> {
+        let project_ref = project_ref(project_id, project_path)?;
+        let dt_project = self.get_db().await?.get_dt_project(project_ref).await?;
+        dt_project.list_tables().await.into_ta_result()
+    }
+
+    #[dtp_command]
+    pub async fn dt_get_table_rows(
+        &self,
+        project_id: Option<i64>,
+        project_path: Option<String>,
+        table: DTProjectTable,
+        skip: Option<i64>,
+        take: Option<i64>,
+    ) -> TAResult<ProjectTablePage> {
+        let project_ref = project_ref(project_id, project_path)?;
+        let dt_project = self.get_db().await?.get_dt_project(project_ref).await?;
+        dt_project
+            .get_table_rows(table, skip.unwrap_or(0), take.unwrap_or(100))
+            .await
+            .into_ta_result()
+    }
+}
+
+fn project_ref(project_id: Option<i64>, project_path: Option<String>) -> TAResult<DtProjectRef> {
+    if let Some(id) = project_id {
+        Ok(DtProjectRef::Id(id))
+    } else if let Some(path) = project_path {
+        Ok(DtProjectRef::Path(path))
+    } else {
+        anyhow::anyhow!("project_id or project_path is required").into_ta_result()
+    }
 }
@@ -1,24 +1,33 @@
 use std::{
     fs,
     sync::{
-        atomic::{AtomicBool, Ordering},
+        atomic::{AtomicBool, AtomicU64, Ordering},
         Arc,
     },
 };
 
 use dtm_macros::{dtm_command, dtp_commands};
+use entity::enums::WatchFolderKind;
 use tauri::{ipc::Channel, State};
 use tokio::sync::{OnceCell, RwLock};
 
 use crate::{
+    bookmarks::{self, PickFolderResult, ResolveResult},
     dtp_service::{
         events::{self, DTPEvent},
-        jobs::{FetchModels, Job, JobContext, ProjectSync, SyncJob, UpdateProjectJob},
+        jobs::{
+            CheckProjectJob, FetchModels, Job, JobContext, ProjectSync, SyncJob,
+            UpdateProjectJob,
+        },
         scheduler::Scheduler,
         watch::WatchService,
         AppHandleWrapper,
     },
-    projects_db::{self, get_last_row, DtmProtocol, ProjectsDb},
+    projects_db::{
+        self, dtos::health::ProjectHealthReport, get_last_row,
+        thumb_cache::DEFAULT_THUMB_CACHE_BUDGET, DtmProtocol, ProjectsDb, ThumbCache,
+    },
+    tensor_cache::{TensorCacheStats, TENSOR_CACHE},
     IntoTAResult,
 };
 
@@ -31,6 +40,7 @@ pub struct DTPService {
     pub watch: Arc<RwLock<Option<WatchService>>>,
     dtm_protocol: Arc<OnceCell<DtmProtocol>>,
     pub auto_watch: Arc<AtomicBool>,
+    thumb_cache_budget: Arc<AtomicU64>,
 }
 
 #[dtp_commands]
@@ -50,6 +60,7 @@ impl DTPService {
             watch,
             dtm_protocol,
             auto_watch: Arc::new(AtomicBool::new(false)),
+            thumb_cache_budget: Arc::new(AtomicU64::new(DEFAULT_THUMB_CACHE_BUDGET)),
         }
     }
 
@@ -59,8 +70,44 @@ impl DTPService {
         auto_watch: bool,
         db_path: String,
     ) -> anyhow::Result<()> {
-        self.auto_watch.store(auto_watch, Ordering::Relaxed);
         let pdb = ProjectsDb::new(&db_path).await?;
+        self.start(channel, auto_watch, pdb).await
+    }
+
+    /// Connects to a portable library stored in `library_dir`, replacing the current
+    /// connection if there is one. The library folder is added as a watch folder the first
+    /// time it is opened.
+    pub async fn connect_portable(
+        &self,
+        channel: Channel<DTPEvent>,
+        auto_watch: bool,
+        library_dir: String,
+    ) -> anyhow::Result<()> {
+        if self.scheduler.read().await.is_some() {
+            self.stop().await;
+        }
+
+        let pdb = ProjectsDb::new_portable(&library_dir).await?;
+        if pdb.list_watch_folders().await?.is_empty() {
+            let root = pdb.from_library_path("");
+            pdb.add_watch_folder(&root, "", true, WatchFolderKind::Projects)
+                .await?;
+        }
+
+        self.start(channel, auto_watch, pdb).await
+    }
+
+    async fn start(
+        &self,
+        channel: Channel<DTPEvent>,
+        auto_watch: bool,
+        pdb: ProjectsDb,
+    ) -> anyhow::Result<()> {
+        self.auto_watch.store(auto_watch, Ordering::Relaxed);
+        pdb.clear_project_path_cache();
+        if let Some(protocol) = self.dtm_protocol.get() {
+            protocol.clear_fingerprints();
+        }
         {
             let mut guard = self.pdb.write().await;
             *guard = Some(pdb.clone());
@@ -106,10 +153,65 @@ impl DTPService {
 
     pub async fn dtm_protocol(&self) -> &DtmProtocol {
         self.dtm_protocol
-            .get_or_init(|| async { DtmProtocol::new(self.get_db().await.unwrap()) })
+            .get_or_init(|| async {
+                // the protocol still works without a cache, just more slowly
+                let budget = self.thumb_cache_budget.load(Ordering::Relaxed);
+                // opening reads the whole cache directory
+                let cache = match self.app_handle.get_app_cache_dir() {
+                    Ok(dir) => tokio::task::spawn_blocking(move || {
+                        ThumbCache::open(dir.join("thumbs"), budget)
+                    })
+                    .await
+                    .unwrap_or_else(|e| Err(e.into())),
+                    Err(e) => Err(e.into()),
+                };
+                let cache = cache
+                    .inspect_err(|e| log::warn!("Thumbnail cache unavailable: {}", e))
+                    .ok();
+                DtmProtocol::new(cache)
+            })
             .await
     }
 
+    /// Drops the cached dtm:// responses of removed projects
+    pub async fn invalidate_thumbs(&self, project_ids: &[i64]) {
+        if let Some(protocol) = self.dtm_protocol.get() {
+            for project_id in project_ids {
+                protocol.invalidate_project(*project_id).await;
+            }
+        }
+    }
+
+    /// Sets the byte budget of the dtm:// thumbnail cache, evicting entries if needed
+    #[dtp_command]
+    pub async fn set_thumb_cache_budget(&self, bytes: u64) -> crate::TAResult<()> {
+        self.thumb_cache_budget.store(bytes, Ordering::Relaxed);
+        if let Some(cache) = self.dtm_protocol.get().and_then(|p| p.cache()) {
+            cache.set_budget(bytes).await;
+        }
+        Ok(())
+    }
+
+    #[dtp_command]
+    pub async fn clear_thumb_cache(&self) -> crate::TAResult<()> {
+        if let Some(cache) = self.dtm_protocol.get().and_then(|p| p.cache()) {
+            cache.clear().await;
+        }
+        Ok(())
+    }
+
+    /// Sets the memory budget for decoded tensors, evicting tensors if needed
+    #[dtp_command]
+    pub async fn set_tensor_cache_budget(&self, bytes: u64) -> crate::TAResult<()> {
+        TENSOR_CACHE.set_budget(bytes);
+        Ok(())
+    }
+
+    #[dtp_command]
+    pub async fn get_tensor_cache_stats(&self) -> crate::TAResult<TensorCacheStats> {
+        Ok(TENSOR_CACHE.stats())
+    }
+
     #[dtp_command]
     pub async fn sync(&self) -> crate::TAResult<()> {
         let scheduler = self.scheduler.read().await;
@@ -160,6 +262,38 @@ impl DTPService {
         Ok(())
     }
 
+    /// Checks every history node of a project, and the tensors, thumbnails and clips they
+    /// reference, without writing to the project. Progress is reported with
+    /// `project_check_progress` events. Like `sync_projects_and_wait`, the check goes to the
+    /// front of the scheduler queue, and this resolves with the report once it finishes.
+    #[dtp_command]
+    pub async fn check_project(&self, project_id: i64) -> crate::TAResult<ProjectHealthReport> {
+        let project = self
+            .get_db()
+            .await?
+            .get_project(project_id)
+            .await
+            .map_err(anyhow::Error::msg)?;
+        let scheduler = self
+            .scheduler
+            .read()
+            .await
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("Scheduler not ready"))?;
+
+        let job = CheckProjectJob::new(project_id, project.full_path);
+        let report = job.report.clone();
+        scheduler
+            .add_job_front_and_wait(job)
+            .await
+            .map_err(|e| anyhow::anyhow!(e))?;
+
+        let report = report.lock().unwrap().take();
+        report
+            .ok_or_else(|| anyhow::anyhow!("Project check did not complete"))
+            .into_ta_result()
+    }
+
     // test to compare checking rowid vs file metadata
     pub async fn check_all(&self) -> anyhow::Result<()> {
         let start = std::time::Instant::now();
@@ -254,7 +388,15 @@ impl DTPService {
         let db = self.get_db().await?;
         let folders = db.list_watch_folders().await.into_ta_result()?;
         let ids = folders.iter().map(|f| f.id).collect::<Vec<i64>>();
+        let project_ids: Vec<i64> = db
+            .list_projects(None)
+            .await
+            .into_ta_result()?
+            .iter()
+            .map(|p| p.id)
+            .collect();
         db.remove_watch_folders(ids).await.into_ta_result()?;
+        self.invalidate_thumbs(&project_ids).await;
         Ok(())
     }
 }
@@ -285,6 +427,46 @@ pub async fn dtp_connect(
     Ok(())
 }
 
+/// Opens a portable library. When `bookmark` is None the user is asked to pick the library
+/// folder. The result is returned so the frontend can reopen the same library next launch.
+#[dtm_command]
+pub async fn dtp_connect_portable(
+    app_handle: State<'_, AppHandleWrapper>,
+    state: State<'_, DTPService>,
+    channel: Channel<DTPEvent>,
+    auto_watch: bool,
+    bookmark: Option<String>,
+) -> crate::TAResult<PickFolderResult> {
+    let bookmark = match bookmark {
+        Some(bookmark) => bookmark,
+        None => {
+            bookmarks::pick_folder(&app_handle, None, Some("Select Library Folder".to_string()))
+                .await?
+                .ok_or_else(|| anyhow::anyhow!("Failed to select a folder"))?
+                .bookmark
+        }
+    };
+
+    let result = match bookmarks::resolve_bookmark_impl(bookmark.clone()).await? {
+        ResolveResult::Resolved(path) => PickFolderResult { path, bookmark },
+        ResolveResult::StaleRefreshed {
+            new_bookmark,
+            resolved_path,
+        } => PickFolderResult {
+            path: resolved_path,
+            bookmark: new_bookmark,
+        },
+        ResolveResult::CannotResolve => {
+            return Err(anyhow::anyhow!("Library folder could not be found").into())
+        }
+    };
+
+    state
+        .connect_portable(channel, auto_watch, result.path.clone())
+        .await?;
+    Ok(result)
+}
+
 #[cfg(dev)]
 const PROJECT_FILE_NAME: &str = "projects4-dev.db";
 #[cfg(not(dev))]
@@ -57,6 +57,10 @@ pub enum DTPEvent {
     ProjectSyncStarted(i64),
     ProjectSyncComplete(i64),
 
+    ProjectCheckStarted(i64),
+    ProjectCheckProgress(ProjectCheckProgress),
+    ProjectCheckComplete(i64),
+
     DtpServiceReady,
 
     /// By default, tuple is (job id, msg)
@@ -74,3 +78,10 @@ pub struct ScanProgress {
     pub images_found: u64,
     pub images_scanned: u64,
 }
+
+#[derive(serde::Serialize, Debug)]
+pub struct ProjectCheckProgress {
+    pub project_id: i64,
+    pub nodes_checked: u64,
+    pub nodes_total: u64,
+}
@@ -13,13 +13,19 @@ use crate::IntoTAResult;
 use crate::{
     dtp_service::{AppHandleWrapper, DTPService},
     projects_db::{
-        decode_tensor,
+        add_a1111_parameters, encode_tensor_data,
         dt_project::{TensorHistoryNode, ThnData, ThnFilter},
-        dtos::image::{ImageExtra, ListImagesOptions},
-        write_jpeg_with_metadata, DecodeTensorOptions, DtProjectRef, DtResourceHandle,
-        DtResourceRef,
+        dtos::{
+            export::{MetadataPrivacy, TensorFileFormat, TensorImageFormat},
+            image::{ImageExtra, ListImagesOptions},
+            index::IndexExportFormat,
+        },
+        filters::ListImagesFilter,
+        inpaint::InpaintViewOptions,
+        write_export_metadata, DecodeTensorOptions, DtProjectRef,
+        DtResourceHandle, DtResourceRef,
     },
-    ResourceHandle,
+    ResizeOptions, ResourceHandle,
 };
 
 #[derive(Debug, Deserialize)]
@@ -27,6 +33,32 @@ use crate::{
 pub struct ProjectExportOptions {
     pub output_folder: String,
     pub use_tensor: bool,
+    /// file format for images decoded from tensors, when `use_tensor` is set
+    #[serde(default)]
+    pub format: TensorImageFormat,
+    /// resizes images decoded from tensors, when `use_tensor` is set
+    #[serde(default)]
+    pub resize: Option<ResizeOptions>,
+    /// also write an A1111 style `parameters` string, for Civitai and other A1111-aware tools
+    #[serde(default)]
+    pub a1111_parameters: bool,
+    /// strips or redacts the metadata written to each image
+    #[serde(default)]
+    pub privacy: MetadataPrivacy,
+}
+
+/// Selection and format for `export_index`. The selection fields match `list_images`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexExportOptions {
+    pub output_folder: String,
+    pub format: IndexExportFormat,
+    pub project_ids: Option<Vec<i64>>,
+    pub search: Option<String>,
+    pub filters: Option<Vec<ListImagesFilter>>,
+    pub show_video: Option<bool>,
+    pub show_image: Option<bool>,
+    pub show_disconnected: Option<bool>,
 }
 
 #[derive(Clone, Serialize, Debug)]
@@ -125,6 +157,10 @@ impl DTPService {
                 let temp_dir = temp_dir.clone();
                 let project_name = project.name.clone();
                 let use_tensor = options.use_tensor;
+                let image_format = options.format;
+                let resize = options.resize;
+                let a1111_parameters = options.a1111_parameters;
+                let privacy = options.privacy;
                 let filename_base = make_filename(index, index_width, &image);
                 let project_id = *project_id;
 
@@ -146,7 +182,7 @@ impl DTPService {
                             return Ok(());
                         }
                     };
-                    let node_data = node.node_data();
+                    let node_data = privacy.apply(node.node_data());
 
                     if use_tensor {
                         // full quality: decode the generated tensor to png, embedding metadata
@@ -157,18 +193,32 @@ impl DTPService {
                                 return Ok(());
                             }
                         };
-                        let tensor = dt_project.get_tensor_raw(&name).await?;
-                        let path = temp_dir.join(format!("{}.png", filename_base));
+                        // decoded through the tensor cache, which the detail view may have filled
+                        let tensor = dt_project.get_tensor_decoded(&name).await?;
+                        let file_name = format!("{}.{}", filename_base, image_format.extension());
+                        let path = temp_dir.join(file_name);
                         tokio::task::spawn_blocking(move || -> anyhow::Result<()> {
-                            let png = decode_tensor(
-                                tensor,
+                            let data = tensor
+                                .as_f32()
+                                .ok_or_else(|| anyhow::anyhow!("{} is not an image tensor", name))?;
+                            let mut image = encode_tensor_data(
+                                data,
+                                tensor.width,
+                                tensor.height,
+                                tensor.channels as usize,
                                 DecodeTensorOptions {
                                     as_png: true,
-                                    history_node: Some(node_data),
-                                    size: None,
+                                    history_node: node_data.clone(),
+                                    resize,
+                                    format: image_format,
                                 },
                             )?;
-                            fs::write(path, png).map_err(anyhow::Error::from)
+                            // tiff and exr are written without metadata
+                            let a1111_parameters = a1111_parameters && image_format.is_png();
+                            if let (true, Some(node_data)) = (a1111_parameters, &node_data) {
+                                image = add_a1111_parameters(&image, node_data)?;
+                            }
+                            fs::write(path, image).map_err(anyhow::Error::from)
                         })
                         .await??;
                     } else {
@@ -183,7 +233,8 @@ impl DTPService {
                             .ok_or_else(|| anyhow::anyhow!("Failed to get preview"))?;
                         let path = temp_dir.join(format!("{}.jpg", filename_base));
                         tokio::task::spawn_blocking(move || -> anyhow::Result<()> {
-                            let jpg = write_jpeg_with_metadata(&jpg, &node_data)?;
+                            let jpg =
+                                write_export_metadata(&jpg, node_data.as_ref(), a1111_parameters)?;
                             fs::write(path, jpg).map_err(anyhow::Error::from)
                         })
                         .await??;
@@ -218,6 +269,99 @@ impl DTPService {
 
         Ok(zip_paths)
     }
+
+    /// Exports one row per matching image (with model, lora and control names) as csv,
+    /// json lines or a standalone sqlite file. Returns the path of the written file.
+    #[dtp_command]
+    pub async fn export_index(&self, options: IndexExportOptions) -> crate::TAResult<String> {
+        let db = self.get_db().await?;
+
+        let output_folder = PathBuf::from(&options.output_folder);
+        tokio::fs::create_dir_all(&output_folder)
+            .await
+            .into_ta_result()?;
+        let path = unique_path(&output_folder, "dtm-index", options.format.extension());
+
+        let opts = ListImagesOptions {
+            project_ids: options.project_ids,
+            search: options.search,
+            filters: options.filters,
+            show_video: options.show_video,
+            show_image: options.show_image,
+            show_disconnected: options.show_disconnected,
+            ..Default::default()
+        };
+
+        let app_handle = self.app_handle.clone();
+        db.export_index(&opts, options.format, &path, |current, total| {
+            emit_event_progress(
+                &app_handle,
+                "export_index_progress",
+                current as usize,
+                total as usize,
+                "Exporting index…",
+            );
+        })
+        .await
+        .into_ta_result()?;
+
+        Ok(path.to_string_lossy().into_owned())
+    }
+
+    /// Writes a tensor, decompressed but otherwise as stored in the project, to `.npy` or
+    /// `.safetensors`. With `node_id`, the tensor is looked up through that node. Returns the
+    /// path of the written file, which is named after the tensor.
+    #[dtp_command]
+    pub async fn export_tensor_file(
+        &self,
+        project_id: i64,
+        node_id: Option<i64>,
+        tensor_id: String,
+        format: TensorFileFormat,
+        output_folder: String,
+    ) -> crate::TAResult<String> {
+        let project_ref = DtProjectRef::Id(project_id);
+        let handle = match node_id {
+            Some(node_id) => project_ref.node(node_id).sub()?.tensor(&tensor_id),
+            None => project_ref.tensor(&tensor_id),
+        };
+        let (name, file) = handle
+            .get_tensor_file(format)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Tensor {} not found", tensor_id))?;
+
+        let output_folder = PathBuf::from(&output_folder);
+        fs::create_dir_all(&output_folder).into_ta_result()?;
+        let path = unique_path(&output_folder, &name, format.extension());
+        fs::write(&path, file).into_ta_result()?;
+
+        Ok(path.to_string_lossy().into_owned())
+    }
+
+    /// Writes the before/after view of an inpainting node (source, masked source and result,
+    /// side by side) as a png. Returns the path of the written file.
+    #[dtp_command]
+    pub async fn export_inpaint_view(
+        &self,
+        project_id: i64,
+        node_id: i64,
+        output_folder: String,
+        options: Option<InpaintViewOptions>,
+    ) -> crate::TAResult<String> {
+        let png = DtProjectRef::Id(project_id)
+            .node(node_id)
+            .get_inpaint_png(options.unwrap_or_default(), None)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Node {} is not an inpainting pass", node_id))?;
+
+        let output_folder = PathBuf::from(&output_folder);
+        fs::create_dir_all(&output_folder).into_ta_result()?;
+        let stem = format!("inpaint_{}_{}", project_id, node_id);
+        let path = unique_path(&output_folder, &stem, "png");
+        fs::write(&path, png).into_ta_result()?;
+
+        Ok(path.to_string_lossy().into_owned())
+    }
 }
 
 /// Returns a path inside `dir` for `stem.ext` that does not already exist,
@@ -233,9 +377,19 @@ fn unique_path(dir: &Path, stem: &str, ext: &str) -> PathBuf {
 }
 
 fn emit_progress(app: &AppHandleWrapper, current: usize, total: usize, msg: &str) {
+    emit_event_progress(app, "export_projects_progress", current, total, msg);
+}
+
+fn emit_event_progress(
+    app: &AppHandleWrapper,
+    event: &str,
+    current: usize,
+    total: usize,
+    msg: &str,
+) {
     if let Some(handle) = &app.app_handle {
         let _ = handle.emit(
-            "export_projects_progress",
+            event,
             ExportProgress {
                 current,
                 total,
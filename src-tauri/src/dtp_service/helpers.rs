@@ -8,6 +8,8 @@ use walkdir::WalkDir;
 use crate::projects_db::dtos::model::ModelType;
 use crate::projects_db::dtos::project::ProjectExtra;
 use crate::projects_db::folder_cache;
+use crate::projects_db::image_metadata::is_image_file;
+use entity::enums::WatchFolderKind;
 
 #[derive(Debug, Clone)]
 pub struct ProjectFile {
@@ -23,10 +25,23 @@ pub struct GetFolderFilesResult {
     pub model_info: Vec<(String, ModelType)>,
 }
 
-pub async fn get_folder_files(watchfolder_path: &str, watchfolder_id: i64) -> GetFolderFilesResult {
+pub async fn get_folder_files(
+    watchfolder_path: &str,
+    watchfolder_id: i64,
+    kind: WatchFolderKind,
+) -> GetFolderFilesResult {
     let mut projects: HashMap<String, ProjectFile> = HashMap::new();
     let mut model_info: Vec<(String, ModelType)> = Vec::new();
 
+    if kind == WatchFolderKind::LooseImages {
+        let folder = get_loose_images_folder(watchfolder_path, watchfolder_id);
+        projects.insert(watchfolder_path.to_string(), folder);
+        return GetFolderFilesResult {
+            projects,
+            model_info,
+        };
+    }
+
     // Walk the folder recursively
     for entry in WalkDir::new(watchfolder_path)
         .follow_links(false)
@@ -106,6 +121,41 @@ pub async fn get_folder_files(watchfolder_path: &str, watchfolder_id: i64) -> Ge
     }
 }
 
+/// A loose images folder is a single project, with an empty path. Its size and modified time
+/// cover all of its images, and its directories, since renaming or removing a file changes
+/// the modified time of the directory it was in.
+pub fn get_loose_images_folder(watchfolder_path: &str, watchfolder_id: i64) -> ProjectFile {
+    let mut folder = ProjectFile {
+        path: String::new(),
+        has_base: true,
+        filesize: 0,
+        modified: 0,
+        _watchfolder_id: watchfolder_id,
+    };
+
+    for entry in WalkDir::new(watchfolder_path)
+        .follow_links(false)
+        .into_iter()
+        .filter_map(Result::ok)
+    {
+        let is_dir = entry.file_type().is_dir();
+        if !is_dir && !is_image_file(entry.path()) {
+            continue;
+        }
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        if !is_dir {
+            folder.filesize += metadata.len();
+        }
+        if let Some(modified) = metadata.modified().ok().and_then(system_time_to_epoch_secs) {
+            folder.modified = folder.modified.max(modified);
+        }
+    }
+
+    folder
+}
+
 pub fn get_project_path(full_path: String, watchfolder_path: &str) -> String {
     let path = PathBuf::from(full_path);
     path.strip_prefix(watchfolder_path)
@@ -117,6 +167,9 @@ pub fn get_project_path(full_path: String, watchfolder_path: &str) -> String {
 
 pub fn get_full_project_path(project: &ProjectExtra) -> String {
     let folder = folder_cache::get_folder(project.watchfolder_id).unwrap();
+    if project.is_loose_images() {
+        return folder;
+    }
     let path = PathBuf::from(folder)
         .join(project.path.to_string())
         .with_extension("sqlite3");
@@ -173,6 +226,14 @@ impl AppHandleWrapper {
             Ok(self.get_test_path("app_data_dir"))
         }
     }
+
+    pub fn get_app_cache_dir(&self) -> tauri::Result<PathBuf> {
+        if let Some(app_handle) = &self.app_handle {
+            app_handle.path().app_cache_dir()
+        } else {
+            Ok(self.get_test_path("app_cache_dir"))
+        }
+    }
 }
 
 impl From<AppHandle> for AppHandleWrapper {
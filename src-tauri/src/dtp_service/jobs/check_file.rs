@@ -1,8 +1,13 @@
 use std::{fs, sync::Arc};
 
-use crate::dtp_service::{
-    helpers::system_time_to_epoch_secs,
-    jobs::{AddProjectJob, Job, JobContext, JobResult, RemoveProjectJob, UpdateProjectJob},
+use entity::enums::WatchFolderKind;
+
+use crate::{
+    dtp_service::{
+        helpers::{get_loose_images_folder, system_time_to_epoch_secs},
+        jobs::{AddProjectJob, Job, JobContext, JobResult, RemoveProjectJob, UpdateProjectJob},
+    },
+    projects_db::image_metadata::is_image_file,
 };
 
 pub struct CheckFileJob {
@@ -31,6 +36,44 @@ impl Job for CheckFileJob {
             return Err("Watch folder not found".to_string());
         }
         let watchfolder = watchfolder.unwrap();
+
+        // loose images folders only index images, and project folders never do
+        let is_loose_images = watchfolder.kind == WatchFolderKind::LooseImages;
+        if is_loose_images != is_image_file(&self.project_path) {
+            return Ok(JobResult::None);
+        }
+
+        // a loose images folder is a single project, and scanning it only reads changed files
+        if is_loose_images {
+            let (path, id) = (watchfolder.path.clone(), watchfolder.id);
+            let folder = tokio::task::spawn_blocking(move || get_loose_images_folder(&path, id))
+                .await
+                .map_err(|e| e.to_string())?;
+            let entity = ctx
+                .pdb
+                .get_project_by_path(watchfolder.id, &folder.path)
+                .await
+                .map_err(|e| e.to_string())?;
+            let job: Arc<dyn Job> = match entity {
+                Some(entity) => Arc::new(UpdateProjectJob {
+                    project_id: entity.id,
+                    filesize: folder.filesize as i64,
+                    modified: folder.modified,
+                    is_import: false,
+                    check_deletions: false,
+                    project_path: entity.full_path.clone(),
+                }),
+                None => Arc::new(AddProjectJob {
+                    path: folder.path,
+                    watchfolder_id: watchfolder.id,
+                    filesize: folder.filesize as i64,
+                    modified: folder.modified,
+                    is_import: false,
+                }),
+            };
+            return Ok(JobResult::Subtasks(vec![job]));
+        }
+
         let project_path = self
             .project_path
             .strip_prefix(format!("{}/", watchfolder.path).as_str())
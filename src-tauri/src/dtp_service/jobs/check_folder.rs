@@ -151,7 +151,7 @@ async fn resolve_folder(folder: &WatchFolderDTO, db: &ProjectsDb) -> Result<bool
             return Ok(true);
         }
     }
-    let resolved = folder_cache::resolve_bookmark(folder.id, &folder.bookmark).await;
+    let resolved = db.resolve_watch_folder(folder).await;
     if let Ok(resolved) = resolved {
         match resolved {
             crate::bookmarks::ResolveResult::Resolved(updated_path) => {
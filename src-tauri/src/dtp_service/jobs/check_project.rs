@@ -0,0 +1,77 @@
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    dtp_service::{
+        events::{DTPEvent, ProjectCheckProgress},
+        jobs::{project_jobs::update_compatibility, Job, JobContext, JobResult},
+    },
+    projects_db::{dtos::health::ProjectHealthReport, DTProject},
+};
+
+/// Checks a project for corrupt or missing data. The file is opened read-only, outside of
+/// the shared project cache, and the report is left in `report` when the job completes.
+/// The project's compatibility report is refreshed as well.
+pub struct CheckProjectJob {
+    pub project_id: i64,
+    pub project_path: String,
+    pub report: Arc<Mutex<Option<ProjectHealthReport>>>,
+}
+
+impl CheckProjectJob {
+    pub fn new(project_id: i64, project_path: String) -> Self {
+        Self {
+            project_id,
+            project_path,
+            report: Arc::new(Mutex::new(None)),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Job for CheckProjectJob {
+    fn get_label(&self) -> String {
+        format!("CheckProjectJob for {}", self.project_id)
+    }
+
+    fn start_event(self: &Self) -> Option<DTPEvent> {
+        Some(DTPEvent::ProjectCheckStarted(self.project_id))
+    }
+
+    async fn execute(self: &Self, ctx: &JobContext) -> Result<JobResult, String> {
+        let dt_project = DTProject::open(&self.project_path)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let project_id = self.project_id;
+        let events = ctx.events.clone();
+        let report = dt_project
+            .check_health(|nodes_checked, nodes_total| {
+                events.emit(DTPEvent::ProjectCheckProgress(ProjectCheckProgress {
+                    project_id,
+                    nodes_checked,
+                    nodes_total,
+                }));
+            })
+            .await
+            .map_err(|e| e.to_string())?;
+
+        if !report.healthy {
+            log::warn!(
+                "Project {} failed health check: {} node, {} tensordata, {} tensor, {} thumbnail, {} clip failures",
+                self.project_id,
+                report.nodes.failed,
+                report.tensor_data.failed,
+                report.tensors.failed,
+                report.thumbnails.failed,
+                report.clips.failed
+            );
+        }
+        *self.report.lock().unwrap() = Some(report);
+
+        update_compatibility(ctx, self.project_id, &self.project_path).await;
+
+        Ok(JobResult::Event(DTPEvent::ProjectCheckComplete(
+            self.project_id,
+        )))
+    }
+}
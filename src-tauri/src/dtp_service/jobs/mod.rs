@@ -1,5 +1,6 @@
 mod check_file;
 mod check_folder;
+mod check_project;
 mod job;
 mod maintenance;
 mod project_jobs;
@@ -9,9 +10,10 @@ mod sync_models;
 
 pub use check_file::CheckFileJob;
 pub use check_folder::CheckFolderJob;
+pub use check_project::CheckProjectJob;
 pub use job::{Job, JobContext, JobResult};
 pub use maintenance::MaintenanceTaskKind;
-pub use project_jobs::{AddProjectJob, RemoveProjectJob, UpdateProjectJob};
+pub use project_jobs::{AddProjectJob, MoveProjectJob, RemoveProjectJob, UpdateProjectJob};
 pub use sync::SyncJob;
 pub use sync_folder::{ProjectSync, SyncFolderJob};
 pub use sync_models::{FetchModels, SyncModelsJob};
@@ -1,15 +1,19 @@
 use std::sync::Arc;
 
-use entity::images::Column;
+use entity::{enums::WatchFolderKind, images::Column};
 use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
 
 use crate::{
     dtp_service::{
         dtp_service::get_db_file_path,
         events::{DTPEvent, ScanProgress},
-        jobs::{sync_folder::ProjectSync, Job, JobContext, JobResult},
+        jobs::{
+            sync_folder::{release_move_claim, ProjectSync},
+            Job, JobContext, JobResult,
+        },
     },
-    projects_db::{DTProject, ProjectsDb},
+    projects_db::{dtos::project::CompatibilityReport, DTProject, ProjectsDb},
+    TENSOR_CACHE,
 };
 use anyhow::{Context, Result};
 
@@ -71,6 +75,72 @@ impl Job for AddProjectJob {
     }
 }
 
+/// Updates the path of a project whose file was renamed or moved, then rescans it.
+/// The project keeps its id, so its images are not removed and re-imported.
+pub struct MoveProjectJob {
+    pub project_id: i64,
+    pub path: String,
+    pub watchfolder_id: i64,
+    pub filesize: i64,
+    pub modified: i64,
+    pub is_import: bool,
+}
+
+impl MoveProjectJob {
+    pub fn new(project_sync: &ProjectSync, is_import: bool) -> Result<Self> {
+        let (Some(entity), Some(file)) = (&project_sync.entity, &project_sync.file) else {
+            anyhow::bail!("Project entity and file are required to move a project")
+        };
+        Ok(Self {
+            project_id: entity.id,
+            path: file.path.to_string(),
+            watchfolder_id: project_sync.watchfolder_id,
+            filesize: file.filesize as i64,
+            modified: file.modified,
+            is_import,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl Job for MoveProjectJob {
+    fn get_label(&self) -> String {
+        format!("MoveProjectJob for {} to {}", self.project_id, self.path)
+    }
+
+    async fn execute(self: &Self, ctx: &JobContext) -> Result<JobResult, String> {
+        // tensors are cached by path, so drop the ones under the old path
+        if let Ok(project) = ctx.pdb.get_project(self.project_id).await {
+            TENSOR_CACHE.invalidate_project(&project.full_path);
+        }
+
+        let project = ctx
+            .pdb
+            .move_project(self.project_id, self.watchfolder_id, &self.path)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        log::debug!("Project {} moved to {}", project.id, project.full_path);
+
+        Ok(JobResult::Subtasks(vec![Arc::new(UpdateProjectJob {
+            project_id: project.id,
+            project_path: project.full_path,
+            filesize: self.filesize,
+            modified: self.modified,
+            is_import: self.is_import,
+            check_deletions: false,
+        })]))
+    }
+
+    async fn on_complete(&self, _ctx: &JobContext) {
+        release_move_claim(self.project_id, self.watchfolder_id, &self.path).await;
+    }
+
+    async fn on_failed(&self, _ctx: &JobContext, _error: String) {
+        release_move_claim(self.project_id, self.watchfolder_id, &self.path).await;
+    }
+}
+
 pub struct RemoveProjectJob {
     pub project_id: i64,
 }
@@ -94,11 +164,21 @@ impl Job for RemoveProjectJob {
     }
 
     async fn execute(self: &Self, ctx: &JobContext) -> Result<JobResult, String> {
+        // another folder's sync may have moved the project since this job was created
+        if let Ok(project) = ctx.pdb.get_project(self.project_id).await {
+            if std::path::Path::new(&project.full_path).exists() {
+                log::debug!("Project {} was moved, not removing", self.project_id);
+                return Ok(JobResult::None);
+            }
+            TENSOR_CACHE.invalidate_project(&project.full_path);
+        }
+
         let result = ctx
             .pdb
             .remove_project(self.project_id)
             .await
             .map_err(|e| e.to_string())?;
+        ctx.dtp.invalidate_thumbs(&[self.project_id]).await;
         Ok(JobResult::Event(DTPEvent::ProjectRemoved(result.unwrap())))
     }
 }
@@ -154,13 +234,21 @@ impl Job for UpdateProjectJob {
     }
 
     async fn execute(self: &Self, ctx: &JobContext) -> Result<JobResult, String> {
+        // the project file changed, so its cached tensors may be stale
+        TENSOR_CACHE.invalidate_project(&self.project_path);
+
         let scan_result: Result<(i64, u64), String> = ctx
             .pdb
             .scan_project(self.project_id, false)
             .await
             .map_err(|e| e.to_string());
 
-        if self.check_deletions {
+        // loose images folders remove their missing files when scanned
+        let is_loose_images = match ctx.pdb.get_project_location(self.project_id).await {
+            Ok((_, kind)) => kind == WatchFolderKind::LooseImages,
+            Err(_) => false,
+        };
+        if self.check_deletions && !is_loose_images {
             check_deletions(&ctx, self.project_id, &self.project_path)
                 .await
                 .map_err(|e| e.to_string())?;
@@ -168,7 +256,17 @@ impl Job for UpdateProjectJob {
 
         let result = match scan_result {
             Ok((_id, total)) => {
-                let project = ctx.pdb.get_project(_id).await.map_err(|e| e.to_string())?;
+                let mut project = ctx.pdb.get_project(_id).await.map_err(|e| e.to_string())?;
+
+                // only the latest rows are sampled, so the check is cheap enough to repeat
+                // whenever the project changes, and catches rows written by a newer Draw Things
+                if !project.is_loose_images() {
+                    if let Some(report) =
+                        update_compatibility(ctx, self.project_id, &self.project_path).await
+                    {
+                        project.compatibility = Some(report);
+                    }
+                }
 
                 let _ = ctx
                     .pdb
@@ -200,12 +298,50 @@ impl Job for UpdateProjectJob {
     }
 }
 
+/// Records the project's compatibility report. A failed check is logged rather than failing
+/// the update, since the images have already been scanned.
+pub(crate) async fn update_compatibility(
+    ctx: &JobContext,
+    project_id: i64,
+    project_path: &str,
+) -> Option<CompatibilityReport> {
+    let result = async {
+        let report = DTProject::get(project_path)
+            .await?
+            .check_compatibility()
+            .await?;
+        if !report.compatible {
+            log::warn!("Project {} has unknown schema: {:?}", project_id, report);
+        }
+        ctx.pdb
+            .update_project_compatibility(project_id, &report)
+            .await?;
+        anyhow::Ok(report)
+    }
+    .await;
+
+    match result {
+        Ok(report) => Some(report),
+        Err(e) => {
+            log::error!(
+                "Error checking compatibility of project {}: {}",
+                project_id,
+                e
+            );
+            None
+        }
+    }
+}
+
 async fn check_deletions(
     ctx: &JobContext,
     project_id: i64,
     project_path: &str,
 ) -> Result<()> {
-    let pdb_path = get_db_file_path(&ctx.app_handle);
+    let pdb_path = match ctx.pdb.library_file() {
+        Some(library_file) => library_file.to_string_lossy().to_string(),
+        None => get_db_file_path(&ctx.app_handle),
+    };
 
     let dt_project = DTProject::open(project_path).await?;
 
@@ -1,30 +1,57 @@
-use std::sync::{
-    atomic::{AtomicBool, Ordering},
-    Arc,
+use std::{
+    collections::HashSet,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
 };
 
-use tokio::fs;
+use once_cell::sync::Lazy;
+use tokio::{fs, sync::Mutex};
 
 use crate::{
     dtp_service::{
         events::DTPEvent,
         helpers::{
-            get_folder_files, get_full_project_path, system_time_to_epoch_secs, ProjectFile,
+            get_folder_files, get_full_project_path, get_loose_images_folder,
+            system_time_to_epoch_secs, ProjectFile,
         },
         jobs::{
-            AddProjectJob, Job, JobContext, JobResult, RemoveProjectJob, SyncModelsJob,
-            UpdateProjectJob,
+            AddProjectJob, Job, JobContext, JobResult, MoveProjectJob, RemoveProjectJob,
+            SyncModelsJob, UpdateProjectJob,
         },
     },
     projects_db::{
         dtos::{project::ProjectExtra, watch_folder::WatchFolderDTO},
-        ProjectsDb,
+        folder_cache, DTProject, ProjectsDb,
     },
 };
+use entity::enums::WatchFolderKind;
+
+/// Projects and files paired up by a move that hasn't completed yet. Folders are synced
+/// concurrently, and both the folder a project left and the folder it arrived in can detect
+/// the move, so detection runs one folder at a time and skips whatever is already claimed.
+#[derive(Default)]
+struct MoveClaims {
+    projects: HashSet<i64>,
+    /// watch folder id and path of the files projects are moving to
+    files: HashSet<(i64, String)>,
+}
+
+static MOVE_CLAIMS: Lazy<Mutex<MoveClaims>> = Lazy::new(Default::default);
+
+/// Called when a move completes or fails
+pub async fn release_move_claim(project_id: i64, watchfolder_id: i64, path: &str) {
+    let mut claims = MOVE_CLAIMS.lock().await;
+    claims.projects.remove(&project_id);
+    claims.files.remove(&(watchfolder_id, path.to_string()));
+}
 
 pub struct SyncFolderJob {
     pub watchfolder_id: i64,
     pub watchfolder_path: String,
+    pub kind: WatchFolderKind,
     pub is_import: Arc<AtomicBool>,
 }
 
@@ -33,9 +60,181 @@ impl SyncFolderJob {
         Self {
             watchfolder_id: watchfolder.id,
             watchfolder_path: watchfolder.path.clone(),
+            kind: watchfolder.kind,
             is_import: Arc::new(AtomicBool::new(false)),
         }
     }
+
+    /// A renamed or moved project shows up as a removed project plus a new file. Pairs them
+    /// up so the project can be updated in place instead of being removed and re-imported.
+    /// Projects can move within this folder or to/from other watch folders.
+    async fn detect_moves(
+        &self,
+        ctx: &JobContext,
+        sync_projects: &mut Vec<ProjectSync>,
+    ) -> anyhow::Result<()> {
+        // held until the moves found here are claimed
+        let mut claims = MOVE_CLAIMS.lock().await;
+
+        // another folder's sync is already moving these
+        for sync in sync_projects.iter_mut() {
+            let is_claimed = match (&sync.action, &sync.entity, &sync.file) {
+                (SyncAction::Remove, Some(entity), _) => claims.projects.contains(&entity.id),
+                (SyncAction::Add, _, Some(file)) => claims
+                    .files
+                    .contains(&(sync.watchfolder_id, file.path.clone())),
+                _ => false,
+            };
+            if is_claimed {
+                sync.action = SyncAction::None;
+            }
+        }
+
+        // projects whose file is gone, with their index in sync_projects if from this folder
+        let mut orphans: Vec<(ProjectExtra, Option<usize>)> = sync_projects
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| s.action == SyncAction::Remove)
+            .map(|(i, s)| (s.entity.clone().unwrap(), Some(i)))
+            .collect();
+        let has_local_orphans = !orphans.is_empty();
+        let has_local_adds = sync_projects.iter().any(|s| s.action == SyncAction::Add);
+
+        // projects from other folders can only have moved here if there are new files
+        if has_local_adds {
+            for project in ctx.pdb.list_projects(None).await? {
+                if project.watchfolder_id == self.watchfolder_id
+                    || project.is_loose_images()
+                    || project.is_missing
+                    || project.is_locked
+                    || claims.projects.contains(&project.id)
+                {
+                    continue;
+                }
+                // if the watch folder itself is unavailable the project isn't really gone
+                let folder_exists = folder_cache::get_folder(project.watchfolder_id)
+                    .map_or(false, |folder| Path::new(&folder).exists());
+                if folder_exists && !Path::new(&project.full_path).exists() {
+                    orphans.push((project, None));
+                }
+            }
+        }
+
+        if orphans.is_empty() {
+            return Ok(());
+        }
+
+        // a moved file keeps its size and modified time, so only files matching an orphan
+        // are opened to compare fingerprints
+        let matches_orphan = |file: &ProjectFile| {
+            orphans.iter().any(|(orphan, _)| {
+                orphan.filesize == Some(file.filesize as i64)
+                    && orphan.modified == Some(file.modified)
+            })
+        };
+
+        // new files in this folder, plus unindexed files in other folders when projects have
+        // gone missing from this one
+        let mut candidates: Vec<(ProjectSync, Option<usize>)> = sync_projects
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| {
+                s.action == SyncAction::Add && s.file.as_ref().is_some_and(matches_orphan)
+            })
+            .map(|(i, s)| (s.clone(), Some(i)))
+            .collect();
+
+        if has_local_orphans {
+            for folder in ctx.pdb.list_watch_folders().await? {
+                if folder.id == self.watchfolder_id
+                    || folder.kind != WatchFolderKind::Projects
+                    || folder.is_missing
+                    || folder.is_locked
+                {
+                    continue;
+                }
+                let Some(folder_path) = folder_cache::get_folder(folder.id) else {
+                    continue;
+                };
+                let indexed: HashSet<String> = ctx
+                    .pdb
+                    .list_projects(Some(folder.id))
+                    .await?
+                    .into_iter()
+                    .map(|p| p.path)
+                    .collect();
+                let files = get_folder_files(&folder_path, folder.id, folder.kind).await;
+                for (_key, file) in files.projects {
+                    if !indexed.contains(&file.path)
+                        && !claims.files.contains(&(folder.id, file.path.clone()))
+                        && matches_orphan(&file)
+                    {
+                        let sync =
+                            ProjectSync::new(None, Some(file), folder.id, folder_path.clone());
+                        candidates.push((sync, None));
+                    }
+                }
+            }
+        }
+
+        for (mut candidate, index) in candidates {
+            let file = candidate.file.as_ref().unwrap();
+            let full_path = Path::new(&candidate.watchfolder_path).join(&file.path);
+            let Ok(dt_project) = DTProject::get(&full_path.to_string_lossy()).await else {
+                continue;
+            };
+            let Ok(fingerprint) = dt_project.get_fingerprint().await else {
+                continue;
+            };
+
+            let mut matched: Option<usize> = None;
+            for (i, (orphan, _)) in orphans.iter().enumerate() {
+                if orphan.filesize == Some(file.filesize as i64)
+                    && orphan.modified == Some(file.modified)
+                    && orphan.fingerprint == fingerprint
+                    && ctx
+                        .pdb
+                        .is_same_project(orphan, &dt_project, &fingerprint)
+                        .await?
+                {
+                    matched = Some(i);
+                    break;
+                }
+            }
+            let Some(matched) = matched else {
+                continue;
+            };
+
+            let (orphan, orphan_index) = orphans.remove(matched);
+            log::debug!(
+                "Project {} moved from {} to {}",
+                orphan.id,
+                orphan.full_path,
+                full_path.display()
+            );
+
+            if let Some(orphan_index) = orphan_index {
+                sync_projects[orphan_index].action = SyncAction::None;
+            }
+            claims.projects.insert(orphan.id);
+            claims
+                .files
+                .insert((candidate.watchfolder_id, file.path.clone()));
+
+            candidate.entity = Some(orphan);
+            candidate.action = SyncAction::Move;
+            match index {
+                Some(index) => sync_projects[index] = candidate,
+                None => sync_projects.push(candidate),
+            }
+
+            if orphans.is_empty() {
+                break;
+            }
+        }
+
+        Ok(())
+    }
 }
 
 #[async_trait::async_trait]
@@ -50,7 +249,8 @@ impl Job for SyncFolderJob {
         Some(DTPEvent::FolderSyncStarted(self.watchfolder_id))
     }
     async fn execute(self: &Self, ctx: &JobContext) -> Result<JobResult, String> {
-        let files = get_folder_files(&self.watchfolder_path, self.watchfolder_id).await;
+        let files =
+            get_folder_files(&self.watchfolder_path, self.watchfolder_id, self.kind).await;
         let mut project_files = files.projects;
         let mut sync_projects: Vec<ProjectSync> = Vec::new();
         let entities = ctx
@@ -89,11 +289,19 @@ impl Job for SyncFolderJob {
             sync_projects.push(sync);
         }
 
-        let mut subtasks: Vec<Arc<dyn Job>> = Vec::new();
-
         for sync in sync_projects.iter_mut() {
             sync.assign_sync_action();
+        }
+
+        if self.kind == WatchFolderKind::Projects {
+            if let Err(e) = self.detect_moves(ctx, &mut sync_projects).await {
+                log::error!("Failed to detect moved projects: {}", e);
+            }
+        }
+
+        let mut subtasks: Vec<Arc<dyn Job>> = Vec::new();
 
+        for sync in sync_projects.iter() {
             match sync.action {
                 SyncAction::Add => {
                     subtasks.push(Arc::new(AddProjectJob::new(
@@ -107,6 +315,12 @@ impl Job for SyncFolderJob {
                         Err(e) => log::error!("Failed to create RemoveProjectJob: {}", e),
                     };
                 }
+                SyncAction::Move => {
+                    match MoveProjectJob::new(&sync, self.is_import.load(Ordering::Relaxed)) {
+                        Ok(job) => subtasks.push(Arc::new(job)),
+                        Err(e) => log::error!("Failed to create MoveProjectJob: {}", e),
+                    };
+                }
                 SyncAction::Update => {
                     subtasks.push(Arc::new(
                         UpdateProjectJob::new(&sync, self.is_import.load(Ordering::Relaxed), false)
@@ -142,6 +356,7 @@ pub enum SyncAction {
     Add,
     Remove,
     Update,
+    Move,
 }
 
 #[derive(Debug, Clone)]
@@ -175,6 +390,23 @@ impl ProjectSync {
             .get_project(project_id)
             .await?;
 
+        if entity.is_loose_images() {
+            let folder_path = entity.full_path.clone();
+            let id = entity.watchfolder_id;
+            let folder = tokio::task::spawn_blocking({
+                let folder_path = folder_path.clone();
+                move || get_loose_images_folder(&folder_path, id)
+            })
+            .await?;
+            return Ok(Self {
+                watchfolder_id: entity.watchfolder_id,
+                entity: Some(entity),
+                file: Some(folder),
+                action: SyncAction::None,
+                watchfolder_path: folder_path,
+            });
+        }
+
         let mut project = None;
         if let Ok(metadata) = fs::metadata(&entity.full_path).await {
             project = Some(ProjectFile {
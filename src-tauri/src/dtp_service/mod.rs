@@ -8,8 +8,8 @@ pub mod jobs;
 pub mod data;
 pub use data::{
     dtp_decode_tensor, dtp_find_image_from_preview_id, dtp_find_predecessor, dtp_get_clip,
-    dtp_get_metadata, dtp_get_tensor_size, dtp_list_images, dtp_list_models, dtp_list_projects,
-    dtp_list_watch_folders, dtp_pick_watch_folder, dtp_remove_watch_folder,
+    dtp_get_metadata, dtp_get_tensor_size, dtp_library_stats, dtp_list_images, dtp_list_models,
+    dtp_list_projects, dtp_list_watch_folders, dtp_pick_watch_folder, dtp_remove_watch_folder,
     dtp_update_project_exclude, dtp_update_watch_folder,
 };
 
@@ -17,7 +17,7 @@ pub mod export;
 
 pub mod dtp_service;
 pub use dtp_service::{
-    dtp_connect, dtp_lock_folder, dtp_reset_db, dtp_sync_projects, get_db_url, DTPService,
+    dtp_connect, dtp_connect_portable, dtp_lock_folder, dtp_reset_db, dtp_sync_projects, get_db_url, DTPService,
 };
 
 pub use helpers::{AppHandleWrapper, GetFolderFilesResult, ProjectFile};
@@ -12,9 +12,12 @@ use std::{
 use tokio::time::Duration;
 use tokio::{fs, sync::Mutex};
 
-use crate::dtp_service::{
-    jobs::{CheckFolderJob, SyncJob},
-    scheduler::Scheduler,
+use crate::{
+    dtp_service::{
+        jobs::{CheckFolderJob, SyncJob},
+        scheduler::Scheduler,
+    },
+    projects_db::image_metadata::is_image_file,
 };
 
 pub struct WatchService {
@@ -47,6 +50,10 @@ impl FolderWatcher {
                                     let project_path = event.path.with_extension("sqlite3");
                                     projects.insert(project_path.to_str().unwrap().to_string());
                                 }
+                                // for loose images folders
+                                _ if is_image_file(&event.path) => {
+                                    projects.insert(event.path.to_string_lossy().to_string());
+                                }
                                 _ => {}
                             }
                         }
@@ -12,11 +12,11 @@ pub mod bookmarks;
 pub mod dtp_service;
 mod ffmpeg;
 pub mod projects_db;
-use dtp_service::dtp_connect;
+use dtp_service::{dtp_connect, dtp_connect_portable};
 use projects_db::dt_project_tensordata;
 mod migrations;
 mod vid;
-mod vid_export;
+pub mod vid_export;
 use migrations::run_migrations;
 
 use once_cell::sync::Lazy;
@@ -26,7 +26,13 @@ mod resource_handle;
 pub use resource_handle::ResourceHandle;
 
 mod tensor;
-pub use tensor::{Tensor, TensorValue};
+pub use tensor::{Tensor, TensorDType, TensorValue};
+
+pub mod tensor_cache;
+pub use tensor_cache::TENSOR_CACHE;
+
+mod resize;
+pub use resize::{resize_pixels, resize_samples, ResizeFilter, ResizeFit, ResizeOptions};
 
 mod error;
 pub use error::{TACommandError, TAResult, IntoTAResult};
@@ -262,12 +268,16 @@ pub fn run() {
             bookmarks::resolve_bookmark,
             bookmarks::stop_accessing_bookmark,
             dtp_connect,
+            dtp_connect_portable,
             dtp_service::data::dtp_pick_watch_folder,
             dtp_service::data::dtp_decode_tensor,
             dtp_service::data::dtp_find_image_from_preview_id,
             dtp_service::data::dtp_find_predecessor,
             dtp_service::data::dtp_get_clip,
+            dtp_service::data::dtp_get_lineage,
+            dtp_service::data::dtp_get_prompt_timeline,
             dtp_service::data::dtp_get_tensor_size,
+            dtp_service::data::dtp_library_stats,
             dtp_service::data::dtp_list_images,
             dtp_service::data::dtp_list_models,
             dtp_service::data::dtp_list_projects,
@@ -277,12 +287,24 @@ pub fn run() {
             dtp_service::data::dtp_update_watch_folder,
             dtp_service::dtp_service::dtp_test,
             dtp_service::dtp_service::dtp_sync,
+            dtp_service::dtp_service::dtp_set_thumb_cache_budget,
+            dtp_service::dtp_service::dtp_clear_thumb_cache,
+            dtp_service::dtp_service::dtp_set_tensor_cache_budget,
+            dtp_service::dtp_service::dtp_get_tensor_cache_stats,
             dtp_service::dtp_service::dtp_lock_folder,
             dtp_service::dtp_service::dtp_sync_projects,
             dtp_service::dtp_service::dtp_sync_projects_and_wait,
+            dtp_service::dtp_service::dtp_check_project,
             dtp_service::data::dtp_get_metadata,
+            dtp_service::data::dtp_diff_metadata,
+            projects_db::image_metadata::dtm_read_image_metadata,
             dtp_service::export::dtp_export_projects,
+            dtp_service::export::dtp_export_index,
+            dtp_service::export::dtp_export_inpaint_view,
+            dtp_service::export::dtp_export_tensor_file,
             dtp_service::dt_data::dtp_dt_get_tensor_history_nodes,
+            dtp_service::dt_data::dtp_dt_list_tables,
+            dtp_service::dt_data::dtp_dt_get_table_rows,
             dt_project_tensordata,
             dtp_service::dtp_service::dtp_reset_db,
         ])
@@ -0,0 +1,365 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+use image::{imageops, RgbaImage};
+
+use crate::resize::{resize_pixels, ResizeFilter, ResizeFit, ResizeOptions};
+
+/// Long edge limit of a reconstructed canvas. Long outpainting sessions can cover a large
+/// area, so the canvas is scaled down to fit.
+pub const MAX_COMPOSITE_SIZE: u32 = 4096;
+
+/// Where a tensor was placed on the infinite canvas
+///
+/// Canvas layers come from tensordata (`x`, `y`, `width`, `height`), generated images from
+/// the node (`content_offset_x/y`). Positions are in canvas units, and the tensor covers its
+/// pixel size divided by `scale_factor_by_120 / 120`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CanvasPlacement {
+    /// rowid of the node the placement was recorded in
+    pub node_id: i64,
+    pub tensor_name: String,
+    pub x: i32,
+    pub y: i32,
+    /// pixel size recorded with the placement. The tensor's own size is used if None.
+    pub size: Option<(u32, u32)>,
+    pub scale_factor_by_120: i32,
+}
+
+impl CanvasPlacement {
+    /// Pixels per canvas unit
+    pub fn scale(&self) -> f64 {
+        match self.scale_factor_by_120 > 0 {
+            true => self.scale_factor_by_120 as f64 / 120.0,
+            false => 1.0,
+        }
+    }
+
+    /// Area covered on the canvas as (x, y, width, height), if the size was recorded
+    pub fn bounds(&self) -> Option<(f64, f64, f64, f64)> {
+        let (width, height) = self.size?;
+        let scale = self.scale();
+        Some((
+            self.x as f64,
+            self.y as f64,
+            width as f64 / scale,
+            height as f64 / scale,
+        ))
+    }
+
+    /// Places a decoded image using this placement's position and scale
+    pub fn layer(&self, image: RgbaImage) -> CanvasLayer {
+        let (width, height) = self.size.unwrap_or(image.dimensions());
+        let scale = self.scale();
+        CanvasLayer {
+            x: self.x as f64,
+            y: self.y as f64,
+            width: width as f64 / scale,
+            height: height as f64 / scale,
+            image,
+        }
+    }
+}
+
+/// Keeps the placements that can be seen. Placements are ordered oldest first, so a tensor
+/// placed more than once is drawn at the position and stacking order of its latest placement,
+/// and a placement entirely covered by a later one is dropped, since canvas images are opaque.
+pub fn visible_placements(placements: Vec<CanvasPlacement>) -> Vec<CanvasPlacement> {
+    let mut seen = HashSet::new();
+    let mut visible: Vec<CanvasPlacement> = Vec::new();
+    for placement in placements.into_iter().rev() {
+        if !seen.insert(placement.tensor_name.clone()) {
+            continue;
+        }
+        let covered = placement.bounds().is_some_and(|below| {
+            visible
+                .iter()
+                .filter_map(CanvasPlacement::bounds)
+                .any(|above| contains(above, below))
+        });
+        if !covered {
+            visible.push(placement);
+        }
+    }
+    visible.reverse();
+    visible
+}
+
+fn contains(outer: (f64, f64, f64, f64), inner: (f64, f64, f64, f64)) -> bool {
+    let (x, y, width, height) = outer;
+    let (inner_x, inner_y, inner_width, inner_height) = inner;
+    inner_x >= x
+        && inner_y >= y
+        && inner_x + inner_width <= x + width
+        && inner_y + inner_height <= y + height
+}
+
+/// Pixel offset from the node's content offset, and pixel size, of a generated image
+///
+/// The image covers the generation size (`start_width/height`, in 64px blocks), so an upscaled
+/// tensor is drawn over the same area. When the node was generated as a crop of a larger image
+/// (`original_image_*` larger than `target_image_*`, or a crop offset), it covers the target
+/// size at the crop offset (`crop_left/top`, in 64px blocks) instead.
+pub fn generated_footprint(
+    start: (u32, u32),
+    original: (u32, u32),
+    target: (u32, u32),
+    crop: (i32, i32),
+) -> ((i32, i32), Option<(u32, u32)>) {
+    let is_crop = target.0 > 0
+        && target.1 > 0
+        && (original.0 > target.0 || original.1 > target.1 || crop != (0, 0));
+    if is_crop {
+        return ((crop.0 * 64, crop.1 * 64), Some(target));
+    }
+    let size = (start.0 > 0 && start.1 > 0).then_some((start.0 * 64, start.1 * 64));
+    ((0, 0), size)
+}
+
+/// A decoded image on the canvas, with its position and size in canvas units
+pub struct CanvasLayer {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+    pub image: RgbaImage,
+}
+
+/// Converts interleaved 8-bit gray, RGB or RGBA pixels to an RGBA image
+pub fn pixels_to_rgba(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    channels: usize,
+) -> Option<RgbaImage> {
+    let len = (width * height) as usize * channels;
+    let pixels = pixels.get(..len)?;
+    let rgba: Vec<u8> = match channels {
+        1 => pixels.iter().flat_map(|&v| [v, v, v, 255]).collect(),
+        3 => pixels
+            .chunks_exact(3)
+            .flat_map(|p| [p[0], p[1], p[2], 255])
+            .collect(),
+        4 => pixels.to_vec(),
+        _ => return None,
+    };
+    RgbaImage::from_raw(width, height, rgba)
+}
+
+/// Paints the layers in order onto a transparent canvas covering all of them
+///
+/// The canvas uses the resolution of the most detailed layer, scaled down to fit
+/// `MAX_COMPOSITE_SIZE`. Layers are resized to their size on the canvas and blended over
+/// the layers before them.
+pub fn composite_layers(layers: &[CanvasLayer]) -> Result<RgbaImage> {
+    let layers: Vec<&CanvasLayer> = layers
+        .iter()
+        .filter(|layer| layer.width > 0.0 && layer.height > 0.0)
+        .collect();
+    if layers.is_empty() {
+        anyhow::bail!("Canvas has no layers");
+    }
+
+    let min_x = layers.iter().map(|l| l.x).fold(f64::INFINITY, f64::min);
+    let min_y = layers.iter().map(|l| l.y).fold(f64::INFINITY, f64::min);
+    let max_x = layers
+        .iter()
+        .map(|l| l.x + l.width)
+        .fold(f64::NEG_INFINITY, f64::max);
+    let max_y = layers
+        .iter()
+        .map(|l| l.y + l.height)
+        .fold(f64::NEG_INFINITY, f64::max);
+    let (bounds_width, bounds_height) = (max_x - min_x, max_y - min_y);
+
+    let detail = layers
+        .iter()
+        .map(|l| (l.image.width() as f64 / l.width).max(l.image.height() as f64 / l.height))
+        .fold(0.0, f64::max);
+    let fit = MAX_COMPOSITE_SIZE as f64 / bounds_width.max(bounds_height);
+    let scale = detail.min(fit);
+
+    let width = ((bounds_width * scale).round() as u32).max(1);
+    let height = ((bounds_height * scale).round() as u32).max(1);
+    let mut canvas = RgbaImage::new(width, height);
+
+    for layer in layers {
+        let x = ((layer.x - min_x) * scale).round() as i64;
+        let y = ((layer.y - min_y) * scale).round() as i64;
+        let layer_width = ((layer.width * scale).round() as u32).max(1);
+        let layer_height = ((layer.height * scale).round() as u32).max(1);
+
+        if layer.image.dimensions() == (layer_width, layer_height) {
+            imageops::overlay(&mut canvas, &layer.image, x, y);
+            continue;
+        }
+        let options = ResizeOptions {
+            width: layer_width,
+            height: layer_height,
+            fit: ResizeFit::Exact,
+            filter: ResizeFilter::Lanczos3,
+        };
+        let (pixels, w, h) = resize_pixels(
+            layer.image.as_raw(),
+            layer.image.width(),
+            layer.image.height(),
+            4,
+            &options,
+        )?;
+        let resized = RgbaImage::from_raw(w, h, pixels)
+            .ok_or_else(|| anyhow::anyhow!("Invalid layer size"))?;
+        imageops::overlay(&mut canvas, &resized, x, y);
+    }
+
+    Ok(canvas)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    fn placement(node_id: i64, tensor_name: &str, x: i32) -> CanvasPlacement {
+        CanvasPlacement {
+            node_id,
+            tensor_name: tensor_name.to_string(),
+            x,
+            y: 0,
+            size: None,
+            scale_factor_by_120: 120,
+        }
+    }
+
+    fn solid(width: u32, height: u32, color: [u8; 4]) -> RgbaImage {
+        RgbaImage::from_pixel(width, height, Rgba(color))
+    }
+
+    #[test]
+    fn test_visible_placements() {
+        let visible = visible_placements(vec![
+            placement(1, "tensor_history_1", 0),
+            placement(2, "tensor_history_2", 64),
+            placement(3, "tensor_history_1", 128),
+        ]);
+        assert_eq!(
+            visible,
+            vec![
+                placement(2, "tensor_history_2", 64),
+                placement(3, "tensor_history_1", 128),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_covered_placements() {
+        let sized = |node_id: i64, tensor_name: &str, x: i32, size: u32| CanvasPlacement {
+            size: Some((size, size)),
+            ..placement(node_id, tensor_name, x)
+        };
+        let visible = visible_placements(vec![
+            sized(1, "tensor_history_1", 0, 64),
+            // only partly covered by the next one
+            sized(2, "tensor_history_2", 96, 64),
+            sized(3, "tensor_history_3", 0, 128),
+            // unknown size, so never dropped
+            placement(4, "tensor_history_4", 0),
+        ]);
+        assert_eq!(
+            visible,
+            vec![
+                sized(2, "tensor_history_2", 96, 64),
+                sized(3, "tensor_history_3", 0, 128),
+                placement(4, "tensor_history_4", 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_generated_footprint() {
+        // the generation size, whatever the tensor's size
+        assert_eq!(
+            generated_footprint((16, 12), (1024, 768), (1024, 768), (0, 0)),
+            ((0, 0), Some((1024, 768)))
+        );
+        assert_eq!(
+            generated_footprint((0, 0), (0, 0), (0, 0), (0, 0)),
+            ((0, 0), None)
+        );
+        // a crop of a larger image
+        assert_eq!(
+            generated_footprint((8, 8), (1024, 1024), (512, 512), (2, 4)),
+            ((128, 256), Some((512, 512)))
+        );
+    }
+
+    #[test]
+    fn test_placement_scale() {
+        let mut zoomed = placement(1, "tensor_history_1", 0);
+        zoomed.scale_factor_by_120 = 240;
+        let layer = zoomed.layer(solid(64, 32, [0; 4]));
+        assert_eq!((layer.width, layer.height), (32.0, 16.0));
+
+        zoomed.scale_factor_by_120 = 0;
+        zoomed.size = Some((128, 128));
+        let layer = zoomed.layer(solid(64, 32, [0; 4]));
+        assert_eq!((layer.width, layer.height), (128.0, 128.0));
+    }
+
+    #[test]
+    fn test_composite_layers() {
+        let red = [255, 0, 0, 255];
+        let blue = [0, 0, 255, 255];
+        let layers = vec![
+            CanvasLayer {
+                x: -8.0,
+                y: 0.0,
+                width: 16.0,
+                height: 8.0,
+                image: solid(16, 8, red),
+            },
+            // drawn at half resolution, so it is scaled up
+            CanvasLayer {
+                x: 4.0,
+                y: 4.0,
+                width: 8.0,
+                height: 8.0,
+                image: solid(4, 4, blue),
+            },
+        ];
+
+        let canvas = composite_layers(&layers).unwrap();
+        assert_eq!(canvas.dimensions(), (20, 12));
+        assert_eq!(canvas.get_pixel(0, 0).0, red);
+        // the second layer is on top where they overlap
+        assert_eq!(canvas.get_pixel(13, 5).0, blue);
+        assert_eq!(canvas.get_pixel(19, 11).0, blue);
+        // outside every layer is transparent
+        assert_eq!(canvas.get_pixel(0, 11).0, [0, 0, 0, 0]);
+
+        assert!(composite_layers(&[]).is_err());
+    }
+
+    #[test]
+    fn test_composite_size_limit() {
+        let layers = vec![CanvasLayer {
+            x: 0.0,
+            y: 0.0,
+            width: 8192.0,
+            height: 1024.0,
+            image: solid(64, 8, [255; 4]),
+        }];
+        // the image covers more canvas than it has pixels, and still fits the limit
+        let canvas = composite_layers(&layers).unwrap();
+        assert_eq!(canvas.dimensions(), (64, 8));
+
+        let layers = vec![CanvasLayer {
+            x: 0.0,
+            y: 0.0,
+            width: 8192.0,
+            height: 64.0,
+            image: solid(8192, 64, [255; 4]),
+        }];
+        let canvas = composite_layers(&layers).unwrap();
+        assert_eq!(canvas.dimensions(), (MAX_COMPOSITE_SIZE, 32));
+    }
+}
@@ -0,0 +1,167 @@
+use serde_json::{json, Map, Value};
+use sqlx::{query, sqlite::SqliteRow, AssertSqlSafe, Column, Row, TypeInfo, ValueRef};
+
+use crate::projects_db::{
+    dt_project::{
+        data::{tensor_data::TensorData, tensor_history_node_data::TensorHistoryNodeData},
+        DTProjectTable,
+    },
+    dtos::{
+        explorer::{ProjectTable, ProjectTablePage, ProjectTableRow},
+        text::TextHistoryNode,
+    },
+    fbs::{root_as_clip, root_as_tensor_moodboard_data},
+    DTProject,
+};
+
+/// Most rows returned by one `get_table_rows` call
+pub const MAX_TABLE_PAGE: i64 = 500;
+
+/// Blobs up to this size are included as hex, e.g. the `dim` column of `tensors`
+const MAX_HEX_BLOB: usize = 64;
+
+impl DTProject {
+    /// The known tables of the project, with their row counts
+    pub async fn list_tables(&self) -> Result<Vec<ProjectTable>, sqlx::Error> {
+        let status = self.check_tables().await?.clone();
+        let mut tables = Vec::new();
+        for table in DTProjectTable::ALL {
+            let present = status.has(&table);
+            let row_count = match present {
+                true => self.count_rows(&table).await?,
+                false => 0,
+            };
+            tables.push(ProjectTable {
+                table,
+                name: table.table_name(),
+                present,
+                row_count,
+            });
+        }
+        Ok(tables)
+    }
+
+    async fn count_rows(&self, table: &DTProjectTable) -> Result<i64, sqlx::Error> {
+        let sql = format!("SELECT COUNT(*) FROM {}", table.table_name());
+        let row = query(AssertSqlSafe(sql)).fetch_one(&*self.pool).await?;
+        row.try_get(0)
+    }
+
+    /// Pages through the rows of a table, ordered by rowid, decoding the flatbuffer `p` column
+    /// where its schema is known. At most `MAX_TABLE_PAGE` rows are returned.
+    pub async fn get_table_rows(
+        &self,
+        table: DTProjectTable,
+        skip: i64,
+        take: i64,
+    ) -> Result<ProjectTablePage, sqlx::Error> {
+        self.check_table(&table).await?;
+        let skip = skip.max(0);
+        let take = take.clamp(0, MAX_TABLE_PAGE);
+
+        let sql = format!(
+            "SELECT rowid, * FROM {} ORDER BY rowid LIMIT {} OFFSET {}",
+            table.table_name(),
+            take,
+            skip
+        );
+        let rows = query(AssertSqlSafe(sql))
+            .fetch_all(&*self.pool)
+            .await?
+            .iter()
+            .map(|row| read_row(table, row))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(ProjectTablePage {
+            table,
+            row_count: self.count_rows(&table).await?,
+            skip,
+            take,
+            rows,
+        })
+    }
+}
+
+fn read_row(table: DTProjectTable, row: &SqliteRow) -> Result<ProjectTableRow, sqlx::Error> {
+    let mut result = ProjectTableRow {
+        rowid: row.try_get(0)?,
+        columns: Map::new(),
+        data: None,
+        data_error: None,
+    };
+
+    // the first column is the rowid
+    for (i, column) in row.columns().iter().enumerate().skip(1) {
+        let raw = row.try_get_raw(i)?;
+        let type_name = match raw.is_null() {
+            true => "NULL".to_string(),
+            false => raw.type_info().name().to_string(),
+        };
+        let value = match type_name.as_str() {
+            "NULL" => Value::Null,
+            "INTEGER" => Value::from(row.try_get::<i64, _>(i)?),
+            "REAL" => Value::from(row.try_get::<f64, _>(i)?),
+            "TEXT" => Value::from(row.try_get::<String, _>(i)?),
+            _ => {
+                let blob: Vec<u8> = row.try_get(i)?;
+                if column.name() == "p" {
+                    match decode_flatbuffer(table, &blob) {
+                        Some(Ok(data)) => result.data = Some(data),
+                        Some(Err(e)) => result.data_error = Some(e),
+                        None => {}
+                    }
+                }
+                match blob.len() <= MAX_HEX_BLOB {
+                    true => json!({ "size": blob.len(), "hex": hex::encode(&blob) }),
+                    false => json!({ "size": blob.len() }),
+                }
+            }
+        };
+        result.columns.insert(column.name().to_string(), value);
+    }
+
+    Ok(result)
+}
+
+/// Decodes the `p` column with the generated flatbuffer accessors. Returns None for tables
+/// without a known schema (thumbnails hold an image rather than a flatbuffer).
+fn decode_flatbuffer(table: DTProjectTable, bytes: &[u8]) -> Option<Result<Value, String>> {
+    let value = match table {
+        DTProjectTable::TensorHistoryNode => TensorHistoryNodeData::try_from(bytes)
+            .map_err(|e| e.to_string())
+            .and_then(|data| serde_json::to_value(data).map_err(|e| e.to_string())),
+        DTProjectTable::TensorData => TensorData::try_from(bytes)
+            .map_err(|e| e.to_string())
+            .and_then(|data| serde_json::to_value(data).map_err(|e| e.to_string())),
+        DTProjectTable::TextHistory => TextHistoryNode::try_from(bytes)
+            .map_err(|e| e.to_string())
+            .and_then(|data| serde_json::to_value(data).map_err(|e| e.to_string())),
+        DTProjectTable::TensorMoodboardData => root_as_tensor_moodboard_data(bytes)
+            .map(|fb| {
+                json!({
+                    "lineage": fb.lineage(),
+                    "logical_time": fb.logical_time(),
+                    "index": fb.index(),
+                    "shuffle_id": fb.shuffle_id(),
+                    "weight": fb.weight(),
+                })
+            })
+            .map_err(|e| e.to_string()),
+        DTProjectTable::Clip => root_as_clip(bytes)
+            .map(|fb| {
+                json!({
+                    "clip_id": fb.clip_id(),
+                    "count": fb.count(),
+                    "frames_per_second": fb.frames_per_second(),
+                    "width": fb.width(),
+                    "height": fb.height(),
+                    "audio_id": fb.audio_id(),
+                })
+            })
+            .map_err(|e| e.to_string()),
+        DTProjectTable::TextLineage | DTProjectTable::Tensors | DTProjectTable::Thumbs => {
+            return None
+        }
+    };
+    Some(value)
+}
@@ -0,0 +1,350 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use image::ImageFormat;
+use sqlx::{query, Row};
+
+use crate::{
+    projects_db::{
+        dt_project::{
+            data::{tensor_data::TensorData, tensor_history_node_data::TensorHistoryNodeData},
+            DTProjectTable,
+        },
+        dtos::{
+            health::{HealthCheck, HealthFailure, ProjectHealthReport},
+            tensor::{
+                format_resource_id, PREFIX_COLOR, PREFIX_CUSTOM, PREFIX_DEPTH, PREFIX_MASK,
+                PREFIX_POSE, PREFIX_SCRIBBLE, PREFIX_TENSOR,
+            },
+        },
+        extract_jpeg_slice,
+        fbs::root_as_clip,
+        DTProject,
+    },
+    Tensor,
+};
+
+/// Failures kept as samples in a health report
+pub const MAX_HEALTH_FAILURES: usize = 100;
+
+/// History nodes read per query, and between progress updates
+const HEALTH_BATCH: i64 = 200;
+
+impl ProjectHealthReport {
+    /// Counts a check, and keeps the failure if there is room for another sample
+    fn record(
+        &mut self,
+        check: HealthCheck,
+        node_id: Option<i64>,
+        name: Option<String>,
+        result: Result<(), String>,
+    ) {
+        let count = match check {
+            HealthCheck::Node => &mut self.nodes,
+            HealthCheck::TensorData => &mut self.tensor_data,
+            HealthCheck::Tensor => &mut self.tensors,
+            HealthCheck::Thumbnail => &mut self.thumbnails,
+            HealthCheck::Clip => &mut self.clips,
+        };
+        count.checked += 1;
+        if let Err(message) = result {
+            count.failed += 1;
+            if self.failures.len() < MAX_HEALTH_FAILURES {
+                self.failures.push(HealthFailure {
+                    check,
+                    node_id,
+                    name,
+                    message,
+                });
+            }
+        }
+    }
+}
+
+impl DTProject {
+    /// Reads every history node, and checks that the tensors and thumbnails it references
+    /// exist and decode, and that clips have as many frames as they say. `on_progress` is
+    /// called with the number of history nodes checked and the total.
+    pub async fn check_health(
+        &self,
+        on_progress: impl Fn(u64, u64) + Send + Sync,
+    ) -> Result<ProjectHealthReport, sqlx::Error> {
+        self.check_table(&DTProjectTable::TensorHistoryNode).await?;
+        let status = self.check_tables().await?.clone();
+        let mut report = ProjectHealthReport {
+            path: self.path.clone(),
+            ..Default::default()
+        };
+
+        let mut tensor_names = HashSet::new();
+        if status.has(&DTProjectTable::Tensors) {
+            for row in query("SELECT name FROM tensors")
+                .fetch_all(&*self.pool)
+                .await?
+            {
+                tensor_names.insert(row.try_get::<String, _>(0)?);
+            }
+        }
+        let mut thumb_ids = HashSet::new();
+        if status.has(&DTProjectTable::Thumbs) {
+            for row in query("SELECT __pk0 FROM thumbnailhistorynode")
+                .fetch_all(&*self.pool)
+                .await?
+            {
+                thumb_ids.insert(row.try_get::<i64, _>(0)?);
+            }
+        }
+
+        // tensors referenced by each node, by lineage and logical time
+        let mut references: HashMap<(i64, i64), Vec<String>> = HashMap::new();
+        if status.has(&DTProjectTable::TensorData) {
+            for row in query("SELECT rowid, p FROM tensordata ORDER BY rowid")
+                .fetch_all(&*self.pool)
+                .await?
+            {
+                let rowid: i64 = row.try_get(0)?;
+                let p: Option<Vec<u8>> = row.try_get(1)?;
+                let name = Some(format!("tensordata {}", rowid));
+                match TensorData::try_from(p.as_deref().unwrap_or_default()) {
+                    Ok(td) => {
+                        report.record(HealthCheck::TensorData, None, name, Ok(()));
+                        references
+                            .entry((td.lineage, td.logical_time))
+                            .or_default()
+                            .extend(referenced_tensors(&td));
+                    }
+                    Err(e) => {
+                        report.record(HealthCheck::TensorData, None, name, Err(e.to_string()))
+                    }
+                }
+            }
+        }
+
+        let total: i64 = query("SELECT COUNT(*) FROM tensorhistorynode")
+            .fetch_one(&*self.pool)
+            .await?
+            .try_get(0)?;
+        let mut checked_tensors = HashSet::new();
+        // frame count and first frame of each clip
+        let mut clip_frames: BTreeMap<i64, (u64, i64)> = BTreeMap::new();
+        let mut last_rowid: i64 = 0;
+        let mut checked: u64 = 0;
+        loop {
+            let rows = query(
+                "SELECT rowid, p FROM tensorhistorynode WHERE rowid > ?1 ORDER BY rowid LIMIT ?2",
+            )
+            .bind(last_rowid)
+            .bind(HEALTH_BATCH)
+            .fetch_all(&*self.pool)
+            .await?;
+            let Some(last) = rows.last() else {
+                break;
+            };
+            last_rowid = last.try_get(0)?;
+
+            for row in &rows {
+                let rowid: i64 = row.try_get(0)?;
+                let p: Option<Vec<u8>> = row.try_get(1)?;
+                let node = match TensorHistoryNodeData::try_from(p.as_deref().unwrap_or_default()) {
+                    Ok(node) => node,
+                    Err(e) => {
+                        report.record(HealthCheck::Node, Some(rowid), None, Err(e.to_string()));
+                        continue;
+                    }
+                };
+                report.record(HealthCheck::Node, Some(rowid), None, Ok(()));
+
+                // the node's own tensors, then those of its canvas layers
+                let mut names = referenced_tensors(&node);
+                if let Some(layers) = references.get(&(node.lineage, node.logical_time)) {
+                    names.extend(layers.iter().cloned());
+                }
+                for name in names {
+                    if !checked_tensors.insert(name.clone()) {
+                        continue;
+                    }
+                    let result = match tensor_names.contains(&name) {
+                        true => self.check_tensor(&name).await,
+                        false => Err("tensor not found".to_string()),
+                    };
+                    report.record(HealthCheck::Tensor, Some(rowid), Some(name), result);
+                }
+
+                if node.preview_id > 0 {
+                    let result = match thumb_ids.contains(&node.preview_id) {
+                        true => self.check_thumb(node.preview_id).await,
+                        false => Err("thumbnail not found".to_string()),
+                    };
+                    let name = Some(node.preview_id.to_string());
+                    report.record(HealthCheck::Thumbnail, Some(rowid), name, result);
+                }
+
+                if node.clip_id > 0 {
+                    clip_frames.entry(node.clip_id).or_insert((0, rowid)).0 += 1;
+                }
+            }
+
+            checked += rows.len() as u64;
+            on_progress(checked, total as u64);
+        }
+
+        if status.has(&DTProjectTable::Clip) {
+            for row in query("SELECT __pk0, p FROM clip ORDER BY __pk0")
+                .fetch_all(&*self.pool)
+                .await?
+            {
+                let clip_id: i64 = row.try_get(0)?;
+                let p: Option<Vec<u8>> = row.try_get(1)?;
+                let (frames, first_frame) = match clip_frames.remove(&clip_id) {
+                    Some((frames, first_frame)) => (frames, Some(first_frame)),
+                    None => (0, None),
+                };
+                let result = match root_as_clip(p.as_deref().unwrap_or_default()) {
+                    Ok(clip) if clip.count() as u64 == frames => Ok(()),
+                    Ok(clip) => Err(format!(
+                        "clip has {} frames, expected {}",
+                        frames,
+                        clip.count()
+                    )),
+                    Err(e) => Err(e.to_string()),
+                };
+                report.record(
+                    HealthCheck::Clip,
+                    first_frame,
+                    Some(clip_id.to_string()),
+                    result,
+                );
+            }
+        }
+        // frames of clips that aren't in the clip table
+        for (clip_id, (_, first_frame)) in clip_frames {
+            let result = Err("clip not found".to_string());
+            report.record(
+                HealthCheck::Clip,
+                Some(first_frame),
+                Some(clip_id.to_string()),
+                result,
+            );
+        }
+
+        report.healthy = [
+            &report.nodes,
+            &report.tensor_data,
+            &report.tensors,
+            &report.thumbnails,
+            &report.clips,
+        ]
+        .iter()
+        .all(|count| count.failed == 0);
+        Ok(report)
+    }
+
+    /// Decodes the tensor without adding it to the tensor cache
+    async fn check_tensor(&self, name: &str) -> Result<(), String> {
+        let tensor_raw = self.get_tensor_raw(name).await.map_err(|e| e.to_string())?;
+        tokio::task::spawn_blocking(move || Tensor::try_from(tensor_raw))
+            .await
+            .map_err(|e| e.to_string())?
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    async fn check_thumb(&self, thumb_id: i64) -> Result<(), String> {
+        let thumb = self.get_thumb(thumb_id).await.map_err(|e| e.to_string())?;
+        let jpeg = extract_jpeg_slice(&thumb).ok_or("thumbnail has no jpeg data")?;
+        image::load_from_memory_with_format(&jpeg, ImageFormat::Jpeg).map_err(|e| e.to_string())?;
+        Ok(())
+    }
+}
+
+/// History nodes and tensordata rows reference tensors with the same set of ids
+trait TensorIds {
+    fn tensor_ids(&self) -> [(&'static str, i64); 7];
+}
+
+impl TensorIds for TensorData {
+    fn tensor_ids(&self) -> [(&'static str, i64); 7] {
+        [
+            (PREFIX_TENSOR, self.tensor_id),
+            (PREFIX_MASK, self.mask_id),
+            (PREFIX_DEPTH, self.depth_map_id),
+            (PREFIX_SCRIBBLE, self.scribble_id),
+            (PREFIX_POSE, self.pose_id),
+            (PREFIX_COLOR, self.color_palette_id),
+            (PREFIX_CUSTOM, self.custom_id),
+        ]
+    }
+}
+
+impl TensorIds for TensorHistoryNodeData {
+    fn tensor_ids(&self) -> [(&'static str, i64); 7] {
+        [
+            (PREFIX_TENSOR, self.tensor_id),
+            (PREFIX_MASK, self.mask_id),
+            (PREFIX_DEPTH, self.depth_map_id),
+            (PREFIX_SCRIBBLE, self.scribble_id),
+            (PREFIX_POSE, self.pose_id),
+            (PREFIX_COLOR, self.color_palette_id),
+            (PREFIX_CUSTOM, self.custom_id),
+        ]
+    }
+}
+
+/// Names of the tensors a history node or tensordata row references
+fn referenced_tensors(item: &impl TensorIds) -> Vec<String> {
+    item.tensor_ids()
+        .into_iter()
+        .filter_map(|(prefix, id)| format_resource_id(prefix, id))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_referenced_tensors() {
+        let td = TensorData {
+            rowid: 1,
+            lineage: 0,
+            logical_time: 3,
+            index: 0,
+            x: 0,
+            y: 0,
+            width: 512,
+            height: 512,
+            scale_factor_by_120: 120,
+            tensor_id: 12,
+            mask_id: 4,
+            depth_map_id: 0,
+            scribble_id: 0,
+            pose_id: 0,
+            color_palette_id: 0,
+            custom_id: 7,
+        };
+        assert_eq!(
+            referenced_tensors(&td),
+            vec!["tensor_history_12", "binary_mask_4", "custom_7"]
+        );
+    }
+
+    #[test]
+    fn test_record() {
+        let mut report = ProjectHealthReport::default();
+        report.record(HealthCheck::Tensor, Some(1), None, Ok(()));
+        for i in 0..MAX_HEALTH_FAILURES + 5 {
+            let name = Some(format!("tensor_history_{}", i));
+            report.record(HealthCheck::Tensor, Some(1), name, Err("bad".to_string()));
+        }
+
+        assert_eq!(report.tensors.checked, MAX_HEALTH_FAILURES as u64 + 6);
+        assert_eq!(report.tensors.failed, MAX_HEALTH_FAILURES as u64 + 5);
+        assert_eq!(report.nodes.checked, 0);
+
+        report.record(HealthCheck::TensorData, None, None, Err("bad".to_string()));
+        assert_eq!(report.tensor_data.checked, 1);
+        assert_eq!(report.tensor_data.failed, 1);
+        assert_eq!(report.nodes.failed, 0);
+        assert_eq!(report.failures.len(), MAX_HEALTH_FAILURES);
+        assert_eq!(report.failures[0].name.as_deref(), Some("tensor_history_0"));
+    }
+}
@@ -0,0 +1,295 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::Arc,
+};
+
+use sqlx::query_as;
+
+use crate::projects_db::{
+    composite::{generated_footprint, visible_placements, CanvasPlacement},
+    dt_project::{DTProjectTable, TdFilter, TensorHistoryNode, ThnData, ThnFilter},
+    dtos::lineage::{LineageNode, NodeLineage},
+    DTProject,
+};
+
+/// The history of a project as a tree of nodes
+///
+/// Each generation adds a node at the next logical time in the current lineage (`__pk0`).
+/// When the user goes back in history and generates from an earlier node, a new lineage is
+/// started, so the first node of a lineage branches from a node at the previous logical time
+/// in an older lineage. When more than one older lineage has a node at that time, the parent
+/// is the one sharing a canvas tensor with the new node, then the one with the same
+/// `text_lineage`, then the most recent lineage.
+#[derive(Debug)]
+pub struct LineageGraph {
+    nodes: BTreeMap<i64, LineageNode>,
+    children: HashMap<i64, Vec<i64>>,
+}
+
+impl LineageGraph {
+    fn new(nodes: Vec<TensorHistoryNode>, canvas: HashMap<(i64, i64), HashSet<String>>) -> Self {
+        let by_key: HashMap<(i64, i64), &TensorHistoryNode> = nodes
+            .iter()
+            .map(|node| ((node.lineage, node.logical_time), node))
+            .collect();
+        let by_time: HashMap<i64, Vec<&TensorHistoryNode>> =
+            nodes.iter().fold(HashMap::new(), |mut map, node| {
+                map.entry(node.logical_time).or_default().push(node);
+                map
+            });
+        let no_tensors = HashSet::new();
+        let tensors = |node: &TensorHistoryNode| {
+            canvas
+                .get(&(node.lineage, node.logical_time))
+                .unwrap_or(&no_tensors)
+        };
+
+        let find_parent = |node: &TensorHistoryNode| -> Option<i64> {
+            if let Some(parent) = by_key.get(&(node.lineage, node.logical_time - 1)) {
+                return Some(parent.rowid);
+            }
+            let node_tensors = tensors(node);
+            let text_lineage = node.data().text_lineage();
+            by_time
+                .get(&(node.logical_time - 1))?
+                .iter()
+                .copied()
+                .filter(|candidate| candidate.lineage < node.lineage)
+                .max_by_key(|&candidate| {
+                    let shares_canvas = !tensors(candidate).is_disjoint(node_tensors);
+                    let same_text = candidate.data().text_lineage() == text_lineage;
+                    (shares_canvas, same_text, candidate.lineage)
+                })
+                .map(|candidate| candidate.rowid)
+        };
+
+        let mut graph = LineageGraph {
+            nodes: BTreeMap::new(),
+            children: HashMap::new(),
+        };
+        let lineages: HashMap<i64, i64> = nodes.iter().map(|n| (n.rowid, n.lineage)).collect();
+
+        for node in &nodes {
+            let parent = find_parent(node);
+            if let Some(parent) = parent {
+                graph.children.entry(parent).or_default().push(node.rowid);
+            }
+            let fb = node.data();
+            graph.nodes.insert(
+                node.rowid,
+                LineageNode {
+                    rowid: node.rowid,
+                    lineage: node.lineage,
+                    logical_time: node.logical_time,
+                    parent,
+                    branch: parent.map_or(false, |p| lineages.get(&p) != Some(&node.lineage)),
+                    text_lineage: fb.text_lineage(),
+                    tensor_id: fb.tensor_id(),
+                    mask_id: fb.mask_id(),
+                    preview_id: fb.preview_id(),
+                    generated: fb.generated(),
+                    strength: fb.strength(),
+                },
+            );
+        }
+
+        graph
+    }
+
+    pub fn get(&self, rowid: i64) -> Option<&LineageNode> {
+        self.nodes.get(&rowid)
+    }
+
+    /// The parent of the node, then its parent, up to the root of the history
+    pub fn ancestors(&self, rowid: i64) -> Vec<LineageNode> {
+        let mut ancestors = Vec::new();
+        let mut current = self.get(rowid).and_then(|node| node.parent);
+        while let Some(parent) = current.and_then(|rowid| self.get(rowid)) {
+            ancestors.push(parent.clone());
+            current = parent.parent;
+        }
+        ancestors
+    }
+
+    /// All nodes generated from the node, directly or indirectly, ordered by rowid
+    pub fn descendants(&self, rowid: i64) -> Vec<LineageNode> {
+        let mut found: Vec<i64> = Vec::new();
+        let mut pending: Vec<i64> = vec![rowid];
+        while let Some(current) = pending.pop() {
+            if let Some(children) = self.children.get(&current) {
+                found.extend(children);
+                pending.extend(children);
+            }
+        }
+        found.sort();
+        found
+            .into_iter()
+            .filter_map(|rowid| self.get(rowid).cloned())
+            .collect()
+    }
+
+    pub fn node_lineage(&self, rowid: i64) -> Option<NodeLineage> {
+        Some(NodeLineage {
+            node: self.get(rowid)?.clone(),
+            ancestors: self.ancestors(rowid),
+            descendants: self.descendants(rowid),
+        })
+    }
+}
+
+/// How far back a canvas is looked for when its lineage has no tensordata
+const MAX_CANVAS_DEPTH: usize = 256;
+
+/// Last rowid and row count of tensorhistorynode and tensordata. Draw Things appends rows as
+/// the user generates and deletes them when history is cleared, so a cached graph is stale
+/// when these change.
+pub(crate) type LineageVersion = (i64, i64, i64, i64);
+
+impl DTProject {
+    /// Builds the history graph of the project from its tensorhistorynode and tensordata
+    /// tables. The graph is kept until the tables change.
+    pub async fn get_lineage_graph(&self) -> Result<Arc<LineageGraph>, sqlx::Error> {
+        let version = self.get_lineage_version().await?;
+        if let Some((cached_version, graph)) = &*self.lineage_graph.lock().unwrap() {
+            if *cached_version == version {
+                return Ok(graph.clone());
+            }
+        }
+
+        let nodes = self.get_tensor_history_nodes(None, None).await?;
+
+        // the canvas tensors referenced by each node, used to pick between branch candidates
+        let mut canvas: HashMap<(i64, i64), HashSet<String>> = HashMap::new();
+        if self.check_tables().await?.has(&DTProjectTable::TensorData) {
+            for td in self.get_tensor_data(TdFilter::None).await? {
+                canvas
+                    .entry((td.lineage, td.logical_time))
+                    .or_default()
+                    .extend(td.tensor_names);
+            }
+        }
+
+        let graph = Arc::new(LineageGraph::new(nodes, canvas));
+        *self.lineage_graph.lock().unwrap() = Some((version, graph.clone()));
+        Ok(graph)
+    }
+
+    async fn get_lineage_version(&self) -> Result<LineageVersion, sqlx::Error> {
+        let tensor_data = match self.check_tables().await?.has(&DTProjectTable::TensorData) {
+            true => "SELECT IFNULL(MAX(rowid), 0), COUNT(*) FROM tensordata",
+            false => "SELECT 0, 0",
+        };
+        let (node_rowid, node_count): (i64, i64) =
+            query_as("SELECT IFNULL(MAX(rowid), 0), COUNT(*) FROM tensorhistorynode")
+                .fetch_one(&*self.pool)
+                .await?;
+        let (td_rowid, td_count): (i64, i64) = query_as(tensor_data).fetch_one(&*self.pool).await?;
+        Ok((node_rowid, node_count, td_rowid, td_count))
+    }
+
+    /// Returns the ancestors and descendants of the node with `rowid`
+    pub async fn get_node_lineage(&self, rowid: i64) -> Result<NodeLineage, sqlx::Error> {
+        self.get_lineage_graph()
+            .await?
+            .node_lineage(rowid)
+            .ok_or(sqlx::Error::RowNotFound)
+    }
+
+    /// The tensors on the canvas at the node with `rowid`, in the order they are drawn
+    ///
+    /// A node's tensordata records every layer on the canvas when it was generated, so the
+    /// canvas starts from the closest node in the lineage that has tensordata, and the images
+    /// generated after it are drawn over it at their content offsets. Lineages without
+    /// tensordata are followed back at most `MAX_CANVAS_DEPTH` nodes.
+    pub async fn get_canvas_placements(
+        &self,
+        rowid: i64,
+    ) -> Result<Vec<CanvasPlacement>, sqlx::Error> {
+        let graph = self.get_lineage_graph().await?;
+        let mut current = Some(graph.get(rowid).ok_or(sqlx::Error::RowNotFound)?.rowid);
+
+        // newest first, until a node with canvas layers
+        let mut chain = Vec::new();
+        while let Some(rowid) = current.filter(|_| chain.len() < MAX_CANVAS_DEPTH) {
+            current = graph.get(rowid).and_then(|node| node.parent);
+            let filter = Some(ThnFilter::Rowid(rowid));
+            let Some(node) = self
+                .get_tensor_history_nodes(filter, Some(ThnData::tensordata()))
+                .await?
+                .into_iter()
+                .next()
+            else {
+                continue;
+            };
+            let has_layers = node
+                .tensordata
+                .iter()
+                .flat_map(|td| td.iter())
+                .any(|td| td.data().tensor_id() > 0);
+            chain.push(node);
+            if has_layers {
+                break;
+            }
+        }
+
+        let mut placements: Vec<CanvasPlacement> = Vec::new();
+        for node in chain.iter().rev() {
+            let mut tensordata: Vec<_> = node.tensordata.iter().flat_map(|td| td.iter()).collect();
+            tensordata.sort_by_key(|td| td.idx);
+            let layers: Vec<CanvasPlacement> = tensordata
+                .into_iter()
+                .filter(|td| td.data().tensor_id() > 0)
+                .map(|td| {
+                    let data = td.data();
+                    let size = match (data.width(), data.height()) {
+                        (width, height) if width > 0 && height > 0 => {
+                            Some((width as u32, height as u32))
+                        }
+                        _ => None,
+                    };
+                    CanvasPlacement {
+                        node_id: node.rowid,
+                        tensor_name: format!("tensor_history_{}", data.tensor_id()),
+                        x: data.x(),
+                        y: data.y(),
+                        size,
+                        scale_factor_by_120: data.scale_factor_by_120(),
+                    }
+                })
+                .collect();
+            // the node's layers replace the canvas before it
+            if !layers.is_empty() {
+                placements = layers;
+            }
+
+            // the generated image goes on top, unless it is already one of the layers
+            let fb = node.data();
+            let generated = format!("tensor_history_{}", fb.tensor_id());
+            let is_layer = placements
+                .iter()
+                .any(|l| l.node_id == node.rowid && l.tensor_name == generated);
+            if fb.tensor_id() > 0 && !is_layer {
+                let ((offset_x, offset_y), size) = generated_footprint(
+                    (fb.start_width() as u32, fb.start_height() as u32),
+                    (fb.original_image_width(), fb.original_image_height()),
+                    (fb.target_image_width(), fb.target_image_height()),
+                    (fb.crop_left(), fb.crop_top()),
+                );
+                let mut placement = CanvasPlacement {
+                    node_id: node.rowid,
+                    tensor_name: generated,
+                    x: fb.content_offset_x(),
+                    y: fb.content_offset_y(),
+                    size,
+                    scale_factor_by_120: fb.scale_factor_by_120(),
+                };
+                // offsets are in pixels, positions in canvas units
+                placement.x += (offset_x as f64 / placement.scale()).round() as i32;
+                placement.y += (offset_y as f64 / placement.scale()).round() as i32;
+                placements.push(placement);
+            }
+        }
+
+        Ok(visible_placements(placements))
+    }
+}
@@ -4,16 +4,17 @@ use crate::projects_db::{
         clip::{ClipExtra, ClipFrame},
         project::DTProjectInfo,
         tensor::{TensorHistoryImport, TensorNodeGrouper, TensorRaw, TensorSize},
-        text::TextHistoryNode,
+        text::{PromptTimeline, TextHistoryNode},
     },
     fbs::root_as_tensor_moodboard_data,
     tensor_history_tensor_data::TensorHistoryTensorData,
     text_history::PromptPair,
     TextHistory,
 };
+use crate::{Tensor, TENSOR_CACHE};
 use dashmap::DashMap;
 use once_cell::sync::Lazy;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use sqlx::{
     query, query_as,
     sqlite::{SqliteConnection, SqliteRow},
@@ -34,7 +35,12 @@ pub use raw::dt_project_tensordata;
 pub mod clip;
 pub use clip::{Clip, ClipFilter};
 pub mod data;
+pub mod explorer;
+pub mod health;
+pub mod lineage;
+use lineage::{LineageGraph, LineageVersion};
 pub mod maintenance;
+pub mod schema;
 pub mod tensor_data;
 pub use tensor_data::{TdFilter, TensorData};
 pub mod tensor_history_node;
@@ -61,6 +67,7 @@ pub struct DTProject {
     pool: Arc<SqlitePool>,
     pub path: String,
     text_history: OnceCell<Arc<TextHistory>>,
+    lineage_graph: std::sync::Mutex<Option<(LineageVersion, Arc<LineageGraph>)>>,
     pub tables: Arc<OnceCell<DTProjectTableStatus>>,
     pub is_shared: bool,
 }
@@ -112,8 +119,8 @@ fn schedule_eviction(path: String, generation: u64) {
     });
 }
 
-#[derive(Debug, Serialize, Copy, Clone)]
-enum DTProjectTable {
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq, Eq)]
+pub enum DTProjectTable {
     TensorHistoryNode,
     TensorData,
     TextHistory,
@@ -124,6 +131,33 @@ enum DTProjectTable {
     Clip,
 }
 
+impl DTProjectTable {
+    pub const ALL: [DTProjectTable; 8] = [
+        DTProjectTable::TensorHistoryNode,
+        DTProjectTable::TensorData,
+        DTProjectTable::TextHistory,
+        DTProjectTable::TextLineage,
+        DTProjectTable::TensorMoodboardData,
+        DTProjectTable::Tensors,
+        DTProjectTable::Thumbs,
+        DTProjectTable::Clip,
+    ];
+
+    /// The name of the table in the project file
+    pub fn table_name(&self) -> &'static str {
+        match self {
+            DTProjectTable::TensorHistoryNode => "tensorhistorynode",
+            DTProjectTable::TensorData => "tensordata",
+            DTProjectTable::TextHistory => "texthistorynode",
+            DTProjectTable::TextLineage => "textlineagenode",
+            DTProjectTable::TensorMoodboardData => "tensormoodboarddata",
+            DTProjectTable::Tensors => "tensors",
+            DTProjectTable::Thumbs => "thumbnailhistorynode",
+            DTProjectTable::Clip => "clip",
+        }
+    }
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct DTProjectTableStatus {
     pub has_tensor_history: bool,
@@ -136,6 +170,21 @@ pub struct DTProjectTableStatus {
     pub has_clip: bool,
 }
 
+impl DTProjectTableStatus {
+    pub fn has(&self, table: &DTProjectTable) -> bool {
+        match table {
+            DTProjectTable::TensorHistoryNode => self.has_tensor_history,
+            DTProjectTable::TextHistory => self.has_text_history,
+            DTProjectTable::TextLineage => self.has_text_lineage,
+            DTProjectTable::TensorMoodboardData => self.has_moodboard,
+            DTProjectTable::Tensors => self.has_tensors,
+            DTProjectTable::Thumbs => self.has_thumbs,
+            DTProjectTable::Clip => self.has_clip,
+            DTProjectTable::TensorData => self.has_tensor_data,
+        }
+    }
+}
+
 impl DTProject {
     async fn new(db_path: &str, is_shared: bool) -> Result<Self, Error> {
         let connect_string = format!("sqlite:{}?mode=ro", db_path);
@@ -146,6 +195,7 @@ impl DTProject {
             path: db_path.to_string(),
             tables: Arc::new(OnceCell::new()),
             text_history: OnceCell::new(),
+            lineage_graph: std::sync::Mutex::new(None),
             is_shared,
         };
 
@@ -216,7 +266,7 @@ impl DTProject {
                         "tensors" => status.has_tensors = true,
                         "thumbnailhistorynode" => status.has_thumbs = true,
                         "texthistorynode" => status.has_text_history = true,
-                        "textlineage" => status.has_text_lineage = true,
+                        "textlineagenode" => status.has_text_lineage = true,
                         "clip" => status.has_clip = true,
                         "tensordata" => status.has_tensor_data = true,
                         _ => {}
@@ -231,18 +281,7 @@ impl DTProject {
     }
 
     async fn check_table(&self, table: &DTProjectTable) -> Result<bool, Error> {
-        let status = self.check_tables().await?;
-
-        let has_table = match table {
-            DTProjectTable::TensorHistoryNode => status.has_tensor_history,
-            DTProjectTable::TextHistory => status.has_text_history,
-            DTProjectTable::TextLineage => status.has_text_lineage,
-            DTProjectTable::TensorMoodboardData => status.has_moodboard,
-            DTProjectTable::Tensors => status.has_tensors,
-            DTProjectTable::Thumbs => status.has_thumbs,
-            DTProjectTable::Clip => status.has_clip,
-            DTProjectTable::TensorData => status.has_tensor_data,
-        };
+        let has_table = self.check_tables().await?.has(table);
 
         if !has_table {
             return Err(Error::Protocol("Table not found".to_string()));
@@ -278,6 +317,19 @@ impl DTProject {
     //            the numeric id can be joined with the type (ie: tensor_history_, depth_map_) to get
     //            the full tensor name
 
+    /// Decodes a tensor, or returns it from the shared tensor cache
+    pub async fn get_tensor_decoded(&self, name: &str) -> anyhow::Result<Arc<Tensor>> {
+        if let Some(tensor) = TENSOR_CACHE.get(&self.path, name) {
+            return Ok(tensor);
+        }
+        let tensor_raw = self.get_tensor_raw(name).await?;
+        // fpzip decoding is cpu bound
+        let tensor = tokio::task::spawn_blocking(move || Tensor::try_from(tensor_raw)).await??;
+        let tensor = Arc::new(tensor);
+        TENSOR_CACHE.insert(&self.path, name, tensor.clone());
+        Ok(tensor)
+    }
+
     // KEEP - should rename to get_tensor
     pub async fn get_tensor_raw(&self, name: &str) -> Result<TensorRaw, Error> {
         self.check_table(&DTProjectTable::Tensors).await?;
@@ -291,6 +343,11 @@ impl DTProject {
         let data_type: i32 = row.get(2);
         let dim: Vec<u8> = row.get(3);
         let data: Vec<u8> = row.get(4);
+        if dim.len() < 16 {
+            return Err(Error::Decode(
+                format!("tensor {} has {} bytes of dimensions", name, dim.len()).into(),
+            ));
+        }
 
         let n = i32::from_le_bytes(dim[0..4].try_into().ok().unwrap());
         let height = i32::from_le_bytes(dim[4..8].try_into().ok().unwrap());
@@ -544,6 +601,26 @@ impl DTProject {
         history.get_edit(lineage, edit).ok_or(Error::RowNotFound)
     }
 
+    /// Lists every prompt revision in a text lineage, with the rowids of the
+    /// tensorhistorynodes that were generated from each revision
+    pub async fn get_prompt_timeline(&self, text_lineage: i64) -> Result<PromptTimeline, Error> {
+        let history = self.get_text_history().await?;
+        let mut timeline = history.get_timeline(text_lineage);
+
+        let mut generations: HashMap<i64, Vec<i64>> = HashMap::new();
+        for node in self.get_tensor_history_nodes(None, None).await? {
+            let fb = node.data();
+            if history.resolve_lineage(fb.text_lineage()) == timeline.lineage {
+                generations.entry(fb.text_edits()).or_default().push(node.rowid);
+            }
+        }
+        for revision in timeline.revisions.iter_mut() {
+            revision.generations = generations.remove(&revision.text_edits).unwrap_or_default();
+        }
+
+        Ok(timeline)
+    }
+
     pub fn raw(&'_ self) -> DTProjectRaw<'_> {
         DTProjectRaw::new(self)
     }
@@ -0,0 +1,215 @@
+use std::collections::BTreeSet;
+
+use flatbuffers::VOffsetT;
+use sqlx::{query, query_as, AssertSqlSafe, Row};
+
+use crate::projects_db::{
+    dt_project::DTProjectTable,
+    dtos::project::{CompatibilityReport, FlatbufferSchema},
+    fbs, DTProject,
+};
+
+/// Rows checked per table, newest first. Fields added by a Draw Things update only appear
+/// in rows written after it.
+pub const SCHEMA_SAMPLE_ROWS: i64 = 200;
+
+/// Tables Draw Things projects are known to have that aren't a `DTProjectTable`.
+/// thumbnailhistoryhalfnode is read by `get_thumb_half`, but isn't required.
+const OTHER_TABLES: [&str; 1] = ["thumbnailhistoryhalfnode"];
+
+impl DTProject {
+    /// Compares the tables and flatbuffer fields in the project with the known schema
+    pub async fn check_compatibility(&self) -> Result<CompatibilityReport, sqlx::Error> {
+        let status = self.check_tables().await?.clone();
+        let tables: Vec<String> = query_as::<_, (String,)>(
+            "SELECT name FROM sqlite_master WHERE type='table' ORDER BY name",
+        )
+        .fetch_all(&*self.pool)
+        .await?
+        .into_iter()
+        .map(|(name,)| name)
+        .filter(|name| !name.starts_with("sqlite_"))
+        .collect();
+
+        let missing_tables = DTProjectTable::ALL
+            .into_iter()
+            .filter(|table| !status.has(table))
+            .collect();
+        let unknown_tables: Vec<String> = tables
+            .iter()
+            .filter(|name| !is_known_table(name))
+            .cloned()
+            .collect();
+
+        let mut flatbuffers = Vec::new();
+        for table in DTProjectTable::ALL {
+            let Some(known_fields) = known_fields(table) else {
+                continue;
+            };
+            if !status.has(&table) {
+                continue;
+            }
+            flatbuffers.push(self.check_flatbuffers(table, known_fields).await?);
+        }
+
+        Ok(CompatibilityReport {
+            compatible: unknown_tables.is_empty()
+                && flatbuffers
+                    .iter()
+                    .all(|fb| fb.invalid_rows == 0 && fb.unknown_fields.is_empty()),
+            tables,
+            missing_tables,
+            unknown_tables,
+            flatbuffers,
+        })
+    }
+
+    async fn check_flatbuffers(
+        &self,
+        table: DTProjectTable,
+        known_fields: usize,
+    ) -> Result<FlatbufferSchema, sqlx::Error> {
+        let sql = format!(
+            "SELECT p FROM {} ORDER BY rowid DESC LIMIT {}",
+            table.table_name(),
+            SCHEMA_SAMPLE_ROWS
+        );
+        let rows = query(AssertSqlSafe(sql)).fetch_all(&*self.pool).await?;
+
+        let mut present = BTreeSet::new();
+        let mut invalid_rows = 0;
+        for row in &rows {
+            let Some(p) = row.try_get::<Option<Vec<u8>>, _>(0)? else {
+                invalid_rows += 1;
+                continue;
+            };
+            if !verify_flatbuffer(table, &p) {
+                invalid_rows += 1;
+            }
+            if let Some(slots) = vtable_slots(&p) {
+                present.extend(
+                    slots
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, o)| **o != 0)
+                        .map(|(i, _)| i),
+                );
+            }
+        }
+
+        let (fields_present, unknown_fields): (Vec<usize>, Vec<usize>) =
+            present.into_iter().partition(|slot| *slot < known_fields);
+        Ok(FlatbufferSchema {
+            table,
+            rows_checked: rows.len() as i64,
+            invalid_rows,
+            known_fields,
+            fields_present,
+            unknown_fields,
+        })
+    }
+}
+
+/// Known tables and their `{table}__f{field}` index tables
+fn is_known_table(name: &str) -> bool {
+    OTHER_TABLES.contains(&name)
+        || DTProjectTable::ALL.iter().any(|table| {
+            let known = table.table_name();
+            name == known || name.starts_with(&format!("{known}__"))
+        })
+}
+
+/// Number of fields in the generated schema of the table's `p` column, from the vtable
+/// offset of its last field. None for tables that don't hold a flatbuffer.
+fn known_fields(table: DTProjectTable) -> Option<usize> {
+    let last: VOffsetT = match table {
+        DTProjectTable::TensorHistoryNode => fbs::TensorHistoryNode::VT_AUDIO,
+        DTProjectTable::TensorData => fbs::TensorData::VT_CUSTOM_ID,
+        DTProjectTable::TextHistory => fbs::TextHistoryNode::VT_MODIFICATIONS,
+        DTProjectTable::TensorMoodboardData => fbs::TensorMoodboardData::VT_WEIGHT,
+        DTProjectTable::Clip => fbs::Clip::VT_AUDIO_ID,
+        DTProjectTable::TextLineage | DTProjectTable::Tensors | DTProjectTable::Thumbs => {
+            return None
+        }
+    };
+    Some((last as usize - 4) / 2 + 1)
+}
+
+fn verify_flatbuffer(table: DTProjectTable, bytes: &[u8]) -> bool {
+    match table {
+        DTProjectTable::TensorHistoryNode => fbs::root_as_tensor_history_node(bytes).is_ok(),
+        DTProjectTable::TensorData => fbs::root_as_tensor_data(bytes).is_ok(),
+        DTProjectTable::TextHistory => fbs::root_as_text_history_node(bytes).is_ok(),
+        DTProjectTable::TensorMoodboardData => fbs::root_as_tensor_moodboard_data(bytes).is_ok(),
+        DTProjectTable::Clip => fbs::root_as_clip(bytes).is_ok(),
+        DTProjectTable::TextLineage | DTProjectTable::Tensors | DTProjectTable::Thumbs => true,
+    }
+}
+
+/// Field offsets from the vtable of a flatbuffer's root table, one per slot. An offset of 0
+/// means the field is not set. Unlike the generated accessors, this sees fields that aren't
+/// in the schema.
+pub fn vtable_slots(buf: &[u8]) -> Option<Vec<u16>> {
+    let read_u16 = |at: usize| Some(u16::from_le_bytes(buf.get(at..at + 2)?.try_into().ok()?));
+    let root = u32::from_le_bytes(buf.get(0..4)?.try_into().ok()?) as usize;
+    let vtable_offset = i32::from_le_bytes(buf.get(root..root + 4)?.try_into().ok()?);
+    let vtable = usize::try_from(root as i64 - vtable_offset as i64).ok()?;
+    let vtable_len = read_u16(vtable)? as usize;
+    (0..vtable_len.saturating_sub(4) / 2)
+        .map(|slot| read_u16(vtable + 4 + slot * 2))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flatbuffers::FlatBufferBuilder;
+
+    /// A table with i64 fields set at the given slots
+    fn table_with_slots(slots: &[usize]) -> Vec<u8> {
+        let mut fbb = FlatBufferBuilder::new();
+        let start = fbb.start_table();
+        for slot in slots {
+            fbb.push_slot::<i64>((4 + slot * 2) as VOffsetT, 1, 0);
+        }
+        let table = fbb.end_table(start);
+        fbb.finish_minimal(table);
+        fbb.finished_data().to_vec()
+    }
+
+    #[test]
+    fn test_vtable_slots() {
+        let buf = table_with_slots(&[0, 2]);
+        let slots = vtable_slots(&buf).unwrap();
+        assert_eq!(slots.len(), 3);
+        assert!(slots[0] != 0);
+        assert_eq!(slots[1], 0);
+        assert!(slots[2] != 0);
+
+        assert_eq!(vtable_slots(&[]), None);
+        assert_eq!(vtable_slots(&[200, 0, 0, 0]), None);
+    }
+
+    #[test]
+    fn test_known_fields() {
+        // clip_id, count, frames_per_second, width, height, audio_id
+        assert_eq!(known_fields(DTProjectTable::Clip), Some(6));
+        assert_eq!(known_fields(DTProjectTable::Tensors), None);
+
+        // a clip written by a newer version, with a field past audio_id
+        let buf = table_with_slots(&[0, 1, 8]);
+        let slots = vtable_slots(&buf).unwrap();
+        assert_eq!(slots.len(), 9);
+        assert!(slots[8] != 0);
+        assert!(verify_flatbuffer(DTProjectTable::Clip, &buf));
+    }
+
+    #[test]
+    fn test_is_known_table() {
+        assert!(is_known_table("tensorhistorynode"));
+        assert!(is_known_table("textlineagenode__f6"));
+        assert!(is_known_table("thumbnailhistoryhalfnode"));
+        assert!(!is_known_table("tensorhistorynodes"));
+        assert!(!is_known_table("audio"));
+    }
+}
@@ -167,6 +167,17 @@ impl TensorHistoryNode {
             .expect("flatbuffer already validated at construction")
     }
 
+    /// Whether the node was generated with a mask, decided as the index decides `has_mask`: from
+    /// the canvas layers if tensordata was loaded and has any, otherwise from the node itself
+    pub fn has_mask(&self) -> bool {
+        match self.tensordata.as_deref() {
+            Some(tensordata) if !tensordata.is_empty() => {
+                tensordata.iter().any(|td| td.data().mask_id() != 0)
+            }
+            _ => self.data().mask_id() != 0,
+        }
+    }
+
     /// Returns the positive prompt, preferring the legacy-resolved value over
     /// the flatbuffer field. Returns None only if both are absent/empty.
     pub fn prompt(&self) -> Option<&str> {
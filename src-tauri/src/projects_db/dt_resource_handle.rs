@@ -1,11 +1,13 @@
 use anyhow::{anyhow, Result};
+use image::{DynamicImage, GrayImage, ImageFormat, RgbImage, RgbaImage};
 use std::convert::TryInto;
+use std::io::Cursor;
 use std::sync::Arc;
 use tokio::sync::OnceCell;
 
 use crate::{
-    ResourceHandle, Tensor, projects_db::{
-        DTProject, DtProjectRef, DtResourceRef, ProjectsDb, decode_audio, dt_project::{TdFilter, TensorData, TensorHistoryNode, ThnData, ThnFilter, TmdFilter}, dtos::tensor::TensorRaw, enums::PartialThnDtResourceHandle, extract_jpeg_slice, tensors::decompress_fzip,
+    resize_pixels, ResizeOptions, ResourceHandle, Tensor, projects_db::{
+        DTProject, DtProjectRef, DtResourceRef, ProjectsDb, decode_audio, dt_project::{TdFilter, TensorData, TensorHistoryNode, TensorMoodboardData, ThnData, ThnFilter}, dtos::tensor::{TensorRaw, PREFIX_POSE}, enums::{PartialThnDtResourceHandle, ThnRef}, extract_jpeg_slice, pose::render_pose, tensors::decompress_fzip, composite::{composite_layers, pixels_to_rgba}, inpaint::{render_inpaint_view, InpaintViewOptions}, dtos::export::TensorFileFormat, encode_tensor_file,
     },
 };
 
@@ -25,18 +27,22 @@ pub struct DtResourceHandle {
 
 #[async_trait::async_trait]
 impl ResourceHandle for DtResourceHandle {
-    async fn get_tensor(&self) -> Result<Option<Tensor>> {
+    async fn get_tensor(&self) -> Result<Option<Arc<Tensor>>> {
         if self.resource.is_thumb() {
             return Ok(None);
         }
 
         if let Some(name) = self.get_tensor_name().await? {
             let dtp = self.get_project().await?;
-            let tensor_raw = dtp.get_tensor_raw(&name).await?;
-            let tensor: Tensor = tensor_raw.try_into()?;
-            println!(
-                "got tensor ({},{},{},{})",
-                tensor.n, tensor.height, tensor.width, tensor.channels
+            // the tensor cache shares the decoded buffer
+            let tensor = dtp.get_tensor_decoded(&name).await?;
+            log::debug!(
+                "got tensor {} ({},{},{},{})",
+                name,
+                tensor.n,
+                tensor.height,
+                tensor.width,
+                tensor.channels
             );
 
             return Ok(Some(tensor));
@@ -45,10 +51,10 @@ impl ResourceHandle for DtResourceHandle {
         Ok(None)
     }
 
-    async fn get_lossless(&self, size: Option<u32>) -> Result<Option<Vec<u8>>> {
+    async fn get_lossless(&self, resize: Option<ResizeOptions>) -> Result<Option<Vec<u8>>> {
         if let Some(tensor) = self.get_tensor().await? {
             let history_node = self.get_history_node().await?;
-            let png = tensor.to_png(history_node, size)?;
+            let png = tensor.to_png(history_node, resize.as_ref())?;
             Ok(Some(png))
         } else {
             Ok(None)
@@ -98,7 +104,35 @@ impl ResourceHandle for DtResourceHandle {
     }
 
     async fn get_frames(&self, preview: bool) -> Result<Option<Vec<Box<dyn ResourceHandle>>>> {
-        return Err(anyhow::anyhow!("Frames not yet implemented"));
+        // only history nodes can be clips
+        if !self.resource.is_tensor_history_node() {
+            return Ok(None);
+        }
+        let node = match self.get_history_node().await? {
+            Some(node) if node.data().clip_id() > 0 => node,
+            _ => return Ok(None),
+        };
+
+        let dtp = self.get_project().await?;
+        let frames = dtp.get_histories_from_clip(node.rowid).await?;
+        if frames.is_empty() {
+            return Ok(None);
+        }
+
+        // frames reference their thumb or tensor directly, so no further lookups are needed
+        let handles = frames
+            .into_iter()
+            .map(|frame| {
+                let resource = match preview {
+                    true => RR::Thumb(frame.preview_id),
+                    false => RR::Tensor(frame.tensor_id),
+                };
+                Box::new(DtResourceHandle::new(self.project.clone(), resource))
+                    as Box<dyn ResourceHandle>
+            })
+            .collect();
+
+        Ok(Some(handles))
     }
 }
 
@@ -219,25 +253,12 @@ impl DtResourceHandle {
             return Ok(Some(name.clone()));
         }
 
-        let dtp = self.get_project().await?;
-
         // handle moodboard case
         if self.resource.is_tensor_history_node() && res.is_moodboard() {
-            if let Some(history_node) = self.get_history_node().await? {
-                let moodboard_data = dtp
-                    .get_tensor_moodboard_data(TmdFilter::LineageTime(
-                        history_node.lineage,
-                        history_node.logical_time,
-                    ))
-                    .await?;
-
-                if let ThnR::Moodboard(idx) = res {
-                    return Ok(moodboard_data
-                        .iter()
-                        .find(|mbd| mbd.idx == *idx as i64)
-                        .map(|mbd| mbd.tensor_name.clone()));
-                }
-            }
+            return Ok(self
+                .get_moodboard_entry()
+                .await?
+                .map(|mbd| mbd.tensor_name.clone()));
         }
 
         // resolve to a list of tensordata
@@ -317,6 +338,219 @@ impl DtResourceHandle {
         }
     }
 
+    /// Returns the moodboard entry for a `ThnResource::Moodboard` resource
+    async fn get_moodboard_entry(&self) -> Result<Option<&TensorMoodboardData>> {
+        let idx = match &self.resource {
+            RR::TensorHistoryNode(_, ThnR::Moodboard(idx)) => *idx as i64,
+            _ => return Ok(None),
+        };
+
+        Ok(self
+            .get_history_node()
+            .await?
+            .and_then(|node| node.moodboard.as_ref())
+            .and_then(|moodboard| moodboard.iter().find(|mbd| mbd.idx == idx)))
+    }
+
+    /// Returns the weight of a moodboard image, or None if the resource is not a moodboard image
+    pub async fn get_moodboard_weight(&self) -> Result<Option<f32>> {
+        Ok(self.get_moodboard_entry().await?.map(|mbd| mbd.weight))
+    }
+
+    /// Renders a pose tensor as OpenPose style skeletons, at the size of the node's canvas, or
+    /// 512x512 without a node. With `overlay`, the skeletons are drawn over the generated image
+    pub async fn get_pose_png(&self, overlay: bool) -> Result<Option<Vec<u8>>> {
+        match self.get_tensor_name().await? {
+            Some(name) if name.starts_with(PREFIX_POSE) => {}
+            _ => return Ok(None),
+        }
+        let tensor = match self.get_tensor().await? {
+            Some(tensor) => tensor,
+            None => return Ok(None),
+        };
+        let keypoints = tensor
+            .as_f32()
+            .ok_or_else(|| anyhow!("Pose tensor is not f32"))?;
+
+        let node = self.get_history_node().await?;
+        let (width, height) = node.map_or((512, 512), |node| {
+            let fb = node.data();
+            (fb.start_width() as u32 * 64, fb.start_height() as u32 * 64)
+        });
+
+        // the pose is drawn over the image generated from it
+        let mut background = None;
+        if let (true, Some(node)) = (overlay, node) {
+            let tensor_id = node.data().tensor_id();
+            if tensor_id > 0 {
+                let dtp = self.get_project().await?;
+                let image = dtp
+                    .get_tensor_decoded(&format!("tensor_history_{}", tensor_id))
+                    .await?;
+                let pixels = image.to_pixel_data(None)?;
+                let channels = image.channels as usize;
+                background = pixels_to_rgba(&pixels, image.width, image.height, channels)
+                    .map(|image| DynamicImage::from(image).to_rgb8());
+            }
+        }
+
+        Ok(Some(render_pose(keypoints, width, height, background)?))
+    }
+
+    /// Encodes the decompressed tensor of the resource as `.npy` or `.safetensors`, keeping
+    /// its shape and dtype. Returns the tensor's name with the file.
+    pub async fn get_tensor_file(
+        &self,
+        format: TensorFileFormat,
+    ) -> Result<Option<(String, Vec<u8>)>> {
+        if self.resource.is_thumb() {
+            return Ok(None);
+        }
+        let Some(name) = self.get_tensor_name().await? else {
+            return Ok(None);
+        };
+        let dtp = self.get_project().await?;
+        let tensor = dtp.get_tensor_decoded(&name).await?;
+        let file = encode_tensor_file(&name, &tensor, format)?;
+        Ok(Some((name, file)))
+    }
+
+    /// Reconstructs the canvas as it was at the node, with the tensors of its lineage placed at
+    /// their offsets. Returns a PNG, transparent where nothing was on the canvas.
+    pub async fn get_composite_png(
+        &self,
+        resize: Option<ResizeOptions>,
+    ) -> Result<Option<Vec<u8>>> {
+        let Some(node) = self.get_history_node().await? else {
+            return Ok(None);
+        };
+        let dtp = self.get_project().await?;
+
+        // decoded tensors are shared with the other views through TENSOR_CACHE
+        let mut tensors = Vec::new();
+        for placement in dtp.get_canvas_placements(node.rowid).await? {
+            // tensors can be missing from projects that were cleaned up
+            if let Ok(tensor) = dtp.get_tensor_decoded(&placement.tensor_name).await {
+                tensors.push((placement, tensor));
+            }
+        }
+        if tensors.is_empty() {
+            return Ok(None);
+        }
+
+        // compositing and encoding large canvases is cpu bound
+        tokio::task::spawn_blocking(move || -> Result<Option<Vec<u8>>> {
+            let mut layers = Vec::new();
+            for (placement, tensor) in tensors {
+                let pixels = tensor.to_pixel_data(None)?;
+                let channels = tensor.channels as usize;
+                if let Some(image) = pixels_to_rgba(&pixels, tensor.width, tensor.height, channels)
+                {
+                    layers.push(placement.layer(image));
+                }
+            }
+            if layers.is_empty() {
+                return Ok(None);
+            }
+
+            let mut canvas = composite_layers(&layers)?;
+            if let Some(resize) = resize {
+                let (width, height) = canvas.dimensions();
+                let (pixels, width, height) =
+                    resize_pixels(canvas.as_raw(), width, height, 4, &resize)?;
+                canvas = RgbaImage::from_raw(width, height, pixels)
+                    .ok_or_else(|| anyhow!("Invalid canvas size"))?;
+            }
+
+            let mut png = Vec::new();
+            canvas.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+            Ok(Some(png))
+        })
+        .await?
+    }
+
+    /// Renders a before/after view of an inpainting node: the image it was given, the same
+    /// image with its mask drawn over it, and the result. Returns None if the node has no mask.
+    ///
+    /// The input is the canvas layer the mask was drawn on. Nodes recorded without tensordata
+    /// only have a mask id, and were generated from their parent's image.
+    pub async fn get_inpaint_png(
+        &self,
+        options: InpaintViewOptions,
+        resize: Option<ResizeOptions>,
+    ) -> Result<Option<Vec<u8>>> {
+        let Some(node) = self.get_history_node().await? else {
+            return Ok(None);
+        };
+        let fb = node.data();
+        if !node.has_mask() || fb.tensor_id() <= 0 {
+            return Ok(None);
+        }
+        let dtp = self.get_project().await?;
+
+        let masked_layer = node
+            .tensordata
+            .iter()
+            .flat_map(|td| td.iter())
+            .filter(|td| td.data().mask_id() != 0)
+            .max_by_key(|td| td.idx);
+        let (mask_id, source_id) = match masked_layer {
+            Some(td) => (td.data().mask_id(), td.data().tensor_id()),
+            None => {
+                let graph = dtp.get_lineage_graph().await?;
+                let parent_tensor_id = graph
+                    .get(node.rowid)
+                    .and_then(|n| n.parent)
+                    .and_then(|parent| graph.get(parent))
+                    .map_or(0, |parent| parent.tensor_id);
+                (fb.mask_id(), parent_tensor_id)
+            }
+        };
+        if source_id <= 0 {
+            return Ok(None);
+        }
+
+        let source = dtp
+            .get_tensor_decoded(&format!("tensor_history_{}", source_id))
+            .await?;
+        let result = dtp
+            .get_tensor_decoded(&format!("tensor_history_{}", fb.tensor_id()))
+            .await?;
+        let mask = dtp
+            .get_tensor_decoded(&format!("binary_mask_{}", mask_id))
+            .await?;
+        let blur = (fb.mask_blur(), fb.mask_blur_outset());
+
+        // decoding, blurring the mask and encoding are cpu bound
+        tokio::task::spawn_blocking(move || -> Result<Option<Vec<u8>>> {
+            let rgb = |tensor: &Tensor| -> Result<RgbImage> {
+                let pixels = tensor.to_pixel_data(None)?;
+                let channels = tensor.channels as usize;
+                pixels_to_rgba(&pixels, tensor.width, tensor.height, channels)
+                    .map(|image| DynamicImage::from(image).to_rgb8())
+                    .ok_or_else(|| anyhow!("Unsupported image tensor"))
+            };
+            let source = rgb(&source)?;
+            let result = rgb(&result)?;
+            let mask = GrayImage::from_raw(mask.width, mask.height, mask.to_pixel_data(None)?)
+                .ok_or_else(|| anyhow!("Invalid mask size"))?;
+
+            let mut view = render_inpaint_view(&source, &mask, &result, blur, &options)?;
+            if let Some(resize) = resize {
+                let (width, height) = view.dimensions();
+                let (pixels, width, height) =
+                    resize_pixels(view.as_raw(), width, height, 3, &resize)?;
+                view = RgbImage::from_raw(width, height, pixels)
+                    .ok_or_else(|| anyhow!("Invalid view size"))?;
+            }
+
+            let mut png = Vec::new();
+            view.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+            Ok(Some(png))
+        })
+        .await?
+    }
+
     pub fn sub(&self) -> Result<PartialThnDtResourceHandle> {
         PartialThnDtResourceHandle::try_from(self)
     }
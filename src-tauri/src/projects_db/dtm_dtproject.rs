@@ -1,8 +1,12 @@
+use std::io::Cursor;
+
+use dashmap::DashMap;
 use tauri::{
-    http::{self, Response, StatusCode, Uri},
+    http::{self, header, HeaderValue, Response, StatusCode, Uri},
     UriSchemeResponder,
 };
 use anyhow::Context;
+use entity::enums::WatchFolderKind;
 
 use crate::{
     projects_db::{
@@ -10,9 +14,11 @@ use crate::{
         decode_audio,
         dt_resource_handle::DtResourceHandle,
         enums::{DtProjectRef, DtResourceRef, ThnRef, ThnResource},
+        inpaint::{parse_hex_color, InpaintViewOptions},
+        thumb_cache::{project_version, CacheKey, ThumbCache},
         DTProject, ProjectsDb,
     },
-    ResourceHandle,
+    ResizeOptions, ResourceHandle,
 };
 
 const MISSING_SVG: &str = r##"<?xml version="1.0" encoding="utf-8"?>
@@ -22,9 +28,25 @@ const MISSING_SVG: &str = r##"<?xml version="1.0" encoding="utf-8"?>
   </g>
 </svg>"##;
 
+/// Long edge of thumbnails made for loose images
+const IMAGE_FILE_THUMB_SIZE: u32 = 512;
+
 // dtm://dtm_dtproject/thumbhalf/5/82988
 // dtm://dtm_dtproject/{item type}/{project_id}/{item id}
 
+// pose tensors are rendered as OpenPose skeletons, add overlay=1 to draw them over the image
+// dtm://dtm_dtproject/tensor/{project_id}/pose_{id}?node={node_id}&overlay=1
+
+// canvas layers, masks and moodboard images are referenced by index through their node
+// dtm://dtm_dtproject/{canvas|mask|moodboard}/{project_id}/{index}?node={node_id}
+// moodboard weights are served with the node, in its moodboard entries
+
+// the canvas reconstructed at a node, from the tensors of its lineage and their offsets
+// dtm://dtm_dtproject/composite/{project_id}/{node_id}
+
+// before/after view of an inpainting node: source, source with the mask drawn over it, result
+// dtm://dtm_dtproject/inpaint/{project_id}/{node_id}?tint=ff0040&opacity=0.5&blur=1
+
 // note: while audio is technically a tensor type, it is better served from a different route
 // dtm://dtm_dtproject/audio/{project_id}/{item_id}
 // for audio, item_id is the node_id
@@ -35,8 +57,13 @@ pub struct DTPResource {
     pub project_id: i64,
     pub item_id: String,
     pub node: Option<i64>,
-    pub size: Option<u32>,
+    /// `s=256` or `s=256x128`, with optional `fit` (contain, cover, exact) and `filter`
+    /// (lanczos3, catmullRom, box)
+    pub resize: Option<ResizeOptions>,
     pub mask: Option<String>,
+    pub overlay: bool,
+    /// `tint`, `opacity` and `blur` for inpaint views
+    pub inpaint: InpaintViewOptions,
     pub range_start: Option<usize>,
     pub range_end: Option<usize>,
 }
@@ -79,27 +106,72 @@ fn parse_request<T>(request: &http::Request<T>) -> Option<DTPResource> {
     }
 
     if let Some(query) = uri.query() {
+        let mut fit = None;
+        let mut filter = None;
         for q in query.split('&') {
             let (key, value) = q.split_once('=').unwrap();
             match key {
                 "node" => resource.node = Some(value.parse().unwrap()),
-                "s" => resource.size = Some(value.parse().unwrap()),
+                "s" => resource.resize = ResizeOptions::parse_size(value).ok(),
+                "fit" => fit = value.parse().ok(),
+                "filter" => filter = value.parse().ok(),
                 "mask" => resource.mask = Some(value.to_string()),
+                "overlay" => resource.overlay = value == "1" || value == "true",
+                "tint" => {
+                    if let Some(tint) = parse_hex_color(value) {
+                        resource.inpaint.tint = tint;
+                    }
+                }
+                "opacity" => {
+                    if let Ok(opacity) = value.parse() {
+                        resource.inpaint.opacity = opacity;
+                    }
+                }
+                "blur" => resource.inpaint.blur = value == "1" || value == "true",
                 _ => (),
             }
         }
+        if let Some(resize) = resource.resize.as_mut() {
+            resize.fit = fit.unwrap_or_default();
+            resize.filter = filter.unwrap_or_default();
+        }
     }
 
     Some(resource)
 }
 
+/// Serves dtm:// requests from the current library. The library is looked up on each
+/// request, since connecting to a portable library replaces it.
 pub struct DtmProtocol {
-    pdb: ProjectsDb,
+    cache: Option<ThumbCache>,
+    /// stored project fingerprints, so cache lookups don't query the library
+    fingerprints: DashMap<i64, String>,
 }
 
 impl DtmProtocol {
-    pub fn new(pdb: ProjectsDb) -> Self {
-        Self { pdb }
+    pub fn new(cache: Option<ThumbCache>) -> Self {
+        Self {
+            cache,
+            fingerprints: DashMap::new(),
+        }
+    }
+
+    /// Forgets the stored fingerprints. Project ids are only meaningful within a single
+    /// library, so this is called when the library changes.
+    pub fn clear_fingerprints(&self) {
+        self.fingerprints.clear();
+    }
+
+    pub fn cache(&self) -> Option<&ThumbCache> {
+        self.cache.as_ref()
+    }
+
+    /// Removes the cached responses of a removed project
+    pub async fn invalidate_project(&self, project_id: i64) {
+        self.fingerprints.remove(&project_id);
+        if let Some(cache) = &self.cache {
+            cache.invalidate_project(project_id).await;
+        }
     }
 
     pub async fn dtm_dtproject_protocol<T>(
@@ -141,26 +213,135 @@ impl DtmProtocol {
 
         let req = req.unwrap();
 
+        let cache_key = match self.cache_key(&req, request.uri()).await {
+            Ok(key) => key,
+            Err(e) => {
+                log::warn!("Failed to get cache key for {}: {}", request.uri(), e);
+                None
+            }
+        };
+        let (cache, key) = match (&self.cache, cache_key) {
+            (Some(cache), Some(key)) => (cache, key),
+            _ => return self.route(&req).await,
+        };
+
+        let etag = key.etag();
+        let if_none_match = request.headers().get(header::IF_NONE_MATCH);
+        if if_none_match.is_some_and(|value| value.as_bytes() == etag.as_bytes()) {
+            let mut response = Response::builder()
+                .status(StatusCode::NOT_MODIFIED)
+                .body(Vec::new())?;
+            set_cache_headers(&mut response, &etag)?;
+            return Ok(response);
+        }
+
+        if let Some(body) = cache.get(&key).await {
+            let mut response = Response::builder()
+                .status(StatusCode::OK)
+                .header("Content-Type", key.content_type)
+                .header("Access-Control-Allow-Origin", "*")
+                .header("Access-Control-Allow-Methods", "GET")
+                .body(body)?;
+            set_cache_headers(&mut response, &etag)?;
+            return Ok(response);
+        }
+
+        let mut response = self.route(&req).await?;
+        if response.status() == StatusCode::OK {
+            if let Err(e) = cache.put(&key, response.body()).await {
+                log::warn!("Failed to cache {}: {}", request.uri(), e);
+            }
+            set_cache_headers(&mut response, &etag)?;
+        }
+        Ok(response)
+    }
+
+    /// Thumbnails, resized tensors and reconstructed canvases are cached, keyed by the request
+    /// (which holds the project id and the item's id) and the project version. Full size tensors
+    /// are too large to be worth caching.
+    async fn cache_key(&self, req: &DTPResource, uri: &Uri) -> anyhow::Result<Option<CacheKey>> {
+        let Some(cache) = &self.cache else {
+            return Ok(None);
+        };
+        let content_type = match req.item_type.as_str() {
+            "thumb" | "thumbhalf" => "image/jpeg",
+            "tensor" | "canvas" | "mask" | "moodboard" if req.resize.is_some() => "image/png",
+            "composite" | "inpaint" => "image/png",
+            _ => return Ok(None),
+        };
+
+        let pdb = ProjectsDb::get().await?;
+        let project_path = pdb.get_project_path(req.project_id).await?;
+        let fingerprint = match self.fingerprints.get(&req.project_id) {
+            Some(fingerprint) => fingerprint.clone(),
+            None => {
+                let fingerprint = pdb.get_project(req.project_id).await?.fingerprint;
+                self.fingerprints
+                    .insert(req.project_id, fingerprint.clone());
+                fingerprint
+            }
+        };
+        let version = project_version(&fingerprint, &project_path);
+        cache.check_version(req.project_id, &version).await;
+
+        Ok(Some(CacheKey {
+            project_id: req.project_id,
+            version,
+            item: uri
+                .path_and_query()
+                .map_or_else(|| uri.path().to_string(), |pq| pq.to_string()),
+            content_type,
+        }))
+    }
+
+    async fn route(&self, req: &DTPResource) -> anyhow::Result<Response<Vec<u8>>> {
         match req.item_type.as_str() {
-            "thumb" => thumb(req.project_id, &req.item_id, false).await,
-            "thumbhalf" => thumb(req.project_id, &req.item_id, true).await,
+            "thumb" | "thumbhalf" => {
+                let half = req.item_type == "thumbhalf";
+                let pdb = ProjectsDb::get().await?;
+                let (_, kind) = pdb
+                    .get_project_location(req.project_id)
+                    .await
+                    .context("Failed to get project path")?;
+                match kind {
+                    WatchFolderKind::LooseImages => {
+                        let file_id: i64 = req.item_id.parse().context("Invalid item ID")?;
+                        let path = pdb.get_image_file_path(req.project_id, file_id).await?;
+                        image_file_thumb(&path, half).await
+                    }
+                    WatchFolderKind::Projects => thumb(req.project_id, &req.item_id, half).await,
+                }
+            }
             "tensor" => {
                 tensor(
                     req.project_id,
                     &req.item_id,
                     req.node,
-                    req.size,
+                    req.resize,
                     req.mask.as_deref(),
+                    req.overlay,
+                )
+                .await
+            }
+            "canvas" | "mask" | "moodboard" => {
+                node_reference(
+                    req.project_id,
+                    &req.item_type,
+                    &req.item_id,
+                    req.node,
+                    req.resize,
                 )
                 .await
             }
+            "composite" => composite(req.project_id, &req.item_id, req.resize).await,
+            "inpaint" => inpaint(req.project_id, &req.item_id, req.inpaint, req.resize).await,
             "audio" => {
-                let project_path = self
-                    .pdb
+                let project_path = ProjectsDb::get()
+                    .await?
                     .get_project_path(req.project_id)
                     .await
                     .context("Failed to get project path")?;
-                audio_request(&project_path, &req).await
+                audio_request(&project_path, req).await
             }
             _ => Ok(Response::builder()
                 .status(StatusCode::NOT_FOUND)
@@ -169,6 +350,18 @@ impl DtmProtocol {
     }
 }
 
+/// The response changes with the project, so the browser revalidates it with the ETag
+fn set_cache_headers(response: &mut Response<Vec<u8>>, etag: &str) -> anyhow::Result<()> {
+    let headers = response.headers_mut();
+    headers.insert(header::ETAG, HeaderValue::from_str(etag)?);
+    headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("private, no-cache"));
+    headers.insert(
+        header::ACCESS_CONTROL_EXPOSE_HEADERS,
+        HeaderValue::from_static("ETag"),
+    );
+    Ok(())
+}
+
 async fn thumb(project_id: i64, item_id: &str, half: bool) -> anyhow::Result<Response<Vec<u8>>> {
     let preview_id: i64 = item_id.parse().context("Invalid item ID")?;
 
@@ -190,15 +383,44 @@ async fn thumb(project_id: i64, item_id: &str, half: bool) -> anyhow::Result<Res
         .map_err(|e| anyhow::anyhow!(e))
 }
 
+/// Thumbnails for loose images are made from the image file itself
+async fn image_file_thumb(path: &str, half: bool) -> anyhow::Result<Response<Vec<u8>>> {
+    let bytes = tokio::fs::read(path).await.context("Failed to read image")?;
+    let size = match half {
+        true => IMAGE_FILE_THUMB_SIZE / 2,
+        false => IMAGE_FILE_THUMB_SIZE,
+    };
+
+    let body = tokio::task::spawn_blocking(move || -> anyhow::Result<Vec<u8>> {
+        let thumb = image::load_from_memory(&bytes)
+            .context("Failed to decode image")?
+            .thumbnail(size, size)
+            .to_rgb8();
+        let mut body = Vec::new();
+        thumb.write_to(&mut Cursor::new(&mut body), image::ImageFormat::Jpeg)?;
+        Ok(body)
+    })
+    .await??;
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", "image/jpeg")
+        .header("Access-Control-Allow-Origin", "*")
+        .header("Access-Control-Allow-Methods", "GET")
+        .body(body)
+        .map_err(|e| anyhow::anyhow!(e))
+}
+
 // Unsupported options by DtResourceHandle API:
 // - mask: NOT supported - mask parameter not available through DtResourceHandle
-// Note: size parameter IS supported through get_lossless()
+// Note: resize parameter IS supported through get_lossless()
 async fn tensor(
     project_id: i64,
     name: &str,
     node: Option<i64>,
-    size: Option<u32>,
+    resize: Option<ResizeOptions>,
     _mask: Option<&str>,
+    overlay: bool,
 ) -> anyhow::Result<Response<Vec<u8>>> {
     let project_ref = DtProjectRef::Id(project_id);
 
@@ -214,15 +436,21 @@ async fn tensor(
 
     let tensor_type = classify_type(name).unwrap_or("");
 
-    // Handle pose type separately as it doesn't return PNG
+    // poses are keypoints rather than an image, so they are rendered as skeletons
     if tensor_type == "pose" {
-        return Ok(Response::builder()
-            .status(StatusCode::BAD_REQUEST)
-            .body(
-                "Unsupported tensor type or decoding failed"
-                    .as_bytes()
-                    .to_vec(),
-            )?);
+        let body = handle
+            .get_pose_png(overlay)
+            .await
+            .context("Failed to render pose")?
+            .ok_or_else(|| anyhow::anyhow!("Failed to render pose"))?;
+
+        return Response::builder()
+            .status(StatusCode::OK)
+            .header("Content-Type", "image/png")
+            .header("Access-Control-Allow-Origin", "*")
+            .header("Access-Control-Allow-Methods", "GET")
+            .body(body)
+            .map_err(|e| anyhow::anyhow!(e));
     }
 
     if tensor_type == "audio" {
@@ -230,7 +458,7 @@ async fn tensor(
     }
 
     let body = handle
-        .get_lossless(size)
+        .get_lossless(resize)
         .await
         .context("Failed to get lossless")?
         .ok_or_else(|| anyhow::anyhow!("Failed to get lossless"))?;
@@ -244,6 +472,88 @@ async fn tensor(
         .map_err(|e| anyhow::anyhow!(e))
 }
 
+/// Canvas layers, masks and moodboard images, looked up by index from the node's tensordata
+/// and moodboard
+async fn node_reference(
+    project_id: i64,
+    item_type: &str,
+    item_id: &str,
+    node: Option<i64>,
+    resize: Option<ResizeOptions>,
+) -> anyhow::Result<Response<Vec<u8>>> {
+    let node_id = node.ok_or_else(|| anyhow::anyhow!("Missing node parameter"))?;
+    let index: usize = item_id.parse().context("Invalid index")?;
+
+    let node_handle = DtProjectRef::Id(project_id).node(node_id);
+    let sub = node_handle.sub()?;
+    let handle = match item_type {
+        "canvas" => sub.Canvas(index),
+        "mask" => sub.Mask(index),
+        _ => sub.Moodboard(index),
+    };
+
+    let body = handle
+        .get_lossless(resize)
+        .await
+        .context("Failed to get lossless")?
+        .ok_or_else(|| anyhow::anyhow!("No {} at index {}", item_type, index))?;
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", "image/png")
+        .header("Access-Control-Allow-Origin", "*")
+        .header("Access-Control-Allow-Methods", "GET")
+        .body(body)
+        .map_err(|e| anyhow::anyhow!(e))
+}
+
+async fn composite(
+    project_id: i64,
+    item_id: &str,
+    resize: Option<ResizeOptions>,
+) -> anyhow::Result<Response<Vec<u8>>> {
+    let node_id: i64 = item_id.parse().context("Invalid node ID")?;
+
+    let body = DtProjectRef::Id(project_id)
+        .node(node_id)
+        .get_composite_png(resize)
+        .await
+        .context("Failed to reconstruct canvas")?
+        .ok_or_else(|| anyhow::anyhow!("Node {} has nothing on its canvas", node_id))?;
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", "image/png")
+        .header("Access-Control-Allow-Origin", "*")
+        .header("Access-Control-Allow-Methods", "GET")
+        .body(body)
+        .map_err(|e| anyhow::anyhow!(e))
+}
+
+async fn inpaint(
+    project_id: i64,
+    item_id: &str,
+    options: InpaintViewOptions,
+    resize: Option<ResizeOptions>,
+) -> anyhow::Result<Response<Vec<u8>>> {
+    let node_id: i64 = item_id.parse().context("Invalid node ID")?;
+
+    let body = DtProjectRef::Id(project_id)
+        .node(node_id)
+        .get_inpaint_png(options, resize)
+        .await
+        .context("Failed to render inpaint view")?
+        .ok_or_else(|| anyhow::anyhow!("Node {} is not an inpainting pass", node_id))?;
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", "image/png")
+        .header("Access-Control-Allow-Origin", "*")
+        .header("Access-Control-Allow-Methods", "GET")
+        .body(body)
+        .map_err(|e| anyhow::anyhow!(e))
+}
+
 fn classify_type(s: &str) -> Option<&str> {
     s.rsplit_once('_').map(|(prefix, _)| prefix)
 }
@@ -293,4 +603,41 @@ mod tests {
         let no_eoi = vec![0xFF, 0xD8, 0x01, 0x02];
         assert!(extract_jpeg_slice(&no_eoi).is_none());
     }
+
+    #[test]
+    fn test_parse_resize() {
+        let parse = |uri: &str| {
+            let request = http::Request::builder().uri(uri).body(()).unwrap();
+            parse_request(&request).unwrap().resize
+        };
+
+        let resize = parse("dtm://dtproject/tensor/1/tensor_history_1?fit=cover&s=256x128");
+        let resize = resize.unwrap();
+        assert_eq!((resize.width, resize.height), (256, 128));
+        assert_eq!(resize.fit, crate::ResizeFit::Cover);
+        assert_eq!(resize.filter, crate::ResizeFilter::Lanczos3);
+
+        let resize = parse("dtm://dtproject/tensor/1/tensor_history_1?s=64&filter=box");
+        assert_eq!(resize.unwrap().filter, crate::ResizeFilter::Box);
+
+        assert!(parse("dtm://dtproject/tensor/1/tensor_history_1?fit=cover").is_none());
+    }
+
+    #[test]
+    fn test_parse_inpaint() {
+        let parse = |uri: &str| {
+            let request = http::Request::builder().uri(uri).body(()).unwrap();
+            parse_request(&request).unwrap().inpaint
+        };
+
+        assert_eq!(parse("dtm://dtproject/inpaint/1/2"), InpaintViewOptions::default());
+        let options = parse("dtm://dtproject/inpaint/1/2?tint=00ff00&opacity=0.25&blur=0");
+        assert_eq!(options.tint, [0, 255, 0]);
+        assert_eq!(options.opacity, 0.25);
+        assert!(!options.blur);
+
+        // invalid values keep the defaults
+        let options = parse("dtm://dtproject/inpaint/1/2?tint=green&opacity=most");
+        assert_eq!(options, InpaintViewOptions::default());
+    }
 }
@@ -0,0 +1,41 @@
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::projects_db::dt_project::DTProjectTable;
+
+/// A known table of a Draw Things project
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectTable {
+    pub table: DTProjectTable,
+    /// the name of the table in the project file
+    pub name: &'static str,
+    /// false for tables that older versions of Draw Things did not create
+    pub present: bool,
+    pub row_count: i64,
+}
+
+/// A row of a project table
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectTableRow {
+    pub rowid: i64,
+    /// every column by name. Blobs are replaced by their size, and their bytes as hex when
+    /// they are small.
+    pub columns: Map<String, Value>,
+    /// the flatbuffer `p` column, decoded for tables with a known schema
+    pub data: Option<Value>,
+    /// why `p` could not be decoded
+    pub data_error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectTablePage {
+    pub table: DTProjectTable,
+    pub row_count: i64,
+    pub skip: i64,
+    pub take: i64,
+    /// ordered by rowid
+    pub rows: Vec<ProjectTableRow>,
+}
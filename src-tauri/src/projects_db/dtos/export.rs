@@ -0,0 +1,96 @@
+use serde::{Deserialize, Serialize};
+
+use crate::projects_db::dt_project::data::tensor_history_node_data::TensorHistoryNodeData;
+
+/// File format for images decoded from tensors
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum TensorImageFormat {
+    /// 8-bit png, with metadata
+    #[default]
+    Png8,
+    /// 16-bit png, with metadata
+    Png16,
+    /// 32-bit float tiff, without metadata
+    Tiff32,
+    /// 32-bit float OpenEXR, without metadata
+    Exr32,
+}
+
+impl TensorImageFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            TensorImageFormat::Png8 | TensorImageFormat::Png16 => "png",
+            TensorImageFormat::Tiff32 => "tiff",
+            TensorImageFormat::Exr32 => "exr",
+        }
+    }
+
+    pub fn is_png(&self) -> bool {
+        matches!(self, TensorImageFormat::Png8 | TensorImageFormat::Png16)
+    }
+}
+
+/// File format for raw tensors, as decompressed from the project
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum TensorFileFormat {
+    /// NumPy array, named after the tensor
+    #[default]
+    Npy,
+    /// safetensors, with the tensor stored under its Draw Things name
+    Safetensors,
+}
+
+impl TensorFileFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            TensorFileFormat::Npy => "npy",
+            TensorFileFormat::Safetensors => "safetensors",
+        }
+    }
+}
+
+/// How much generation metadata is embedded in exported images
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MetadataPrivacy {
+    /// write clean files, with no Draw Things json, XMP or EXIF
+    pub strip: bool,
+    /// keep the generation settings, but remove the prompts
+    pub redact_prompts: bool,
+    /// keep the generation settings, but remove model, lora, control and upscaler filenames
+    pub redact_models: bool,
+}
+
+impl MetadataPrivacy {
+    /// Returns the node with the redacted fields removed, or None if no metadata should be written
+    pub fn apply(&self, mut node: TensorHistoryNodeData) -> Option<TensorHistoryNodeData> {
+        if self.strip {
+            return None;
+        }
+
+        if self.redact_prompts {
+            node.text_prompt = None;
+            node.negative_text_prompt = None;
+            node.clip_l_text = None;
+            node.open_clip_g_text = None;
+            node.t5_text = None;
+        }
+
+        if self.redact_models {
+            node.model = None;
+            node.refiner_model = None;
+            node.upscaler = None;
+            node.face_restoration = None;
+            for lora in node.loras.iter_mut().flatten() {
+                lora.file = None;
+            }
+            for control in node.controls.iter_mut().flatten() {
+                control.file = None;
+            }
+        }
+
+        Some(node)
+    }
+}
@@ -0,0 +1,49 @@
+use serde::Serialize;
+
+/// What a health check failure was found in
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum HealthCheck {
+    /// a tensorhistorynode flatbuffer
+    Node,
+    /// a tensordata flatbuffer
+    TensorData,
+    Tensor,
+    Thumbnail,
+    Clip,
+}
+
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthCount {
+    pub checked: u64,
+    pub failed: u64,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthFailure {
+    pub check: HealthCheck,
+    /// rowid of the history node the failure was found through
+    pub node_id: Option<i64>,
+    /// tensor name, thumbnail or clip id
+    pub name: Option<String>,
+    pub message: String,
+}
+
+/// Result of reading every history node of a project, and the tensors, thumbnails and clips
+/// they reference
+#[derive(Debug, Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectHealthReport {
+    pub path: String,
+    /// true if nothing failed
+    pub healthy: bool,
+    pub nodes: HealthCount,
+    pub tensor_data: HealthCount,
+    pub tensors: HealthCount,
+    pub thumbnails: HealthCount,
+    pub clips: HealthCount,
+    /// the first failures found, up to `MAX_HEALTH_FAILURES`. The counts include the rest.
+    pub failures: Vec<HealthFailure>,
+}
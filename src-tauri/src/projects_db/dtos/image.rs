@@ -62,8 +62,8 @@ pub struct ImageExtra {
     pub sampler: i8,
     pub steps: i16,
     pub guidance_scale: f32,
-    pub strength: f32,
-    pub shift: f32,
+    pub strength: Option<f32>,
+    pub shift: Option<f32>,
     pub hires_fix: bool,
     pub tiled_decoding: bool,
     pub tiled_diffusion: bool,
@@ -0,0 +1,113 @@
+use serde::Serialize;
+
+use crate::projects_db::DrawThingsMetadata;
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFileFormat {
+    Png,
+    Jpeg,
+    Webp,
+}
+
+/// A piece of text metadata found in an image file
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MetadataChunk {
+    /// where the text was stored, ie "iTXt", "tEXt", "zTXt", "XMP", "UserComment" or "COM"
+    pub kind: String,
+    /// the keyword of png text chunks
+    pub keyword: Option<String>,
+    pub text: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageMetadata {
+    pub format: ImageFileFormat,
+    /// Draw Things metadata, if any of the chunks contain it
+    pub metadata: Option<DrawThingsMetadata>,
+    /// generation parameters from the Draw Things metadata, or from another tool's metadata
+    pub generation: Option<GenerationParams>,
+    pub chunks: Vec<MetadataChunk>,
+}
+
+/// The tool that wrote an image's generation metadata
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum MetadataSource {
+    DrawThings,
+    A1111,
+    ComfyUI,
+    InvokeAI,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LoraWeight {
+    pub name: String,
+    pub weight: f32,
+}
+
+/// Generation parameters common to most tools. Fields are None when they couldn't be inferred
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GenerationParams {
+    pub source: MetadataSource,
+    pub prompt: Option<String>,
+    pub negative_prompt: Option<String>,
+    pub seed: Option<u64>,
+    pub steps: Option<u32>,
+    pub sampler: Option<String>,
+    pub cfg_scale: Option<f32>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub model: Option<String>,
+    pub loras: Vec<LoraWeight>,
+}
+
+impl GenerationParams {
+    pub fn new(source: MetadataSource) -> Self {
+        Self {
+            source,
+            prompt: None,
+            negative_prompt: None,
+            seed: None,
+            steps: None,
+            sampler: None,
+            cfg_scale: None,
+            width: None,
+            height: None,
+            model: None,
+            loras: Vec::new(),
+        }
+    }
+}
+
+impl From<&DrawThingsMetadata> for GenerationParams {
+    fn from(metadata: &DrawThingsMetadata) -> Self {
+        let v2 = &metadata.v2;
+        Self {
+            source: MetadataSource::DrawThings,
+            prompt: Some(metadata.c.clone()),
+            negative_prompt: Some(metadata.uc.clone()),
+            seed: Some(v2.seed as u64),
+            steps: Some(v2.steps),
+            sampler: Some(metadata.sampler.to_string()),
+            cfg_scale: Some(v2.guidance_scale),
+            width: Some(v2.width),
+            height: Some(v2.height),
+            model: Some(v2.model.clone()),
+            loras: v2
+                .loras
+                .iter()
+                .filter_map(|lora| {
+                    Some(LoraWeight {
+                        name: lora["file"].as_str()?.to_string(),
+                        weight: lora["weight"].as_f64().unwrap_or(1.0) as f32,
+                    })
+                })
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,20 @@
+use serde::{Deserialize, Serialize};
+
+/// File format for an exported library index
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum IndexExportFormat {
+    Csv,
+    Jsonl,
+    Sqlite,
+}
+
+impl IndexExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            IndexExportFormat::Csv => "csv",
+            IndexExportFormat::Jsonl => "jsonl",
+            IndexExportFormat::Sqlite => "sqlite",
+        }
+    }
+}
@@ -0,0 +1,31 @@
+use serde::Serialize;
+
+/// A tensorhistorynode in a project's history graph
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LineageNode {
+    pub rowid: i64,
+    pub lineage: i64,
+    pub logical_time: i64,
+    /// rowid of the node this one was generated from, None for the root of the history
+    pub parent: Option<i64>,
+    /// true when the parent is in a different lineage, i.e. the user went back in history
+    /// and generated from an earlier node
+    pub branch: bool,
+    pub text_lineage: i64,
+    pub tensor_id: i64,
+    pub mask_id: i64,
+    pub preview_id: i64,
+    pub generated: bool,
+    pub strength: f32,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeLineage {
+    pub node: LineageNode,
+    /// parent first, ending with the root of the history
+    pub ancestors: Vec<LineageNode>,
+    /// every node generated from this one, directly or indirectly, ordered by rowid
+    pub descendants: Vec<LineageNode>,
+}
@@ -0,0 +1,49 @@
+use serde::Serialize;
+use serde_json::Value;
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum DiffOp {
+    Equal,
+    Added,
+    Removed,
+    Changed,
+}
+
+/// A `V2` field in both images. `a` or `b` is null when the field is missing from that image
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldDiff {
+    pub field: String,
+    pub a: Value,
+    pub b: Value,
+    pub changed: bool,
+}
+
+/// A run of words that are in both prompts (Equal), only in b (Added) or only in a (Removed)
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct WordDiff {
+    pub op: DiffOp,
+    pub text: String,
+}
+
+/// A lora or control, matched by file between the two images
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelWeightDiff {
+    pub file: String,
+    pub op: DiffOp,
+    pub weight_a: Option<f32>,
+    pub weight_b: Option<f32>,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MetadataDiff {
+    pub fields: Vec<FieldDiff>,
+    pub prompt: Vec<WordDiff>,
+    pub negative_prompt: Vec<WordDiff>,
+    pub loras: Vec<ModelWeightDiff>,
+    pub controls: Vec<ModelWeightDiff>,
+}
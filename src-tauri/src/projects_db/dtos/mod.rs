@@ -1,7 +1,15 @@
 pub mod clip;
+pub mod explorer;
+pub mod health;
+pub mod export;
 pub mod image;
+pub mod image_metadata;
+pub mod index;
+pub mod lineage;
+pub mod metadata_diff;
 pub mod model;
 pub mod project;
+pub mod stats;
 pub mod tensor;
 pub mod text;
 pub mod watch_folder;
@@ -1,5 +1,8 @@
-use sea_orm::FromQueryResult;
-use serde::Serialize;
+use entity::enums::WatchFolderKind;
+use sea_orm::{FromJsonQueryResult, FromQueryResult};
+use serde::{Deserialize, Serialize};
+
+use crate::projects_db::dt_project::DTProjectTable;
 
 #[derive(Debug, FromQueryResult, Serialize, Clone)]
 pub struct ProjectExtra {
@@ -16,29 +19,72 @@ pub struct ProjectExtra {
     pub full_path: String,
     pub is_missing: bool,
     pub is_locked: bool,
+    /// the kind of its watch folder. A loose images folder is a single project, with an
+    /// empty path
+    pub kind: WatchFolderKind,
+    /// None until the project has been scanned
+    pub compatibility: Option<CompatibilityReport>,
 }
 
 impl ProjectExtra {
     pub fn populate(&mut self) {
-        self.name = std::path::Path::new(&self.path)
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .unwrap_or("")
-            .to_string();
-
         let wf_path = crate::projects_db::folder_cache::get_folder(self.watchfolder_id);
 
-        self.full_path = if let Some(ref wf) = wf_path {
-            std::path::Path::new(wf)
+        self.full_path = match (wf_path, self.kind) {
+            (Some(wf), WatchFolderKind::LooseImages) => wf,
+            (Some(wf), WatchFolderKind::Projects) => std::path::Path::new(&wf)
                 .join(&self.path)
                 .to_string_lossy()
-                .to_string()
-        } else {
-            self.path.clone()
+                .to_string(),
+            (None, _) => self.path.clone(),
         };
+
+        let name = match self.kind {
+            WatchFolderKind::LooseImages => std::path::Path::new(&self.full_path).file_name(),
+            WatchFolderKind::Projects => std::path::Path::new(&self.path).file_stem(),
+        };
+        self.name = name.and_then(|s| s.to_str()).unwrap_or("").to_string();
+    }
+
+    pub fn is_loose_images(&self) -> bool {
+        self.kind == WatchFolderKind::LooseImages
     }
 }
 
+/// The tables and flatbuffer fields found in a project file, compared with the schema DTM
+/// was built with. Draw Things adds fields as it is updated, and rows that DTM can't parse
+/// are otherwise skipped without notice.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default, FromJsonQueryResult)]
+pub struct CompatibilityReport {
+    /// false if anything in the file is unknown or could not be parsed
+    pub compatible: bool,
+    /// every table in the file
+    pub tables: Vec<String>,
+    /// known tables the file doesn't have, usually because it was created by an older
+    /// version of Draw Things
+    pub missing_tables: Vec<DTProjectTable>,
+    /// tables that are neither known nor an index of a known table
+    pub unknown_tables: Vec<String>,
+    pub flatbuffers: Vec<FlatbufferSchema>,
+}
+
+/// The fields found in the `p` column of a table
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct FlatbufferSchema {
+    pub table: DTProjectTable,
+    /// number of rows checked, starting from the newest
+    pub rows_checked: i64,
+    /// rows that don't verify against the known schema
+    pub invalid_rows: i64,
+    /// number of fields in the known schema
+    pub known_fields: usize,
+    /// vtable slots set in at least one row. Fields left at their default are not written,
+    /// so a known field can be missing here.
+    pub fields_present: Vec<usize>,
+    /// slots past the known fields that are set in at least one row
+    pub unknown_fields: Vec<usize>,
+}
+
 #[derive(Debug, Serialize, Clone)]
 pub struct DTProjectInfo {
     pub _path: String,
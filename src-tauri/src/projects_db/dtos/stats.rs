@@ -0,0 +1,83 @@
+use sea_orm::FromQueryResult;
+use serde::Serialize;
+
+#[derive(Debug, Serialize, Default)]
+pub struct LibraryStats {
+    pub total: i64,
+    pub image_count: i64,
+    pub video_count: i64,
+    pub avg_steps: Option<f64>,
+    pub avg_guidance: Option<f64>,
+    pub first_wall_clock: Option<sea_orm::prelude::DateTimeUtc>,
+    pub last_wall_clock: Option<sea_orm::prelude::DateTimeUtc>,
+    /// Images per day, `YYYY-MM-DD`
+    pub per_day: Vec<PeriodCount>,
+    /// Images per week, `YYYY-Www`
+    pub per_week: Vec<PeriodCount>,
+    /// Images per hour of the day, 0-23
+    pub hours: Vec<HourCount>,
+    pub models: Vec<ModelUsage>,
+    pub loras: Vec<ModelUsage>,
+    pub controls: Vec<ModelUsage>,
+    pub samplers: Vec<SamplerUsage>,
+    pub resolutions: Vec<ResolutionUsage>,
+    /// Weekly usage, keyed by model id
+    pub models_by_week: Vec<PeriodUsage>,
+    /// Weekly usage, keyed by lora id
+    pub loras_by_week: Vec<PeriodUsage>,
+    /// Weekly usage, keyed by sampler
+    pub samplers_by_week: Vec<PeriodUsage>,
+    /// Weekly usage, keyed by `{width}x{height}`
+    pub resolutions_by_week: Vec<PeriodUsage>,
+}
+
+#[derive(Debug, FromQueryResult)]
+pub struct StatsSummary {
+    pub total: i64,
+    pub video_count: Option<i64>,
+    pub avg_steps: Option<f64>,
+    pub avg_guidance: Option<f64>,
+    pub first_wall_clock: Option<sea_orm::prelude::DateTimeUtc>,
+    pub last_wall_clock: Option<sea_orm::prelude::DateTimeUtc>,
+}
+
+#[derive(Debug, FromQueryResult, Serialize)]
+pub struct PeriodCount {
+    pub period: String,
+    pub count: i64,
+}
+
+#[derive(Debug, FromQueryResult, Serialize)]
+pub struct HourCount {
+    pub hour: i32,
+    pub count: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ModelUsage {
+    pub id: i64,
+    pub filename: Option<String>,
+    pub name: Option<String>,
+    pub count: i64,
+}
+
+#[derive(Debug, FromQueryResult, Serialize)]
+pub struct SamplerUsage {
+    pub sampler: i8,
+    pub count: i64,
+}
+
+#[derive(Debug, FromQueryResult, Serialize)]
+pub struct ResolutionUsage {
+    /// in pixels
+    pub width: i32,
+    pub height: i32,
+    pub count: i64,
+}
+
+#[derive(Debug, FromQueryResult, Serialize)]
+pub struct PeriodUsage {
+    pub period: String,
+    pub key: String,
+    pub count: i64,
+}
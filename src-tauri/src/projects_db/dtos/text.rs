@@ -28,3 +28,21 @@ pub struct TextHistoryNode {
     pub start_negative_text: String,
     pub modifications: Vec<TextModification>,
 }
+
+/// The prompts after `text_edits` edits, and the modification that produced them. The first
+/// revision of each text history node has no modification.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PromptRevision {
+    pub text_edits: i64,
+    pub positive: String,
+    pub negative: String,
+    pub modification: Option<TextModification>,
+    /// rowids of the tensorhistorynodes generated with this revision
+    pub generations: Vec<i64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PromptTimeline {
+    pub lineage: i64,
+    pub revisions: Vec<PromptRevision>,
+}
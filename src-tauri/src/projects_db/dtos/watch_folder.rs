@@ -1,4 +1,4 @@
-use entity::watch_folders;
+use entity::{enums::WatchFolderKind, watch_folders};
 use serde::Serialize;
 
 #[derive(Debug, Serialize, Clone)]
@@ -11,6 +11,7 @@ pub struct WatchFolderDTO {
     pub is_locked: bool,
     pub bookmark: String,
     pub maint: u32,
+    pub kind: WatchFolderKind,
 }
 
 impl From<watch_folders::Model> for WatchFolderDTO {
@@ -23,6 +24,7 @@ impl From<watch_folders::Model> for WatchFolderDTO {
             is_locked: m.is_locked,
             bookmark: m.bookmark,
             maint: m.maint,
+            kind: m.kind,
         }
     }
 }
@@ -225,9 +225,27 @@ impl<'a> PartialThnDtResourceHandle<'a> {
             DtResourceRef::TensorHistoryNode(self.node, ThnResource::ColorPalette),
         )
     }
-    // pub fn Canvas(usize) Canvas(usize)
-    // pub fn Mask(usize) Mask(usize)
-    // pub fn Moodboard(usize) Moodboard(usize)
+    /// canvas layers are indexed from the top, so 0 is the topmost layer
+    pub fn Canvas(self, index: usize) -> DtResourceHandle {
+        DtResourceHandle::new(
+            self.project,
+            DtResourceRef::TensorHistoryNode(self.node, ThnResource::Canvas(index)),
+        )
+    }
+    /// masks are indexed from the top, so 0 is the topmost mask
+    pub fn Mask(self, index: usize) -> DtResourceHandle {
+        DtResourceHandle::new(
+            self.project,
+            DtResourceRef::TensorHistoryNode(self.node, ThnResource::Mask(index)),
+        )
+    }
+    /// moodboard images are indexed by their position in the moodboard (`__pk2`)
+    pub fn Moodboard(self, index: usize) -> DtResourceHandle {
+        DtResourceHandle::new(
+            self.project,
+            DtResourceRef::TensorHistoryNode(self.node, ThnResource::Moodboard(index)),
+        )
+    }
     pub fn tensor(self, tensor_name: &str) -> DtResourceHandle {
         DtResourceHandle::new(
             self.project,
@@ -29,6 +29,10 @@ pub async fn resolve_bookmark(id: i64, bookmark: &str) -> Result<bookmarks::Reso
     Ok(result)
 }
 
+pub fn set_folder(id: i64, path: &str) {
+    CACHE.write().unwrap().insert(id, PathBuf::from(path));
+}
+
 pub fn get_folder(id: i64) -> Option<String> {
     CACHE
         .read()
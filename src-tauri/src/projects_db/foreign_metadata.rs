@@ -0,0 +1,427 @@
+//! Parsers for the generation metadata written by other tools, so images from them can be
+//! inspected and indexed alongside Draw Things images.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_json::{Map, Value};
+
+use crate::projects_db::dtos::image_metadata::{
+    GenerationParams, LoraWeight, MetadataChunk, MetadataSource,
+};
+
+/// ComfyUI graphs can be deep, but following links further than this is never useful
+const MAX_LINK_DEPTH: usize = 32;
+
+/// `<lora:name:weight>` tags in A1111 prompts
+static LORA_TAG: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"<lora:([^:>]+)(?::([^:>]+))?[^>]*>").unwrap());
+
+/// Finds and parses the first chunk containing metadata from a supported tool
+pub fn find_generation_params(chunks: &[MetadataChunk]) -> Option<GenerationParams> {
+    // ComfyUI's prompt is preferred over its workflow, since it only has the nodes that ran
+    with_keyword(chunks, &["parameters"])
+        .find_map(|chunk| parse_a1111_parameters(&chunk.text))
+        .or_else(|| {
+            with_keyword(chunks, &["invokeai_metadata", "sd-metadata"])
+                .find_map(|chunk| parse_invokeai_metadata(&chunk.text))
+        })
+        .or_else(|| {
+            with_keyword(chunks, &["prompt"]).find_map(|chunk| parse_comfyui_prompt(&chunk.text))
+        })
+        .or_else(|| {
+            with_keyword(chunks, &["workflow"])
+                .find_map(|chunk| parse_comfyui_workflow(&chunk.text))
+        })
+        // A1111 stores its parameters in the EXIF UserComment of jpegs and webps
+        .or_else(|| {
+            chunks
+                .iter()
+                .filter(|chunk| chunk.kind == "UserComment")
+                .find_map(|chunk| parse_a1111_parameters(&chunk.text))
+        })
+}
+
+fn with_keyword<'a>(
+    chunks: &'a [MetadataChunk],
+    keywords: &'a [&str],
+) -> impl Iterator<Item = &'a MetadataChunk> {
+    chunks.iter().filter(move |chunk| {
+        chunk
+            .keyword
+            .as_deref()
+            .map_or(false, |keyword| keywords.contains(&keyword))
+    })
+}
+
+/// Parses an A1111 (or Forge, SD.Next, Fooocus in A1111 mode) `parameters` string:
+///
+/// ```text
+/// a photo of a cat <lora:fluffy:0.8>
+/// Negative prompt: blurry
+/// Steps: 20, Sampler: DPM++ 2M, Schedule type: Karras, CFG scale: 7, Seed: 1234, Size: 512x768, Model: sd_xl_base_1.0
+/// ```
+pub fn parse_a1111_parameters(text: &str) -> Option<GenerationParams> {
+    let text = text.trim();
+    // the settings are always on the last line, and always start with Steps
+    let (body, settings) = match text.rfind("\nSteps: ") {
+        Some(i) => (&text[..i], &text[i + 1..]),
+        None if text.starts_with("Steps: ") => ("", text),
+        None => return None,
+    };
+
+    let (prompt, negative_prompt) = match body.strip_prefix("Negative prompt: ") {
+        Some(negative) => ("", Some(negative)),
+        None => match body.split_once("\nNegative prompt: ") {
+            Some((prompt, negative)) => (prompt, Some(negative)),
+            None => (body, None),
+        },
+    };
+
+    let mut params = GenerationParams::new(MetadataSource::A1111);
+    params.prompt = Some(prompt.trim().to_string());
+    params.negative_prompt = negative_prompt.map(|p| p.trim().to_string());
+    params.loras = prompt_loras(prompt);
+
+    let mut schedule_type = None;
+    for (key, value) in split_a1111_settings(settings) {
+        match key.as_str() {
+            "Steps" => params.steps = value.parse().ok(),
+            "Sampler" => params.sampler = Some(value),
+            "Schedule type" => schedule_type = Some(value),
+            "CFG scale" => params.cfg_scale = value.parse().ok(),
+            "Seed" => params.seed = value.parse().ok(),
+            "Size" => {
+                if let Some((width, height)) = value.split_once('x') {
+                    params.width = width.trim().parse().ok();
+                    params.height = height.trim().parse().ok();
+                }
+            }
+            "Model" => params.model = Some(value),
+            _ => {}
+        }
+    }
+
+    // newer versions split the scheduler out of the sampler name
+    if let (Some(sampler), Some(schedule)) = (&params.sampler, schedule_type) {
+        if schedule != "Automatic" {
+            params.sampler = Some(format!("{} {}", sampler, schedule));
+        }
+    }
+
+    Some(params)
+}
+
+/// Splits `Key: value, Key: "quoted, value"` pairs
+fn split_a1111_settings(settings: &str) -> Vec<(String, String)> {
+    let mut pairs = Vec::new();
+    let mut rest = settings.trim();
+
+    while let Some(colon) = rest.find(": ") {
+        let key = rest[..colon].trim().to_string();
+        rest = &rest[colon + 2..];
+
+        let value = if let Some(quoted) = rest.strip_prefix('"') {
+            let end = quoted.find('"').unwrap_or(quoted.len());
+            let value = &quoted[..end];
+            rest = quoted.get(end + 1..).unwrap_or("");
+            value
+        } else {
+            let end = rest.find(", ").unwrap_or(rest.len());
+            let value = &rest[..end];
+            rest = &rest[end..];
+            value
+        };
+        pairs.push((key, value.trim().to_string()));
+
+        rest = rest.trim_start_matches([',', ' ']);
+    }
+
+    pairs
+}
+
+/// Collects `<lora:name:weight>` tags from a prompt
+fn prompt_loras(prompt: &str) -> Vec<LoraWeight> {
+    LORA_TAG
+        .captures_iter(prompt)
+        .map(|captures| LoraWeight {
+            name: captures[1].trim().to_string(),
+            weight: captures
+                .get(2)
+                .and_then(|w| w.as_str().trim().parse().ok())
+                .unwrap_or(1.0),
+        })
+        .collect()
+}
+
+/// Parses InvokeAI's `invokeai_metadata` json, or the `sd-metadata` json of older versions
+pub fn parse_invokeai_metadata(json: &str) -> Option<GenerationParams> {
+    let value: Value = serde_json::from_str(json).ok()?;
+    let mut params = GenerationParams::new(MetadataSource::InvokeAI);
+
+    // 2.x nested the generation under "image"
+    if let Some(image) = value.get("image").filter(|i| i.is_object()) {
+        params.prompt = match &image["prompt"] {
+            Value::String(prompt) => Some(prompt.clone()),
+            Value::Array(prompts) => prompts
+                .first()
+                .and_then(|p| p["prompt"].as_str())
+                .map(str::to_string),
+            _ => None,
+        };
+        params.seed = as_u64(&image["seed"]);
+        params.steps = as_u64(&image["steps"]).map(|s| s as u32);
+        params.sampler = as_string(&image["sampler"]);
+        params.cfg_scale = as_f32(&image["cfg_scale"]);
+        params.width = as_u64(&image["width"]).map(|w| w as u32);
+        params.height = as_u64(&image["height"]).map(|h| h as u32);
+        params.model = as_string(&value["model_weights"]);
+        return Some(params);
+    }
+
+    let prompt = value.get("positive_prompt")?;
+    params.prompt = as_string(prompt);
+    params.negative_prompt = as_string(&value["negative_prompt"]);
+    params.seed = as_u64(&value["seed"]);
+    params.steps = as_u64(&value["steps"]).map(|s| s as u32);
+    params.sampler = as_string(&value["scheduler"]);
+    params.cfg_scale = as_f32(&value["cfg_scale"]);
+    params.width = as_u64(&value["width"]).map(|w| w as u32);
+    params.height = as_u64(&value["height"]).map(|h| h as u32);
+    params.model = invokeai_model_name(&value["model"]);
+    params.loras = value["loras"]
+        .as_array()
+        .map(|loras| {
+            loras
+                .iter()
+                .filter_map(|lora| {
+                    let model = lora.get("model").or_else(|| lora.get("lora"))?;
+                    Some(LoraWeight {
+                        name: invokeai_model_name(model)?,
+                        weight: as_f32(&lora["weight"]).unwrap_or(1.0),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Some(params)
+}
+
+/// Models are identified by name in 4.x+ and by model_name in 3.x
+fn invokeai_model_name(model: &Value) -> Option<String> {
+    as_string(&model["name"])
+        .or_else(|| as_string(&model["model_name"]))
+        .or_else(|| as_string(model))
+}
+
+/// Parses the API format graph ComfyUI stores in its `prompt` chunk. This is a map of node
+/// ids to `{ class_type, inputs }`, where linked inputs are `[node_id, output_index]`
+pub fn parse_comfyui_prompt(json: &str) -> Option<GenerationParams> {
+    let value: Value = serde_json::from_str(json).ok()?;
+    let nodes = value.as_object()?;
+
+    let (_, sampler) = nodes.iter().find(|(_, node)| {
+        node["class_type"]
+            .as_str()
+            .map_or(false, |class| class.starts_with("KSampler"))
+    })?;
+    let inputs = &sampler["inputs"];
+    let linked = |input: &Value| link_id(input).and_then(|id| nodes.get(&id));
+
+    let mut params = GenerationParams::new(MetadataSource::ComfyUI);
+    params.prompt = find_comfyui_input(nodes, &inputs["positive"], "text", 0);
+    params.negative_prompt = find_comfyui_input(nodes, &inputs["negative"], "text", 0);
+    params.seed = ["seed", "noise_seed"].iter().find_map(|key| {
+        as_u64(&inputs[*key]).or_else(|| {
+            let node = linked(&inputs[*key])?;
+            as_u64(&node["inputs"]["seed"]).or_else(|| as_u64(&node["inputs"]["value"]))
+        })
+    });
+    params.steps = as_u64(&inputs["steps"]).map(|s| s as u32);
+    params.cfg_scale = as_f32(&inputs["cfg"]);
+    params.sampler = comfyui_sampler(&inputs["sampler_name"], &inputs["scheduler"]);
+
+    if let Some(latent) = linked(&inputs["latent_image"]) {
+        params.width = as_u64(&latent["inputs"]["width"]).map(|w| w as u32);
+        params.height = as_u64(&latent["inputs"]["height"]).map(|h| h as u32);
+    }
+
+    // follow the model input back through any lora loaders to the checkpoint
+    let mut model = linked(&inputs["model"]);
+    for _ in 0..MAX_LINK_DEPTH {
+        let Some(node) = model else { break };
+        let node_inputs = &node["inputs"];
+        if let Some(lora) = as_string(&node_inputs["lora_name"]) {
+            params.loras.push(LoraWeight {
+                name: lora,
+                weight: as_f32(&node_inputs["strength_model"]).unwrap_or(1.0),
+            });
+        }
+        if let Some(checkpoint) =
+            as_string(&node_inputs["ckpt_name"]).or_else(|| as_string(&node_inputs["unet_name"]))
+        {
+            params.model = Some(checkpoint);
+            break;
+        }
+        model = linked(&node_inputs["model"]);
+    }
+    params.loras.reverse();
+
+    Some(params)
+}
+
+/// Searches upstream from a linked input for a node with a string input named `key`. Nodes
+/// between the sampler and the text encoder (ie. conditioning combine) are followed through.
+fn find_comfyui_input(
+    nodes: &Map<String, Value>,
+    input: &Value,
+    key: &str,
+    depth: usize,
+) -> Option<String> {
+    if depth > MAX_LINK_DEPTH {
+        return None;
+    }
+    let node_inputs = nodes.get(&link_id(input)?)?["inputs"].as_object()?;
+
+    match node_inputs.get(key) {
+        Some(Value::String(text)) => Some(text.clone()),
+        // text from a primitive or string node
+        Some(text @ Value::Array(_)) => find_comfyui_input(nodes, text, "value", depth + 1)
+            .or_else(|| find_comfyui_input(nodes, text, "text", depth + 1))
+            .or_else(|| find_comfyui_input(nodes, text, "string", depth + 1)),
+        _ => node_inputs
+            .values()
+            .find_map(|input| find_comfyui_input(nodes, input, key, depth + 1)),
+    }
+}
+
+/// Parses the UI format graph ComfyUI stores in its `workflow` chunk. This is only used when
+/// there is no `prompt` chunk, since the widget values have to be matched up by position.
+pub fn parse_comfyui_workflow(json: &str) -> Option<GenerationParams> {
+    let value: Value = serde_json::from_str(json).ok()?;
+    let nodes = value["nodes"].as_array()?;
+    let links = value["links"].as_array();
+
+    let node_type = |node: &Value| node["type"].as_str().unwrap_or_default().to_string();
+    // bypassed and muted nodes don't contribute to the image
+    let active = nodes
+        .iter()
+        .filter(|node| !matches!(as_u64(&node["mode"]), Some(2 | 4)));
+    let widgets = |node: &Value| {
+        node["widgets_values"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default()
+    };
+
+    let sampler = active
+        .clone()
+        .find(|node| node_type(node).starts_with("KSampler"))?;
+    let sampler_widgets = widgets(sampler);
+    // KSamplerAdvanced has an add_noise widget before the seed
+    let offset = match node_type(sampler).as_str() {
+        "KSamplerAdvanced" => 1,
+        _ => 0,
+    };
+    let widget = |i: usize| sampler_widgets.get(i + offset).cloned().unwrap_or_default();
+
+    let text = |name: &str| {
+        workflow_linked_node(nodes, links, sampler, name)
+            .and_then(|node| widgets(node).first().and_then(as_string))
+    };
+
+    let mut params = GenerationParams::new(MetadataSource::ComfyUI);
+    params.prompt = text("positive");
+    params.negative_prompt = text("negative");
+    // widgets are seed, control_after_generate, steps, cfg, sampler_name, scheduler, denoise
+    params.seed = as_u64(&widget(0));
+    params.steps = as_u64(&widget(2)).map(|s| s as u32);
+    params.cfg_scale = as_f32(&widget(3));
+    params.sampler = comfyui_sampler(&widget(4), &widget(5));
+
+    if let Some(latent) = workflow_linked_node(nodes, links, sampler, "latent_image") {
+        let latent_widgets = widgets(latent);
+        params.width = latent_widgets.first().and_then(as_u64).map(|w| w as u32);
+        params.height = latent_widgets.get(1).and_then(as_u64).map(|h| h as u32);
+    }
+
+    for node in active {
+        let node_widgets = widgets(node);
+        match node_type(node).as_str() {
+            "CheckpointLoaderSimple" | "CheckpointLoader" | "UNETLoader" => {
+                params.model = params.model.take().or_else(|| {
+                    node_widgets
+                        .iter()
+                        .find_map(|w| as_string(w).filter(|s| s.contains('.')))
+                })
+            }
+            "LoraLoader" | "LoraLoaderModelOnly" => {
+                if let Some(name) = node_widgets.first().and_then(as_string) {
+                    params.loras.push(LoraWeight {
+                        name,
+                        weight: node_widgets.get(1).and_then(as_f32).unwrap_or(1.0),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Some(params)
+}
+
+/// Finds the node linked to a named input of a workflow node. Links are stored as
+/// `[link_id, origin_id, origin_slot, target_id, target_slot, type]`
+fn workflow_linked_node<'a>(
+    nodes: &'a [Value],
+    links: Option<&Vec<Value>>,
+    node: &Value,
+    name: &str,
+) -> Option<&'a Value> {
+    let link = node["inputs"]
+        .as_array()?
+        .iter()
+        .find(|input| input["name"].as_str() == Some(name))?["link"]
+        .as_u64()?;
+    let origin = links?
+        .iter()
+        .find(|l| as_u64(&l[0]) == Some(link))
+        .and_then(|l| as_u64(&l[1]))?;
+    nodes.iter().find(|n| as_u64(&n["id"]) == Some(origin))
+}
+
+fn comfyui_sampler(sampler: &Value, scheduler: &Value) -> Option<String> {
+    let sampler = as_string(sampler)?;
+    Some(match as_string(scheduler) {
+        Some(scheduler) if scheduler != "normal" => format!("{} {}", sampler, scheduler),
+        _ => sampler,
+    })
+}
+
+fn link_id(input: &Value) -> Option<String> {
+    match input.get(0)? {
+        Value::String(id) => Some(id.clone()),
+        Value::Number(id) => Some(id.to_string()),
+        _ => None,
+    }
+}
+
+fn as_string(value: &Value) -> Option<String> {
+    value.as_str().map(str::to_string).filter(|s| !s.is_empty())
+}
+
+fn as_u64(value: &Value) -> Option<u64> {
+    match value {
+        Value::Number(n) => n.as_u64().or_else(|| n.as_f64().map(|f| f as u64)),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+fn as_f32(value: &Value) -> Option<f32> {
+    match value {
+        Value::Number(n) => n.as_f64().map(|f| f as f32),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
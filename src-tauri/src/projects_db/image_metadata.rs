@@ -0,0 +1,322 @@
+use std::io::Read;
+use std::path::Path;
+
+use dtm_macros::dtm_command;
+use flate2::read::ZlibDecoder;
+use img_parts::{
+    jpeg::{markers, Jpeg},
+    png::Png,
+    Bytes,
+};
+
+use crate::projects_db::dtos::image_metadata::{
+    GenerationParams, ImageFileFormat, ImageMetadata, MetadataChunk,
+};
+use crate::projects_db::foreign_metadata::find_generation_params;
+use crate::projects_db::metadata::DrawThingsMetadata;
+use crate::projects_db::tensors::XMP_APP1_HEADER;
+
+/// Extensions of the image files indexed from a loose images folder
+pub const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg"];
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+const JPEG_SOI: &[u8] = &[0xFF, 0xD8];
+const EXIF_APP1_HEADER: &[u8] = b"Exif\0\0";
+pub(crate) const EXIF_IFD_POINTER: u16 = 0x8769;
+pub(crate) const EXIF_USER_COMMENT: u16 = 0x9286;
+
+const KIND_XMP: &str = "XMP";
+const KIND_USER_COMMENT: &str = "UserComment";
+
+pub fn is_image_file(path: impl AsRef<Path>) -> bool {
+    path.as_ref()
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map_or(false, |ext| {
+            IMAGE_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str())
+        })
+}
+
+/// Reads an image file's metadata from either its path or its contents
+#[dtm_command]
+pub async fn dtm_read_image_metadata(
+    path: Option<String>,
+    bytes: Option<Vec<u8>>,
+) -> crate::TAResult<ImageMetadata> {
+    let bytes = match (bytes, path) {
+        (Some(bytes), _) => bytes,
+        (None, Some(path)) => tokio::fs::read(&path).await.map_err(anyhow::Error::msg)?,
+        (None, None) => return Err(anyhow::anyhow!("Either path or bytes is required").into()),
+    };
+    Ok(read_image_metadata(&bytes)?)
+}
+
+/// Collects the text metadata of a PNG, JPEG or WebP, and parses the Draw Things metadata
+/// from it if present. Draw Things (and DTM's writers) store the same json in an XMP packet
+/// and in the EXIF UserComment; other tools may store it in a png text chunk. Metadata from
+/// A1111, ComfyUI and InvokeAI is read into `generation` when there is no Draw Things metadata.
+pub fn read_image_metadata(bytes: &[u8]) -> anyhow::Result<ImageMetadata> {
+    let (format, chunks) = if bytes.starts_with(PNG_SIGNATURE) {
+        (ImageFileFormat::Png, png_chunks(bytes)?)
+    } else if bytes.starts_with(JPEG_SOI) {
+        (ImageFileFormat::Jpeg, jpeg_chunks(bytes)?)
+    } else if bytes.get(0..4) == Some(b"RIFF") && bytes.get(8..12) == Some(b"WEBP") {
+        (ImageFileFormat::Webp, webp_chunks(bytes)?)
+    } else {
+        anyhow::bail!("Unsupported image format");
+    };
+
+    let metadata = find_drawthings_metadata(&chunks);
+    let generation = match &metadata {
+        Some(metadata) => Some(GenerationParams::from(metadata)),
+        None => find_generation_params(&chunks),
+    };
+
+    Ok(ImageMetadata {
+        format,
+        metadata,
+        generation,
+        chunks,
+    })
+}
+
+/// Reads the Draw Things metadata embedded in an image, as written by Draw Things itself or
+/// by `write_png_with_usercomment`/`write_jpeg_with_metadata`
+pub fn read_drawthings_metadata(bytes: &[u8]) -> Option<DrawThingsMetadata> {
+    read_image_metadata(bytes).ok()?.metadata
+}
+
+fn find_drawthings_metadata(chunks: &[MetadataChunk]) -> Option<DrawThingsMetadata> {
+    let is_xmp = |chunk: &&MetadataChunk| chunk.text.contains("<exif:UserComment");
+
+    // XMP first, since that is what DT reads back
+    chunks
+        .iter()
+        .filter(is_xmp)
+        .filter_map(|chunk| xmp_user_comment(&chunk.text))
+        .chain(
+            chunks
+                .iter()
+                .filter(|chunk| !is_xmp(chunk))
+                .map(|chunk| chunk.text.clone()),
+        )
+        .find_map(|json| parse_metadata_json(&json))
+}
+
+fn parse_metadata_json(json: &str) -> Option<DrawThingsMetadata> {
+    let json = json.trim();
+    if !json.starts_with('{') {
+        return None;
+    }
+    // DTM writes the json as-is, but other writers may escape it for XML
+    serde_json::from_str(json)
+        .or_else(|_| serde_json::from_str(&unescape_xml(json)))
+        .ok()
+}
+
+fn png_chunks(bytes: &[u8]) -> anyhow::Result<Vec<MetadataChunk>> {
+    let png = Png::from_bytes(Bytes::copy_from_slice(bytes))?;
+
+    Ok(png
+        .chunks()
+        .iter()
+        .filter_map(|chunk| {
+            let contents = chunk.contents();
+            match &chunk.kind() {
+                b"tEXt" => {
+                    let (keyword, text) = split_keyword(contents)?;
+                    // tEXt is latin-1
+                    let text = text.iter().map(|b| *b as char).collect();
+                    Some(text_chunk("tEXt", Some(keyword), text))
+                }
+                b"zTXt" => {
+                    let (keyword, data) = split_keyword(contents)?;
+                    let text = inflate_text(data.get(1..)?)?;
+                    Some(text_chunk("zTXt", Some(keyword), text))
+                }
+                b"iTXt" => {
+                    let (keyword, text) = parse_itxt(contents)?;
+                    Some(text_chunk("iTXt", Some(keyword), text))
+                }
+                b"eXIf" => exif_user_comment(contents)
+                    .map(|text| text_chunk(KIND_USER_COMMENT, None, text)),
+                _ => None,
+            }
+        })
+        .collect())
+}
+
+fn jpeg_chunks(bytes: &[u8]) -> anyhow::Result<Vec<MetadataChunk>> {
+    let jpeg = Jpeg::from_bytes(Bytes::copy_from_slice(bytes))?;
+
+    Ok(jpeg
+        .segments()
+        .iter()
+        .filter_map(|segment| {
+            let contents = segment.contents();
+            match segment.marker() {
+                markers::APP1 => {
+                    if let Some(xmp) = contents.strip_prefix(XMP_APP1_HEADER) {
+                        Some(text_chunk(KIND_XMP, None, utf8(xmp)))
+                    } else {
+                        let tiff = contents.strip_prefix(EXIF_APP1_HEADER)?;
+                        exif_user_comment(tiff)
+                            .map(|text| text_chunk(KIND_USER_COMMENT, None, text))
+                    }
+                }
+                markers::COM => Some(text_chunk("COM", None, utf8(contents))),
+                _ => None,
+            }
+        })
+        .collect())
+}
+
+fn webp_chunks(bytes: &[u8]) -> anyhow::Result<Vec<MetadataChunk>> {
+    let mut chunks = Vec::new();
+
+    // RIFF header is "RIFF", the file size, then "WEBP"
+    let mut offset = 12;
+    while let Some(header) = bytes.get(offset..offset + 8) {
+        let id = &header[0..4];
+        let size = u32::from_le_bytes(header[4..8].try_into()?) as usize;
+        let data = bytes
+            .get(offset + 8..offset + 8 + size)
+            .ok_or_else(|| anyhow::anyhow!("Truncated WebP chunk"))?;
+
+        match id {
+            b"XMP " => chunks.push(text_chunk(KIND_XMP, None, utf8(data))),
+            b"EXIF" => {
+                // the "Exif\0\0" prefix is not part of the spec, but some writers include it
+                let tiff = data.strip_prefix(EXIF_APP1_HEADER).unwrap_or(data);
+                if let Some(text) = exif_user_comment(tiff) {
+                    chunks.push(text_chunk(KIND_USER_COMMENT, None, text));
+                }
+            }
+            _ => {}
+        }
+
+        // chunks are padded to an even size
+        offset += 8 + size + (size & 1);
+    }
+
+    Ok(chunks)
+}
+
+fn text_chunk(kind: &str, keyword: Option<String>, text: String) -> MetadataChunk {
+    MetadataChunk {
+        kind: kind.to_string(),
+        keyword,
+        text,
+    }
+}
+
+fn utf8(data: &[u8]) -> String {
+    String::from_utf8_lossy(data).to_string()
+}
+
+fn inflate_text(data: &[u8]) -> Option<String> {
+    let mut out = String::new();
+    ZlibDecoder::new(data).read_to_string(&mut out).ok()?;
+    Some(out)
+}
+
+/// Splits the null terminated keyword from the start of a png text chunk
+fn split_keyword(data: &[u8]) -> Option<(String, &[u8])> {
+    let end = data.iter().position(|b| *b == 0)?;
+    Some((utf8(&data[..end]), &data[end + 1..]))
+}
+
+/// Returns the keyword and text of an iTXt chunk
+fn parse_itxt(data: &[u8]) -> Option<(String, String)> {
+    let (keyword, rest) = split_keyword(data)?;
+    let compressed = *rest.first()? == 1;
+
+    // skip compression flag and method, then the language tag and translated keyword
+    let mut parts = rest.get(2..)?.splitn(3, |b| *b == 0);
+    let _language = parts.next()?;
+    let _translated = parts.next()?;
+    let text = parts.next()?;
+
+    let text = match compressed {
+        true => inflate_text(text)?,
+        false => utf8(text),
+    };
+    Some((keyword, text))
+}
+
+/// Pulls the UserComment value out of an XMP packet
+fn xmp_user_comment(xmp: &str) -> Option<String> {
+    let start = xmp.find("<exif:UserComment")?;
+    let value = &xmp[start..];
+    let value = &value[value.find("<rdf:li")?..];
+    let value = &value[value.find('>')? + 1..];
+    let end = value.find("</rdf:li>")?;
+    Some(value[..end].to_string())
+}
+
+fn unescape_xml(text: &str) -> String {
+    text.replace("&#xA;", "\n")
+        .replace("&#10;", "\n")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+/// Finds the UserComment in a TIFF structured EXIF block (without the "Exif\0\0" prefix)
+fn exif_user_comment(tiff: &[u8]) -> Option<String> {
+    let big_endian = match tiff.get(0..2)? {
+        b"MM" => true,
+        b"II" => false,
+        _ => return None,
+    };
+    let read_u16 = |offset: usize| -> Option<u16> {
+        let bytes: [u8; 2] = tiff.get(offset..offset + 2)?.try_into().ok()?;
+        Some(match big_endian {
+            true => u16::from_be_bytes(bytes),
+            false => u16::from_le_bytes(bytes),
+        })
+    };
+    let read_u32 = |offset: usize| -> Option<u32> {
+        let bytes: [u8; 4] = tiff.get(offset..offset + 4)?.try_into().ok()?;
+        Some(match big_endian {
+            true => u32::from_be_bytes(bytes),
+            false => u32::from_le_bytes(bytes),
+        })
+    };
+    // returns (count, value or offset) for a tag in the ifd at `ifd`
+    let find_entry = |ifd: usize, tag: u16| -> Option<(usize, usize)> {
+        let entries = read_u16(ifd)? as usize;
+        (0..entries).find_map(|i| {
+            let entry = ifd + 2 + i * 12;
+            match read_u16(entry)? == tag {
+                true => Some((read_u32(entry + 4)? as usize, read_u32(entry + 8)? as usize)),
+                false => None,
+            }
+        })
+    };
+
+    let ifd0 = read_u32(4)? as usize;
+    let (_, exif_ifd) = find_entry(ifd0, EXIF_IFD_POINTER)?;
+    let (count, offset) = find_entry(exif_ifd, EXIF_USER_COMMENT)?;
+    let data = tiff.get(offset..offset.checked_add(count)?)?;
+
+    // the first 8 bytes identify the character code
+    let (code, text) = (data.get(..8)?, data.get(8..)?);
+    let text = match code {
+        b"UNICODE\0" => {
+            let units: Vec<u16> = text
+                .chunks_exact(2)
+                .map(|c| match big_endian {
+                    true => u16::from_be_bytes([c[0], c[1]]),
+                    false => u16::from_le_bytes([c[0], c[1]]),
+                })
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        _ => utf8(text),
+    };
+
+    Some(text.trim_end_matches('\0').to_string())
+}
@@ -0,0 +1,262 @@
+use std::collections::VecDeque;
+
+use anyhow::Result;
+use image::{imageops, GrayImage, Luma, Rgb, RgbImage};
+use serde::{Deserialize, Serialize};
+
+use crate::resize::{resize_pixels, ResizeFilter, ResizeFit, ResizeOptions};
+
+/// Space between the panels of a before/after view
+const PANEL_GAP: u32 = 16;
+const BACKGROUND: [u8; 3] = [24, 24, 24];
+
+/// How the mask is drawn in the middle panel of a before/after view
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct InpaintViewOptions {
+    /// color drawn over the masked area
+    pub tint: [u8; 3],
+    /// opacity of the tint where the mask is fully on, 0 to 1
+    pub opacity: f32,
+    /// soften the mask edge with the node's `mask_blur` and `mask_blur_outset`, as Draw Things
+    /// does when it blends the result into the canvas
+    pub blur: bool,
+}
+
+impl Default for InpaintViewOptions {
+    fn default() -> Self {
+        Self {
+            tint: [255, 0, 64],
+            opacity: 0.5,
+            blur: true,
+        }
+    }
+}
+
+/// Parses `ff0040` or `#ff0040`
+pub fn parse_hex_color(color: &str) -> Option<[u8; 3]> {
+    let color = color.strip_prefix('#').unwrap_or(color);
+    if color.len() != 6 {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(color.get(i..i + 2)?, 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+/// Grows (positive `outset`) or shrinks the mask by `outset` pixels, then blurs its edge by
+/// `blur` pixels
+pub fn soften_mask(mask: &GrayImage, blur: f32, outset: i32) -> GrayImage {
+    let mut mask = match outset {
+        0 => mask.clone(),
+        outset if outset > 0 => morph(mask, outset as u32, u8::max),
+        outset => morph(mask, outset.unsigned_abs(), u8::min),
+    };
+    if blur > 0.0 {
+        // blur is the radius of the soft edge, about two standard deviations
+        mask = imageops::blur(&mask, blur / 2.0);
+    }
+    mask
+}
+
+/// Square max (dilate) or min (erode) filter, applied separately to rows and columns
+fn morph(mask: &GrayImage, radius: u32, pick: fn(u8, u8) -> u8) -> GrayImage {
+    let (width, height) = mask.dimensions();
+    if width == 0 || height == 0 {
+        return mask.clone();
+    }
+    let (w, h) = (width as usize, height as usize);
+    let radius = radius as usize;
+
+    let mut rows = vec![0u8; w * h];
+    for (src, out) in mask.as_raw().chunks(w).zip(rows.chunks_mut(w)) {
+        window_extreme(src, radius, pick, out);
+    }
+
+    let mut out = vec![0u8; w * h];
+    let mut column = vec![0u8; h];
+    let mut picked = vec![0u8; h];
+    for x in 0..w {
+        for (y, value) in column.iter_mut().enumerate() {
+            *value = rows[y * w + x];
+        }
+        window_extreme(&column, radius, pick, &mut picked);
+        for (y, value) in picked.iter().enumerate() {
+            out[y * w + x] = *value;
+        }
+    }
+    GrayImage::from_raw(width, height, out).expect("buffer matches the mask size")
+}
+
+/// Writes the max or min of each `radius` window of `src` to `out`. The window is kept as a
+/// queue of candidate positions, so each value is added and removed once whatever the radius.
+fn window_extreme(src: &[u8], radius: usize, pick: fn(u8, u8) -> u8, out: &mut [u8]) {
+    let mut window: VecDeque<usize> = VecDeque::new();
+    let mut next = 0;
+    for (i, value) in out.iter_mut().enumerate() {
+        let end = (i + radius).min(src.len() - 1);
+        while next <= end {
+            // values the new one beats can never be picked
+            while window
+                .back()
+                .is_some_and(|&last| pick(src[last], src[next]) == src[next])
+            {
+                window.pop_back();
+            }
+            window.push_back(next);
+            next += 1;
+        }
+        let start = i.saturating_sub(radius);
+        while window.front().is_some_and(|&first| first < start) {
+            window.pop_front();
+        }
+        *value = src[window[0]];
+    }
+}
+
+/// Blends `tint` over the image, weighted by the mask
+pub fn tint_masked(image: &RgbImage, mask: &GrayImage, tint: [u8; 3], opacity: f32) -> RgbImage {
+    let opacity = opacity.clamp(0.0, 1.0);
+    let mut out = image.clone();
+    for (x, y, Rgb(pixel)) in out.enumerate_pixels_mut() {
+        let alpha = mask.get_pixel(x, y)[0] as f32 / 255.0 * opacity;
+        for (p, t) in pixel.iter_mut().zip(tint) {
+            *p = (*p as f32 * (1.0 - alpha) + t as f32 * alpha).round() as u8;
+        }
+    }
+    out
+}
+
+/// Draws the images left to right, centered vertically, with a gap between them
+pub fn side_by_side(panels: &[RgbImage]) -> RgbImage {
+    let gaps = PANEL_GAP * panels.len().saturating_sub(1) as u32;
+    let width = panels.iter().map(|p| p.width()).sum::<u32>() + gaps;
+    let height = panels.iter().map(|p| p.height()).max().unwrap_or(0);
+    let mut out = RgbImage::from_pixel(width.max(1), height.max(1), Rgb(BACKGROUND));
+    let mut x = 0;
+    for panel in panels {
+        let y = (height - panel.height()) / 2;
+        imageops::replace(&mut out, panel, x as i64, y as i64);
+        x += panel.width() + PANEL_GAP;
+    }
+    out
+}
+
+/// Renders a before/after view of an inpainting pass: the source canvas, the source with the
+/// mask drawn over it, and the result. The source and mask are resized to the result's size.
+pub fn render_inpaint_view(
+    source: &RgbImage,
+    mask: &GrayImage,
+    result: &RgbImage,
+    (mask_blur, mask_blur_outset): (f32, i32),
+    options: &InpaintViewOptions,
+) -> Result<RgbImage> {
+    let (width, height) = result.dimensions();
+    let source = match source.dimensions() == (width, height) {
+        true => source.clone(),
+        false => {
+            let (pixels, w, h) = resize_pixels(
+                source.as_raw(),
+                source.width(),
+                source.height(),
+                3,
+                &exact(width, height, ResizeFilter::Lanczos3),
+            )?;
+            RgbImage::from_raw(w, h, pixels)
+                .ok_or_else(|| anyhow::anyhow!("Invalid source size"))?
+        }
+    };
+
+    // the mask is resized while it is still hard edged, so the blur is in result pixels
+    let mut mask = match mask.dimensions() == (width, height) {
+        true => mask.clone(),
+        false => {
+            let (pixels, w, h) = resize_pixels(
+                mask.as_raw(),
+                mask.width(),
+                mask.height(),
+                1,
+                &exact(width, height, ResizeFilter::Box),
+            )?;
+            GrayImage::from_raw(w, h, pixels).ok_or_else(|| anyhow::anyhow!("Invalid mask size"))?
+        }
+    };
+    if options.blur {
+        mask = soften_mask(&mask, mask_blur, mask_blur_outset);
+    }
+
+    let masked = tint_masked(&source, &mask, options.tint, options.opacity);
+    Ok(side_by_side(&[source, masked, result.clone()]))
+}
+
+fn exact(width: u32, height: u32, filter: ResizeFilter) -> ResizeOptions {
+    ResizeOptions {
+        width,
+        height,
+        fit: ResizeFit::Exact,
+        filter,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 9x9 with only the center pixel on
+    fn dot() -> GrayImage {
+        GrayImage::from_fn(9, 9, |x, y| match (x, y) {
+            (4, 4) => Luma([255]),
+            _ => Luma([0]),
+        })
+    }
+
+    #[test]
+    fn test_parse_hex_color() {
+        assert_eq!(parse_hex_color("ff0040"), Some([255, 0, 64]));
+        assert_eq!(parse_hex_color("#00FF00"), Some([0, 255, 0]));
+        assert_eq!(parse_hex_color("f00"), None);
+        assert_eq!(parse_hex_color("gg0000"), None);
+    }
+
+    #[test]
+    fn test_soften_mask() {
+        let grown = soften_mask(&dot(), 0.0, 2);
+        assert_eq!(grown.get_pixel(2, 2)[0], 255);
+        assert_eq!(grown.get_pixel(6, 6)[0], 255);
+        assert_eq!(grown.get_pixel(1, 4)[0], 0);
+
+        let shrunk = soften_mask(&grown, 0.0, -2);
+        assert_eq!(shrunk, dot());
+
+        // the window is clamped to the image
+        let filled = soften_mask(&dot(), 0.0, 20);
+        assert!(filled.pixels().all(|p| p[0] == 255));
+
+        // blurring spreads the dot without moving it
+        let blurred = soften_mask(&dot(), 4.0, 0);
+        let center = blurred.get_pixel(4, 4)[0];
+        assert!(center < 255);
+        assert!(blurred.get_pixel(3, 4)[0] > 0);
+        assert!(blurred.get_pixel(3, 4)[0] < center);
+    }
+
+    #[test]
+    fn test_render_inpaint_view() {
+        let source = RgbImage::from_pixel(4, 4, Rgb([0, 0, 0]));
+        let result = RgbImage::from_pixel(8, 8, Rgb([255, 255, 255]));
+        // left half masked, at a different size than the result
+        let mask = GrayImage::from_fn(2, 2, |x, _| Luma([if x == 0 { 255 } else { 0 }]));
+        let options = InpaintViewOptions {
+            tint: [255, 0, 0],
+            opacity: 1.0,
+            blur: false,
+        };
+
+        let view = render_inpaint_view(&source, &mask, &result, (0.0, 0), &options).unwrap();
+        assert_eq!(view.dimensions(), (8 * 3 + PANEL_GAP * 2, 8));
+        assert_eq!(view.get_pixel(0, 0).0, [0, 0, 0]);
+        let masked = 8 + PANEL_GAP;
+        assert_eq!(view.get_pixel(masked, 0).0, [255, 0, 0]);
+        assert_eq!(view.get_pixel(masked + 7, 0).0, [0, 0, 0]);
+        assert_eq!(view.get_pixel(view.width() - 1, 7).0, [255, 255, 255]);
+    }
+}
@@ -64,8 +64,8 @@ impl TryFrom<&TensorHistoryNodeData> for DrawThingsMetadata {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
 pub struct V2 {
     #[serde(serialize_with = "serialize_float")]
     pub aesthetic_score: f32,
@@ -412,3 +412,36 @@ impl fmt::Display for Sampler {
         write!(f, "{}", s)
     }
 }
+
+impl Sampler {
+    /// The closest A1111 sampler and schedule type. Trailing maps to SGM Uniform, which
+    /// spaces the timesteps the same way.
+    pub fn a1111_name(&self) -> Option<(&'static str, Option<&'static str>)> {
+        const KARRAS: Option<&str> = Some("Karras");
+        const SGM_UNIFORM: Option<&str> = Some("SGM Uniform");
+        const AYS: Option<&str> = Some("Align Your Steps");
+
+        Some(match self {
+            Sampler::Unknown => return None,
+            Sampler::DPMPP2MKarras => ("DPM++ 2M", KARRAS),
+            Sampler::EulerA | Sampler::EulerASubstep => ("Euler a", None),
+            Sampler::DDIM => ("DDIM", None),
+            Sampler::PLMS => ("PLMS", None),
+            Sampler::DPMPPSDEKarras => ("DPM++ SDE", KARRAS),
+            Sampler::UniPC => ("UniPC", None),
+            Sampler::LCM => ("LCM", None),
+            Sampler::DPMPPSDESubstep => ("DPM++ SDE", None),
+            Sampler::TCD => ("TCD", None),
+            Sampler::EulerATrailing => ("Euler a", SGM_UNIFORM),
+            Sampler::DPMPPSDETrailing => ("DPM++ SDE", SGM_UNIFORM),
+            Sampler::DPMPP2MAYS => ("DPM++ 2M", AYS),
+            Sampler::EulerAAYS => ("Euler a", AYS),
+            Sampler::DPMPPSDEAYS => ("DPM++ SDE", AYS),
+            Sampler::DPMPP2MTrailing => ("DPM++ 2M", SGM_UNIFORM),
+            Sampler::DDIMTrailing => ("DDIM", SGM_UNIFORM),
+            Sampler::UniPCTrailing => ("UniPC", SGM_UNIFORM),
+            Sampler::UniPCAYS => ("UniPC", AYS),
+            Sampler::TCDTrailing => ("TCD", SGM_UNIFORM),
+        })
+    }
+}
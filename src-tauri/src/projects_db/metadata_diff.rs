@@ -0,0 +1,143 @@
+use std::collections::BTreeSet;
+
+use serde_json::Value;
+
+use crate::projects_db::{
+    dtos::metadata_diff::{DiffOp, FieldDiff, MetadataDiff, ModelWeightDiff, WordDiff},
+    metadata::{DrawThingsMetadata, V2},
+};
+
+/// Compares the generation settings of two images. Every `V2` field is listed, with loras and
+/// controls matched up by file, and prompts compared word by word.
+pub fn diff_metadata(a: &DrawThingsMetadata, b: &DrawThingsMetadata) -> MetadataDiff {
+    MetadataDiff {
+        fields: diff_fields(&a.v2, &b.v2),
+        prompt: diff_words(&a.c, &b.c),
+        negative_prompt: diff_words(&a.uc, &b.uc),
+        loras: diff_model_weights(&a.v2.loras, &b.v2.loras),
+        controls: diff_model_weights(&a.v2.controls, &b.v2.controls),
+    }
+}
+
+fn diff_fields(a: &V2, b: &V2) -> Vec<FieldDiff> {
+    let to_object = |v2: &V2| match serde_json::to_value(v2) {
+        Ok(Value::Object(map)) => map,
+        _ => Default::default(),
+    };
+    let (a, b) = (to_object(a), to_object(b));
+
+    // loras and controls are compared separately
+    let fields: BTreeSet<&String> = a
+        .keys()
+        .chain(b.keys())
+        .filter(|field| !matches!(field.as_str(), "loras" | "controls"))
+        .collect();
+
+    fields
+        .into_iter()
+        .map(|field| {
+            let a = a.get(field).cloned().unwrap_or_default();
+            let b = b.get(field).cloned().unwrap_or_default();
+            FieldDiff {
+                field: field.clone(),
+                changed: a != b,
+                a,
+                b,
+            }
+        })
+        .collect()
+}
+
+/// Word level diff using the longest common subsequence. Adjacent words with the same op are
+/// joined into a single run.
+fn diff_words(a: &str, b: &str) -> Vec<WordDiff> {
+    let a: Vec<&str> = a.split_whitespace().collect();
+    let b: Vec<&str> = b.split_whitespace().collect();
+
+    // lcs[i][j] is the length of the lcs of a[i..] and b[j..]
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = match a[i] == b[j] {
+                true => lcs[i + 1][j + 1] + 1,
+                false => lcs[i + 1][j].max(lcs[i][j + 1]),
+            };
+        }
+    }
+
+    let mut diff: Vec<WordDiff> = Vec::new();
+    let mut push = |op: DiffOp, word: &str| match diff.last_mut() {
+        Some(last) if last.op == op => {
+            last.text.push(' ');
+            last.text.push_str(word);
+        }
+        _ => diff.push(WordDiff {
+            op,
+            text: word.to_string(),
+        }),
+    };
+
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            push(DiffOp::Equal, a[i]);
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            push(DiffOp::Removed, a[i]);
+            i += 1;
+        } else {
+            push(DiffOp::Added, b[j]);
+            j += 1;
+        }
+    }
+    a[i..].iter().for_each(|word| push(DiffOp::Removed, word));
+    b[j..].iter().for_each(|word| push(DiffOp::Added, word));
+
+    diff
+}
+
+/// Loras and controls are stored as `{ "file": ..., "weight": ... }` objects
+fn diff_model_weights(a: &[Value], b: &[Value]) -> Vec<ModelWeightDiff> {
+    let weights = |entries: &[Value]| -> Vec<(String, f32)> {
+        entries
+            .iter()
+            .filter_map(|entry| {
+                let file = entry["file"].as_str()?.to_string();
+                Some((file, entry["weight"].as_f64().unwrap_or(1.0) as f32))
+            })
+            .collect()
+    };
+    let (a, b) = (weights(a), weights(b));
+    let find = |entries: &[(String, f32)], file: &str| {
+        entries.iter().find(|(f, _)| f == file).map(|(_, w)| *w)
+    };
+
+    // keep the order of a, then anything new in b
+    let mut files: Vec<&String> = a.iter().map(|(file, _)| file).collect();
+    for (file, _) in &b {
+        if !files.contains(&file) {
+            files.push(file);
+        }
+    }
+
+    files
+        .into_iter()
+        .map(|file| {
+            let weight_a = find(&a, file);
+            let weight_b = find(&b, file);
+            let op = match (weight_a, weight_b) {
+                (Some(wa), Some(wb)) if wa == wb => DiffOp::Equal,
+                (Some(_), Some(_)) => DiffOp::Changed,
+                (Some(_), None) => DiffOp::Removed,
+                _ => DiffOp::Added,
+            };
+            ModelWeightDiff {
+                file: file.clone(),
+                op,
+                weight_a,
+                weight_b,
+            }
+        })
+        .collect()
+}
@@ -19,20 +19,41 @@ pub mod tensor_history_generated;
 mod dtm_dtproject;
 pub use dtm_dtproject::{extract_jpeg_slice, DTPResource, DtmProtocol};
 
+pub mod thumb_cache;
+pub use thumb_cache::ThumbCache;
+
 mod tensor_history_mod;
 
 mod tensors;
 pub use tensors::{
-    build_description, decode_tensor, write_jpeg_with_metadata, write_png_with_usercomment,
+    add_a1111_parameters, build_a1111_parameters, build_description, build_drawthings_xmp,
+    decode_tensor, encode_high_bit_depth, encode_tensor_data, strip_image_metadata,
+    write_export_metadata, write_jpeg_with_metadata, write_png_with_usercomment,
     DecodeTensorOptions, decode_pose, scribble_mask_to_png, inflate_deflate, decompress_fzip,
+    MAX_TENSOR_VALUES,
 };
 
+mod tensor_file;
+pub use tensor_file::{encode_npy, encode_safetensors, encode_tensor_file, tensor_shape};
+
 mod audio;
 pub use audio::{decode_audio, get_audio};
 
 mod metadata;
 pub use metadata::DrawThingsMetadata;
 
+pub mod image_metadata;
+
+pub mod foreign_metadata;
+
+pub mod metadata_diff;
+
+pub mod pose;
+
+pub mod composite;
+
+pub mod inpaint;
+
 mod text_history;
 pub use text_history::TextHistory;
 
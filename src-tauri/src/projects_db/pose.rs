@@ -0,0 +1,212 @@
+use std::io::Cursor;
+
+use anyhow::Result;
+use image::{ImageFormat, Rgb, RgbImage};
+
+/// Number of keypoints per person in an OpenPose (COCO 18) skeleton
+pub const POSE_KEYPOINTS: usize = 18;
+
+/// Limbs as pairs of keypoint indices, in the standard OpenPose drawing order
+const LIMBS: [(usize, usize); 17] = [
+    (1, 2),
+    (1, 5),
+    (2, 3),
+    (3, 4),
+    (5, 6),
+    (6, 7),
+    (1, 8),
+    (8, 9),
+    (9, 10),
+    (1, 11),
+    (11, 12),
+    (12, 13),
+    (1, 0),
+    (0, 14),
+    (14, 16),
+    (0, 15),
+    (15, 17),
+];
+
+/// OpenPose colors, used for both the limbs and the keypoints
+const COLORS: [[u8; 3]; POSE_KEYPOINTS] = [
+    [255, 0, 0],
+    [255, 85, 0],
+    [255, 170, 0],
+    [255, 255, 0],
+    [170, 255, 0],
+    [85, 255, 0],
+    [0, 255, 0],
+    [0, 255, 85],
+    [0, 255, 170],
+    [0, 255, 255],
+    [0, 170, 255],
+    [0, 85, 255],
+    [0, 0, 255],
+    [85, 0, 255],
+    [170, 0, 255],
+    [255, 0, 255],
+    [255, 0, 170],
+    [255, 0, 85],
+];
+
+/// Limbs are blended over the background, keypoints are drawn opaque
+const LIMB_ALPHA: f32 = 0.6;
+/// Limb and keypoint radius for a 512px canvas, scaled up for larger canvases
+const LIMB_RADIUS: f32 = 4.0;
+const KEYPOINT_RADIUS: f32 = 4.0;
+
+/// A person's keypoints in canvas pixels, None for missing points
+pub type Skeleton = [Option<(f32, f32)>; POSE_KEYPOINTS];
+
+/// Splits a decoded pose tensor into skeletons, one per person. The tensor holds 18 (x, y)
+/// pairs per person, normalized to `[0, 1]`, with (-1, -1) for missing points.
+pub fn pose_skeletons(keypoints: &[f32], width: u32, height: u32) -> Vec<Skeleton> {
+    keypoints
+        .chunks_exact(POSE_KEYPOINTS * 2)
+        .map(|person| {
+            let mut skeleton: Skeleton = [None; POSE_KEYPOINTS];
+            for (i, point) in person.chunks_exact(2).enumerate() {
+                let (x, y) = (point[0], point[1]);
+                if x >= 0.0 && y >= 0.0 {
+                    skeleton[i] = Some((x * width as f32, y * height as f32));
+                }
+            }
+            skeleton
+        })
+        .filter(|skeleton| skeleton.iter().any(Option::is_some))
+        .collect()
+}
+
+/// Draws OpenPose style skeletons for every person in a pose tensor, either on a black canvas
+/// of `width` x `height`, or over `background` (in which case its size is used). Returns a PNG.
+pub fn render_pose(
+    keypoints: &[f32],
+    width: u32,
+    height: u32,
+    background: Option<RgbImage>,
+) -> Result<Vec<u8>> {
+    let mut canvas = background.unwrap_or_else(|| RgbImage::new(width, height));
+    let (width, height) = canvas.dimensions();
+    if width == 0 || height == 0 {
+        anyhow::bail!("Pose canvas has no size");
+    }
+
+    let scale = (width.max(height) as f32 / 512.0).max(1.0);
+    for skeleton in pose_skeletons(keypoints, width, height) {
+        draw_skeleton(&mut canvas, &skeleton, scale);
+    }
+
+    let mut png = Vec::new();
+    canvas.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+    Ok(png)
+}
+
+fn draw_skeleton(canvas: &mut RgbImage, skeleton: &Skeleton, scale: f32) {
+    for (i, (a, b)) in LIMBS.iter().enumerate() {
+        if let (Some(a), Some(b)) = (skeleton[*a], skeleton[*b]) {
+            draw_segment(canvas, a, b, LIMB_RADIUS * scale, COLORS[i], LIMB_ALPHA);
+        }
+    }
+    for (i, point) in skeleton.iter().enumerate() {
+        if let Some(point) = point {
+            draw_segment(
+                canvas,
+                *point,
+                *point,
+                KEYPOINT_RADIUS * scale,
+                COLORS[i],
+                1.0,
+            );
+        }
+    }
+}
+
+/// Fills every pixel within `radius` of the segment a-b. A zero length segment is a disc.
+fn draw_segment(
+    canvas: &mut RgbImage,
+    a: (f32, f32),
+    b: (f32, f32),
+    radius: f32,
+    color: [u8; 3],
+    alpha: f32,
+) {
+    let (width, height) = canvas.dimensions();
+    let x0 = (a.0.min(b.0) - radius).floor().max(0.0) as u32;
+    let y0 = (a.1.min(b.1) - radius).floor().max(0.0) as u32;
+    let x1 = ((a.0.max(b.0) + radius).ceil().max(0.0) as u32).min(width);
+    let y1 = ((a.1.max(b.1) + radius).ceil().max(0.0) as u32).min(height);
+
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let length_sq = dx * dx + dy * dy;
+
+    for y in y0..y1 {
+        for x in x0..x1 {
+            let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+            // closest point on the segment
+            let t = match length_sq > 0.0 {
+                true => (((px - a.0) * dx + (py - a.1) * dy) / length_sq).clamp(0.0, 1.0),
+                false => 0.0,
+            };
+            let (cx, cy) = (a.0 + t * dx, a.1 + t * dy);
+            if (px - cx).powi(2) + (py - cy).powi(2) > radius * radius {
+                continue;
+            }
+
+            let Rgb(pixel) = canvas.get_pixel_mut(x, y);
+            for (p, c) in pixel.iter_mut().zip(color) {
+                *p = (*p as f32 * (1.0 - alpha) + c as f32 * alpha).round() as u8;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn person(points: &[(usize, f32, f32)]) -> Vec<f32> {
+        let mut keypoints = vec![-1.0; POSE_KEYPOINTS * 2];
+        for (i, x, y) in points {
+            keypoints[i * 2] = *x;
+            keypoints[i * 2 + 1] = *y;
+        }
+        keypoints
+    }
+
+    #[test]
+    fn test_pose_skeletons() {
+        let mut keypoints = person(&[(0, 0.5, 0.25), (1, 0.5, 0.5)]);
+        keypoints.extend(person(&[]));
+        keypoints.extend(person(&[(2, 0.25, 0.5)]));
+
+        // the empty person is dropped
+        let skeletons = pose_skeletons(&keypoints, 200, 100);
+        assert_eq!(skeletons.len(), 2);
+        assert_eq!(skeletons[0][0], Some((100.0, 25.0)));
+        assert_eq!(skeletons[0][1], Some((100.0, 50.0)));
+        assert_eq!(skeletons[0][2], None);
+        assert_eq!(skeletons[1][2], Some((50.0, 50.0)));
+    }
+
+    #[test]
+    fn test_render_pose() {
+        // nose to neck is limb 12
+        let keypoints = person(&[(0, 0.5, 0.25), (1, 0.5, 0.75)]);
+        let png = render_pose(&keypoints, 64, 64, None).unwrap();
+        let image = image::load_from_memory(&png).unwrap().to_rgb8();
+        assert_eq!(image.dimensions(), (64, 64));
+
+        // keypoints are opaque, limbs are blended, and the rest is untouched
+        assert_eq!(image.get_pixel(32, 16).0, COLORS[0]);
+        let limb = COLORS[12].map(|c| (c as f32 * LIMB_ALPHA).round() as u8);
+        assert_eq!(image.get_pixel(32, 32).0, limb);
+        assert_eq!(image.get_pixel(4, 4).0, [0, 0, 0]);
+
+        // with a background, its size is used
+        let background = RgbImage::from_pixel(32, 16, Rgb([255, 255, 255]));
+        let png = render_pose(&keypoints, 64, 64, Some(background)).unwrap();
+        let image = image::load_from_memory(&png).unwrap().to_rgb8();
+        assert_eq!(image.dimensions(), (32, 16));
+        assert_eq!(image.get_pixel(0, 15).0, [255, 255, 255]);
+    }
+}
@@ -48,60 +48,17 @@ impl ProjectsDb {
             _ => Order::Desc,
         };
 
-        let mut query = images::Entity::find()
-            .join(JoinType::LeftJoin, images::Relation::Models.def())
-            .join(JoinType::LeftJoin, images::Relation::Projects.def())
-            .join(JoinType::LeftJoin, projects::Relation::WatchFolders.def())
-            .column_as(entity::models::Column::Filename, "model_file")
-            .column_as(
-                Expr::col(watch_folders::Column::IsMissing)
-                    .eq(false)
-                    .and(Expr::col(watch_folders::Column::IsLocked).eq(false)),
-                "is_ready",
-            );
-
-        if opts.show_disconnected != Some(true) {
-            query = query.filter(
-                Expr::col(watch_folders::Column::IsMissing)
-                    .eq(false)
-                    .and(Expr::col(watch_folders::Column::IsLocked).eq(false)),
-            );
-        }
-
-        let mut query = query.order_by(images::Column::WallClock, direction);
-
-        if let Some(project_ids) = &opts.project_ids {
-            if !project_ids.is_empty() {
-                query = query.filter(images::Column::ProjectId.is_in(project_ids.clone()));
-            }
-        }
-
-        if let Some(search_text) = &opts.search {
-            query = search::add_search(query, search_text);
-        }
-
-        if let Some(filters) = opts.filters {
-            for f in filters {
-                query = f.target.apply(f.operator, &f.value, query);
+        let query = match filtered_images_query(&opts) {
+            Some(query) => query,
+            None => {
+                return Ok(ListImagesResult {
+                    counts: None,
+                    images: Some(vec![]),
+                    total: 0,
+                })
             }
-        }
-
-        let show_image = opts.show_image.unwrap_or(true);
-        let show_video = opts.show_video.unwrap_or(true);
-
-        if !show_image && !show_video {
-            return Ok(ListImagesResult {
-                counts: None,
-                images: Some(vec![]),
-                total: 0,
-            });
-        }
-
-        if show_image && !show_video {
-            query = query.filter(images::Column::NumFrames.is_null());
-        } else if !show_image && show_video {
-            query = query.filter(images::Column::NumFrames.is_not_null());
-        }
+        };
+        let mut query = query.order_by(images::Column::WallClock, direction);
 
         if Some(true) == opts.count {
             let project_counts = query
@@ -193,3 +150,61 @@ impl ProjectsDb {
         Ok(clip)
     }
 }
+
+/// Builds the images query for everything in `ListImagesOptions` that narrows the selection
+/// (projects, search, filters, image/video, disconnected). Sorting and paging are left to the
+/// caller. Returns None when the options can't match anything.
+pub(super) fn filtered_images_query(
+    opts: &ListImagesOptions,
+) -> Option<sea_orm::Select<images::Entity>> {
+    let mut query = images::Entity::find()
+        .join(JoinType::LeftJoin, images::Relation::Models.def())
+        .join(JoinType::LeftJoin, images::Relation::Projects.def())
+        .join(JoinType::LeftJoin, projects::Relation::WatchFolders.def())
+        .column_as(entity::models::Column::Filename, "model_file")
+        .column_as(
+            Expr::col(watch_folders::Column::IsMissing)
+                .eq(false)
+                .and(Expr::col(watch_folders::Column::IsLocked).eq(false)),
+            "is_ready",
+        );
+
+    if opts.show_disconnected != Some(true) {
+        query = query.filter(
+            Expr::col(watch_folders::Column::IsMissing)
+                .eq(false)
+                .and(Expr::col(watch_folders::Column::IsLocked).eq(false)),
+        );
+    }
+
+    if let Some(project_ids) = &opts.project_ids {
+        if !project_ids.is_empty() {
+            query = query.filter(images::Column::ProjectId.is_in(project_ids.clone()));
+        }
+    }
+
+    if let Some(search_text) = &opts.search {
+        query = search::add_search(query, search_text);
+    }
+
+    if let Some(filters) = &opts.filters {
+        for f in filters {
+            query = f.target.apply(f.operator.clone(), &f.value, query);
+        }
+    }
+
+    let show_image = opts.show_image.unwrap_or(true);
+    let show_video = opts.show_video.unwrap_or(true);
+
+    if !show_image && !show_video {
+        return None;
+    }
+
+    if show_image && !show_video {
+        query = query.filter(images::Column::NumFrames.is_null());
+    } else if !show_image && show_video {
+        query = query.filter(images::Column::NumFrames.is_not_null());
+    }
+
+    Some(query)
+}
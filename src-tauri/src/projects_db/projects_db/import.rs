@@ -8,7 +8,7 @@ use entity::{
     enums::{ModelType, Sampler},
     images,
 };
-use sea_orm::{sea_query::OnConflict, ConnectionTrait, EntityTrait, Set};
+use sea_orm::{sea_query::OnConflict, ConnectionTrait, EntityTrait, Set, Statement};
 use std::collections::{HashMap, HashSet};
 
 use super::models::ModelTypeAndFile;
@@ -30,6 +30,10 @@ impl ProjectsDb {
             return Ok((project.id, 0));
         }
 
+        if project.is_loose_images() {
+            return self.scan_image_folder(&project).await;
+        }
+
         let dt_project = DTProject::open(&project.full_path).await?;
         let dt_project_info = dt_project.get_info().await?;
         let end = dt_project_info.history_max_id;
@@ -92,6 +96,7 @@ impl ProjectsDb {
             }
 
             self.insert_related_data(
+                &self.db,
                 &node_id_to_image_id,
                 batch_image_loras,
                 batch_image_controls,
@@ -215,10 +220,10 @@ impl ProjectsDb {
                     start_width: Set(fb.start_width() as i16),
                     start_height: Set(fb.start_height() as i16),
                     seed: Set(fb.seed() as i64),
-                    strength: Set(fb.strength()),
+                    strength: Set(Some(fb.strength())),
                     steps: Set(fb.steps() as i16),
                     guidance_scale: Set(fb.guidance_scale()),
-                    shift: Set(fb.shift()),
+                    shift: Set(Some(fb.shift())),
                     hires_fix: Set(fb.hires_fix()),
                     tiled_decoding: Set(fb.tiled_decoding()),
                     tiled_diffusion: Set(fb.tiled_diffusion()),
@@ -308,6 +313,7 @@ impl ProjectsDb {
 
     pub async fn insert_related_data(
         &self,
+        db: &impl ConnectionTrait,
         node_id_to_image_id: &HashMap<i64, i64>,
         batch_image_loras: Vec<NodeModelWeight>,
         batch_image_controls: Vec<NodeModelWeight>,
@@ -334,7 +340,7 @@ impl ProjectsDb {
                     .do_nothing()
                     .to_owned(),
                 )
-                .exec(&self.db)
+                .exec(db)
                 .await?;
         }
 
@@ -360,7 +366,7 @@ impl ProjectsDb {
                     .do_nothing()
                     .to_owned(),
                 )
-                .exec(&self.db)
+                .exec(db)
                 .await?;
         }
 
@@ -374,4 +380,49 @@ impl ProjectsDb {
 
         Ok(())
     }
+
+    /// Deletes the FTS rows of `removed` images, without rebuilding the whole index.
+    /// images_fts is an external content table, so rows must be deleted with the values they
+    /// were indexed with.
+    pub async fn delete_image_fts(
+        db: &impl ConnectionTrait,
+        removed: &[images::Model],
+    ) -> Result<(), MixedError> {
+        for image in removed {
+            db.execute(Statement::from_sql_and_values(
+                db.get_database_backend(),
+                "INSERT INTO images_fts(images_fts, rowid, prompt_search, negative_prompt_search) \
+                 VALUES('delete', ?, ?, ?)",
+                [
+                    image.id.into(),
+                    image.prompt_search.clone().into(),
+                    image.negative_prompt_search.clone().into(),
+                ],
+            ))
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Adds the FTS rows of `inserted` images, without rebuilding the whole index
+    pub async fn insert_image_fts(
+        db: &impl ConnectionTrait,
+        inserted: &[images::Model],
+    ) -> Result<(), MixedError> {
+        for image in inserted {
+            db.execute(Statement::from_sql_and_values(
+                db.get_database_backend(),
+                "INSERT INTO images_fts(rowid, prompt_search, negative_prompt_search) VALUES(?, ?, ?)",
+                [
+                    image.id.into(),
+                    image.prompt_search.clone().into(),
+                    image.negative_prompt_search.clone().into(),
+                ],
+            ))
+            .await?;
+        }
+
+        Ok(())
+    }
 }
@@ -0,0 +1,424 @@
+use std::path::Path;
+
+use futures::StreamExt;
+use sea_orm::{prelude::DateTimeUtc, FromQueryResult, PaginatorTrait, QueryOrder, QuerySelect};
+use sea_query::Expr;
+use sqlx::{sqlite::SqliteConnectOptions, AssertSqlSafe, Connection, SqliteConnection};
+use tokio::{
+    fs::File,
+    io::{AsyncWriteExt, BufWriter},
+};
+
+use crate::projects_db::{
+    dtos::{image::ListImagesOptions, index::IndexExportFormat},
+    folder_cache,
+    metadata::Sampler,
+};
+use entity::{images, models, projects};
+
+use super::{images::filtered_images_query, MixedError, ProjectsDb};
+
+/// Number of rows written to a sqlite export per transaction
+const SQLITE_BATCH_SIZE: u64 = 5000;
+
+/// Exported columns and their sqlite types, in output order
+const COLUMNS: &[(&str, &str)] = &[
+    ("id", "INTEGER PRIMARY KEY"),
+    ("project_id", "INTEGER NOT NULL"),
+    ("project_path", "TEXT NOT NULL"),
+    ("node_id", "INTEGER NOT NULL"),
+    ("preview_id", "INTEGER NOT NULL"),
+    ("clip_id", "INTEGER NOT NULL"),
+    ("num_frames", "INTEGER"),
+    ("wall_clock", "TEXT NOT NULL"),
+    ("model", "TEXT"),
+    ("model_name", "TEXT"),
+    ("refiner", "TEXT"),
+    ("refiner_start", "REAL"),
+    ("upscaler", "TEXT"),
+    ("upscaler_scale_factor", "INTEGER"),
+    ("prompt", "TEXT NOT NULL"),
+    ("negative_prompt", "TEXT NOT NULL"),
+    ("width", "INTEGER NOT NULL"),
+    ("height", "INTEGER NOT NULL"),
+    ("seed", "INTEGER NOT NULL"),
+    ("strength", "REAL"),
+    ("steps", "INTEGER NOT NULL"),
+    ("guidance_scale", "REAL NOT NULL"),
+    ("shift", "REAL"),
+    ("sampler", "TEXT NOT NULL"),
+    ("hires_fix", "INTEGER NOT NULL"),
+    ("tiled_decoding", "INTEGER NOT NULL"),
+    ("tiled_diffusion", "INTEGER NOT NULL"),
+    ("tea_cache", "INTEGER NOT NULL"),
+    ("cfg_zero_star", "INTEGER NOT NULL"),
+    ("has_mask", "INTEGER NOT NULL"),
+    ("has_depth", "INTEGER NOT NULL"),
+    ("has_pose", "INTEGER NOT NULL"),
+    ("has_color", "INTEGER NOT NULL"),
+    ("has_custom", "INTEGER NOT NULL"),
+    ("has_scribble", "INTEGER NOT NULL"),
+    ("has_shuffle", "INTEGER NOT NULL"),
+    ("loras", "TEXT NOT NULL"),
+    ("controls", "TEXT NOT NULL"),
+];
+
+#[derive(Debug, FromQueryResult)]
+struct IndexQueryRow {
+    id: i64,
+    project_id: i64,
+    project_path: String,
+    watchfolder_id: i64,
+    node_id: i64,
+    preview_id: i64,
+    clip_id: i64,
+    num_frames: Option<i16>,
+    wall_clock: DateTimeUtc,
+    model_file: Option<String>,
+    model_name: Option<String>,
+    refiner_file: Option<String>,
+    refiner_start: Option<f32>,
+    upscaler_file: Option<String>,
+    upscaler_scale_factor: Option<u8>,
+    prompt: String,
+    negative_prompt: String,
+    start_width: i16,
+    start_height: i16,
+    seed: i64,
+    strength: Option<f32>,
+    steps: i16,
+    guidance_scale: f32,
+    shift: Option<f32>,
+    sampler: i8,
+    hires_fix: bool,
+    tiled_decoding: bool,
+    tiled_diffusion: bool,
+    tea_cache: bool,
+    cfg_zero_star: bool,
+    has_mask: bool,
+    has_depth: bool,
+    has_pose: bool,
+    has_color: bool,
+    has_custom: bool,
+    has_scribble: bool,
+    has_shuffle: bool,
+    loras: Option<String>,
+    controls: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+enum IndexValue {
+    Null,
+    Int(i64),
+    Real(f64),
+    Bool(bool),
+    Text(String),
+    /// json array, kept as text for csv and sqlite
+    Json(String),
+}
+
+impl IndexQueryRow {
+    /// Values in `COLUMNS` order
+    fn values(self) -> Vec<IndexValue> {
+        use IndexValue::*;
+
+        let project_path = match folder_cache::get_folder(self.watchfolder_id) {
+            Some(folder) => Path::new(&folder)
+                .join(&self.project_path)
+                .to_string_lossy()
+                .to_string(),
+            None => self.project_path,
+        };
+        let sampler = Sampler::try_from(self.sampler)
+            .ok()
+            .and_then(|s| serde_json::to_value(s).ok())
+            .and_then(|v| v.as_str().map(|s| s.to_string()))
+            .unwrap_or_else(|| self.sampler.to_string());
+        let text = |v: Option<String>| v.map_or(Null, Text);
+
+        vec![
+            Int(self.id),
+            Int(self.project_id),
+            Text(project_path),
+            Int(self.node_id),
+            Int(self.preview_id),
+            Int(self.clip_id),
+            self.num_frames.map_or(Null, |v| Int(v as i64)),
+            Text(self.wall_clock.to_rfc3339()),
+            text(self.model_file),
+            text(self.model_name),
+            text(self.refiner_file),
+            self.refiner_start.map_or(Null, |v| Real(v as f64)),
+            text(self.upscaler_file),
+            self.upscaler_scale_factor.map_or(Null, |v| Int(v as i64)),
+            Text(self.prompt),
+            Text(self.negative_prompt),
+            // stored in 64px blocks
+            Int(self.start_width as i64 * 64),
+            Int(self.start_height as i64 * 64),
+            Int(self.seed),
+            self.strength.map_or(Null, |v| Real(v as f64)),
+            Int(self.steps as i64),
+            Real(self.guidance_scale as f64),
+            self.shift.map_or(Null, |v| Real(v as f64)),
+            Text(sampler),
+            Bool(self.hires_fix),
+            Bool(self.tiled_decoding),
+            Bool(self.tiled_diffusion),
+            Bool(self.tea_cache),
+            Bool(self.cfg_zero_star),
+            Bool(self.has_mask),
+            Bool(self.has_depth),
+            Bool(self.has_pose),
+            Bool(self.has_color),
+            Bool(self.has_custom),
+            Bool(self.has_scribble),
+            Bool(self.has_shuffle),
+            Json(self.loras.unwrap_or_else(|| "[]".to_string())),
+            Json(self.controls.unwrap_or_else(|| "[]".to_string())),
+        ]
+    }
+}
+
+enum IndexWriter {
+    Csv(BufWriter<File>),
+    Jsonl(BufWriter<File>),
+    Sqlite {
+        conn: SqliteConnection,
+        insert: String,
+        pending: u64,
+    },
+}
+
+impl IndexWriter {
+    async fn create(format: IndexExportFormat, path: &Path) -> Result<Self, MixedError> {
+        match format {
+            IndexExportFormat::Csv => {
+                let mut file = BufWriter::new(File::create(path).await?);
+                let header: Vec<&str> = COLUMNS.iter().map(|(name, _)| *name).collect();
+                file.write_all(format!("{}\n", header.join(",")).as_bytes())
+                    .await?;
+                Ok(IndexWriter::Csv(file))
+            }
+            IndexExportFormat::Jsonl => Ok(IndexWriter::Jsonl(BufWriter::new(
+                File::create(path).await?,
+            ))),
+            IndexExportFormat::Sqlite => {
+                let options = SqliteConnectOptions::new()
+                    .filename(path)
+                    .create_if_missing(true);
+                let mut conn = SqliteConnection::connect_with(&options).await?;
+
+                let columns: Vec<String> = COLUMNS
+                    .iter()
+                    .map(|(name, ty)| format!("{name} {ty}"))
+                    .collect();
+                sqlx::query(AssertSqlSafe(format!(
+                    "CREATE TABLE images ({})",
+                    columns.join(", ")
+                )))
+                .execute(&mut conn)
+                .await?;
+                sqlx::query("BEGIN").execute(&mut conn).await?;
+
+                let names: Vec<&str> = COLUMNS.iter().map(|(name, _)| *name).collect();
+                let params = vec!["?"; COLUMNS.len()].join(", ");
+                let insert = format!(
+                    "INSERT INTO images ({}) VALUES ({})",
+                    names.join(", "),
+                    params
+                );
+                Ok(IndexWriter::Sqlite {
+                    conn,
+                    insert,
+                    pending: 0,
+                })
+            }
+        }
+    }
+
+    async fn write(&mut self, values: Vec<IndexValue>) -> Result<(), MixedError> {
+        match self {
+            IndexWriter::Csv(file) => {
+                let fields: Vec<String> = values.iter().map(csv_field).collect();
+                file.write_all(format!("{}\n", fields.join(",")).as_bytes())
+                    .await?;
+            }
+            IndexWriter::Jsonl(file) => {
+                let mut object = serde_json::Map::with_capacity(COLUMNS.len());
+                for ((name, _), value) in COLUMNS.iter().zip(values) {
+                    object.insert(name.to_string(), json_value(value));
+                }
+                let mut line =
+                    serde_json::to_vec(&object).map_err(|e| MixedError::Other(e.to_string()))?;
+                line.push(b'\n');
+                file.write_all(&line).await?;
+            }
+            IndexWriter::Sqlite {
+                conn,
+                insert,
+                pending,
+            } => {
+                let mut query = sqlx::query(AssertSqlSafe(insert.clone()));
+                for value in values {
+                    query = match value {
+                        IndexValue::Null => query.bind(None::<i64>),
+                        IndexValue::Int(v) => query.bind(v),
+                        IndexValue::Real(v) => query.bind(v),
+                        IndexValue::Bool(v) => query.bind(v),
+                        IndexValue::Text(v) | IndexValue::Json(v) => query.bind(v),
+                    };
+                }
+                query.execute(&mut *conn).await?;
+
+                *pending += 1;
+                if *pending >= SQLITE_BATCH_SIZE {
+                    sqlx::query("COMMIT").execute(&mut *conn).await?;
+                    sqlx::query("BEGIN").execute(&mut *conn).await?;
+                    *pending = 0;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn finish(self) -> Result<(), MixedError> {
+        match self {
+            IndexWriter::Csv(mut file) | IndexWriter::Jsonl(mut file) => file.flush().await?,
+            IndexWriter::Sqlite { mut conn, .. } => {
+                sqlx::query("COMMIT").execute(&mut conn).await?;
+                conn.close().await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ProjectsDb {
+    /// Writes one row per image matching `opts` to `path`, joined with model, lora and control
+    /// names. Rows are streamed from the db, so this works for very large libraries.
+    /// `on_progress` is called periodically with (written, total). Returns the row count.
+    pub async fn export_index(
+        &self,
+        opts: &ListImagesOptions,
+        format: IndexExportFormat,
+        path: &Path,
+        on_progress: impl Fn(u64, u64),
+    ) -> Result<u64, MixedError> {
+        let mut writer = IndexWriter::create(format, path).await?;
+
+        let Some(query) = filtered_images_query(opts) else {
+            writer.finish().await?;
+            return Ok(0);
+        };
+
+        let total = query.clone().count(&self.db).await?;
+
+        let query = query
+            .select_only()
+            .columns([
+                images::Column::Id,
+                images::Column::ProjectId,
+                images::Column::NodeId,
+                images::Column::PreviewId,
+                images::Column::ClipId,
+                images::Column::NumFrames,
+                images::Column::WallClock,
+                images::Column::RefinerStart,
+                images::Column::UpscalerScaleFactor,
+                images::Column::Prompt,
+                images::Column::NegativePrompt,
+                images::Column::StartWidth,
+                images::Column::StartHeight,
+                images::Column::Seed,
+                images::Column::Strength,
+                images::Column::Steps,
+                images::Column::GuidanceScale,
+                images::Column::Shift,
+                images::Column::Sampler,
+                images::Column::HiresFix,
+                images::Column::TiledDecoding,
+                images::Column::TiledDiffusion,
+                images::Column::TeaCache,
+                images::Column::CfgZeroStar,
+                images::Column::HasMask,
+                images::Column::HasDepth,
+                images::Column::HasPose,
+                images::Column::HasColor,
+                images::Column::HasCustom,
+                images::Column::HasScribble,
+                images::Column::HasShuffle,
+            ])
+            .column_as(projects::Column::Path, "project_path")
+            .column_as(projects::Column::WatchfolderId, "watchfolder_id")
+            .column_as(models::Column::Filename, "model_file")
+            .column_as(models::Column::Name, "model_name")
+            .column_as(
+                Expr::cust("(SELECT filename FROM models m WHERE m.id = images.refiner_id)"),
+                "refiner_file",
+            )
+            .column_as(
+                Expr::cust("(SELECT filename FROM models m WHERE m.id = images.upscaler_id)"),
+                "upscaler_file",
+            )
+            .column_as(
+                Expr::cust(
+                    "(SELECT json_group_array(json_object('file', m.filename, 'name', m.name, 'weight', il.weight)) \
+                     FROM image_loras il JOIN models m ON m.id = il.lora_id WHERE il.image_id = images.id)",
+                ),
+                "loras",
+            )
+            .column_as(
+                Expr::cust(
+                    "(SELECT json_group_array(json_object('file', m.filename, 'name', m.name, 'weight', ic.weight)) \
+                     FROM image_controls ic JOIN models m ON m.id = ic.control_id WHERE ic.image_id = images.id)",
+                ),
+                "controls",
+            )
+            .order_by_asc(images::Column::Id)
+            .into_model::<IndexQueryRow>();
+
+        let mut stream = query.stream(&self.db).await?;
+        let mut written: u64 = 0;
+        while let Some(row) = stream.next().await {
+            writer.write(row?.values()).await?;
+            written += 1;
+            if written % 1000 == 0 {
+                on_progress(written, total);
+            }
+        }
+        drop(stream);
+
+        writer.finish().await?;
+        on_progress(written, total);
+
+        Ok(written)
+    }
+}
+
+fn csv_field(value: &IndexValue) -> String {
+    match value {
+        IndexValue::Null => String::new(),
+        IndexValue::Int(v) => v.to_string(),
+        IndexValue::Real(v) => v.to_string(),
+        IndexValue::Bool(v) => v.to_string(),
+        IndexValue::Text(v) | IndexValue::Json(v) => {
+            if v.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", v.replace('"', "\"\""))
+            } else {
+                v.clone()
+            }
+        }
+    }
+}
+
+fn json_value(value: IndexValue) -> serde_json::Value {
+    match value {
+        IndexValue::Null => serde_json::Value::Null,
+        IndexValue::Int(v) => v.into(),
+        IndexValue::Real(v) => v.into(),
+        IndexValue::Bool(v) => v.into(),
+        IndexValue::Text(v) => v.into(),
+        IndexValue::Json(v) => serde_json::from_str(&v).unwrap_or(serde_json::Value::String(v)),
+    }
+}
@@ -0,0 +1,380 @@
+use std::collections::{HashMap, HashSet};
+use std::io::Cursor;
+use std::path::Path;
+
+use crate::projects_db::{
+    dtos::{
+        image_metadata::{GenerationParams, LoraWeight, MetadataSource},
+        project::ProjectExtra,
+    },
+    image_metadata::{is_image_file, read_image_metadata},
+    metadata::{DrawThingsMetadata, V2},
+    search::process_prompt,
+};
+use entity::{enums::ModelType, image_files, images};
+use sea_orm::{
+    ColumnTrait, ConnectionTrait, EntityTrait, PaginatorTrait, QueryFilter, Set, TransactionTrait,
+};
+use walkdir::WalkDir;
+
+use super::{import::NodeModelWeight, models::ModelTypeAndFile, MixedError, ProjectsDb};
+
+/// An image file found in a loose images folder
+#[derive(Debug, Clone)]
+pub struct ImageFileEntry {
+    /// relative to the folder
+    pub path: String,
+    pub filesize: i64,
+    pub modified: i64,
+}
+
+/// Lists the image files in a loose images folder. This walks the folder, so call it from
+/// a blocking task.
+pub fn list_image_files(folder: &Path) -> Vec<ImageFileEntry> {
+    WalkDir::new(folder)
+        .follow_links(false)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file() && is_image_file(entry.path()))
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            let modified = metadata.modified().ok()?;
+            Some(ImageFileEntry {
+                path: entry
+                    .path()
+                    .strip_prefix(folder)
+                    .ok()?
+                    .to_string_lossy()
+                    .to_string(),
+                filesize: metadata.len() as i64,
+                modified: chrono::DateTime::<chrono::Utc>::from(modified).timestamp(),
+            })
+        })
+        .collect()
+}
+
+impl ProjectsDb {
+    /// Indexes the image files of a loose images folder, which is a single project. Only
+    /// files that are new or whose size or modified time changed are read.
+    pub async fn scan_image_folder(
+        &self,
+        project: &ProjectExtra,
+    ) -> Result<(i64, u64), MixedError> {
+        let folder = project.full_path.clone();
+        let files = tokio::task::spawn_blocking(move || list_image_files(Path::new(&folder)))
+            .await
+            .map_err(|e| MixedError::Other(e.to_string()))?;
+
+        let mut indexed: HashMap<String, image_files::Model> = image_files::Entity::find()
+            .filter(image_files::Column::ProjectId.eq(project.id))
+            .all(&self.db)
+            .await?
+            .into_iter()
+            .map(|file| (file.path.clone(), file))
+            .collect();
+
+        for file in files {
+            let previous = indexed.remove(&file.path);
+            if let Some(previous) = &previous {
+                if previous.filesize == file.filesize && previous.modified == file.modified {
+                    continue;
+                }
+            }
+            // one unreadable image shouldn't stop the rest of the folder from being indexed
+            if let Err(e) = self.scan_image_file(project, &file, previous).await {
+                log::warn!("Failed to index {}: {}", file.path, e);
+            }
+        }
+
+        // whatever is left is no longer in the folder
+        for file in indexed.into_values() {
+            self.remove_image_file(project.id, file).await?;
+        }
+
+        let total = images::Entity::find()
+            .filter(images::Column::ProjectId.eq(project.id))
+            .count(&self.db)
+            .await?;
+
+        Ok((project.id, total))
+    }
+
+    /// Indexes an image file, replacing the image of its previous version. The file gets a new
+    /// id, which is also its image's node and preview id, so cached thumbnails of the previous
+    /// version aren't reused. Images from other tools are indexed with the generation
+    /// parameters that could be read.
+    async fn scan_image_file(
+        &self,
+        project: &ProjectExtra,
+        file: &ImageFileEntry,
+        previous: Option<image_files::Model>,
+    ) -> Result<(), MixedError> {
+        let full_path = Path::new(&project.full_path).join(&file.path);
+        let bytes = tokio::fs::read(&full_path).await?;
+        let (metadata, generation) = match read_image_metadata(&bytes) {
+            Ok(image_metadata) => (image_metadata.metadata, image_metadata.generation),
+            Err(_) => (None, None),
+        };
+
+        let wall_clock: chrono::DateTime<chrono::Utc> =
+            tokio::fs::metadata(&full_path).await?.modified()?.into();
+
+        let generated_size = generation
+            .as_ref()
+            .and_then(|g| Some((g.width?, g.height?)));
+        let (width, height) = match generated_size {
+            Some(size) => size,
+            None => image::ImageReader::new(Cursor::new(&bytes))
+                .with_guessed_format()?
+                .into_dimensions()
+                .map_err(|e| MixedError::Other(e.to_string()))?,
+        };
+
+        let models_lookup = match (&metadata, &generation) {
+            (Some(m), _) => self.upsert_models(get_all_models_from_metadata(m)).await?,
+            (None, Some(g)) => {
+                self.upsert_models(get_all_models_from_generation(g))
+                    .await?
+            }
+            (None, None) => HashMap::new(),
+        };
+
+        // refiner, upscaler, controls, strength and shift are only known for Draw Things
+        // images, and are left empty for others
+        let default_v2 = V2::default();
+        let v2 = metadata.as_ref().map_or(&default_v2, |m| &m.v2);
+        let default_generation = GenerationParams::new(MetadataSource::DrawThings);
+        let generation = generation.as_ref().unwrap_or(&default_generation);
+        let prompt = generation.prompt.as_deref().unwrap_or_default().trim();
+        let negative_prompt = generation
+            .negative_prompt
+            .as_deref()
+            .unwrap_or_default()
+            .trim();
+        let model_id = |file: &str, model_type: ModelType| {
+            models_lookup.get(&(file.to_string(), model_type)).copied()
+        };
+
+        let txn = self.db.begin().await?;
+
+        if let Some(previous) = previous {
+            delete_image_file(&txn, previous).await?;
+        }
+
+        let image_file = image_files::Entity::insert(image_files::ActiveModel {
+            project_id: Set(project.id),
+            path: Set(file.path.clone()),
+            filesize: Set(file.filesize),
+            modified: Set(file.modified),
+            ..Default::default()
+        })
+        .exec_with_returning(&txn)
+        .await?;
+
+        let image = images::ActiveModel {
+            project_id: Set(project.id),
+            node_id: Set(image_file.id),
+            preview_id: Set(image_file.id),
+            thumbnail_half: Set(None),
+            clip_id: Set(-1),
+            num_frames: Set(None),
+            prompt: Set(prompt.to_string()),
+            negative_prompt: Set(negative_prompt.to_string()),
+            prompt_search: Set(process_prompt(prompt)),
+            negative_prompt_search: Set(process_prompt(negative_prompt)),
+            model_id: Set(generation
+                .model
+                .as_deref()
+                .and_then(|f| model_id(f, ModelType::Model))),
+            refiner_id: Set(v2
+                .refiner_model
+                .as_deref()
+                .and_then(|f| model_id(f, ModelType::Model))),
+            refiner_start: Set(metadata.as_ref().map(|m| m.v2.refiner_start)),
+            upscaler_id: Set(v2
+                .upscaler
+                .as_deref()
+                .and_then(|f| model_id(f, ModelType::Upscaler))),
+            upscaler_scale_factor: Set(v2.upscaler.as_ref().map(|_| {
+                if v2.upscaler_scale_factor == 2 {
+                    2
+                } else {
+                    4
+                }
+            })),
+            start_width: Set((width / 64) as i16),
+            start_height: Set((height / 64) as i16),
+            seed: Set(generation.seed.unwrap_or_default() as i64),
+            strength: Set(metadata.as_ref().map(|m| m.v2.strength)),
+            steps: Set(generation.steps.unwrap_or_default() as i16),
+            guidance_scale: Set(generation.cfg_scale.unwrap_or_default()),
+            shift: Set(metadata.as_ref().map(|m| m.v2.shift)),
+            hires_fix: Set(v2.hires_fix),
+            tiled_decoding: Set(v2.tiled_decoding),
+            tiled_diffusion: Set(v2.tiled_diffusion),
+            tea_cache: Set(v2.tea_cache),
+            cfg_zero_star: Set(v2.cfg_zero_star),
+            wall_clock: Set(wall_clock.into()),
+            has_mask: Set(false),
+            has_depth: Set(false),
+            has_pose: Set(false),
+            has_color: Set(false),
+            has_custom: Set(false),
+            has_scribble: Set(false),
+            has_shuffle: Set(false),
+            sampler: Set(metadata.as_ref().map_or(-1, |m| m.v2.sampler)),
+            ..Default::default()
+        };
+
+        let inserted = images::Entity::insert(image)
+            .exec_with_returning(&txn)
+            .await?;
+
+        self.insert_related_data(
+            &txn,
+            &HashMap::from([(image_file.id, inserted.id)]),
+            lora_weights(image_file.id, &generation.loras, &models_lookup),
+            model_weights(image_file.id, &v2.controls, ModelType::Cnet, &models_lookup),
+        )
+        .await?;
+
+        Self::insert_image_fts(&txn, &[inserted]).await?;
+
+        txn.commit().await?;
+
+        Ok(())
+    }
+
+    /// Removes a file that is no longer in its loose images folder, along with its image
+    async fn remove_image_file(
+        &self,
+        project_id: i64,
+        file: image_files::Model,
+    ) -> Result<(), MixedError> {
+        log::debug!("Removing {} from project {}", file.path, project_id);
+        let txn = self.db.begin().await?;
+        delete_image_file(&txn, file).await?;
+        txn.commit().await?;
+        Ok(())
+    }
+
+    /// Full path of a loose image file, from its id, which is also its image's preview id
+    pub async fn get_image_file_path(
+        &self,
+        project_id: i64,
+        image_file_id: i64,
+    ) -> Result<String, MixedError> {
+        let file = image_files::Entity::find_by_id(image_file_id)
+            .filter(image_files::Column::ProjectId.eq(project_id))
+            .one(&self.db)
+            .await?
+            .ok_or_else(|| MixedError::Other(format!("Image file {image_file_id} not found")))?;
+        let folder = self.get_project_path(project_id).await?;
+
+        Ok(Path::new(&folder)
+            .join(&file.path)
+            .to_string_lossy()
+            .to_string())
+    }
+}
+
+/// Deletes an image file row and its image, whose loras, controls and FTS row go with it
+async fn delete_image_file(
+    db: &impl ConnectionTrait,
+    file: image_files::Model,
+) -> Result<(), MixedError> {
+    let removed = images::Entity::find()
+        .filter(images::Column::ProjectId.eq(file.project_id))
+        .filter(images::Column::NodeId.eq(file.id))
+        .all(db)
+        .await?;
+
+    ProjectsDb::delete_image_fts(db, &removed).await?;
+
+    images::Entity::delete_many()
+        .filter(images::Column::ProjectId.eq(file.project_id))
+        .filter(images::Column::NodeId.eq(file.id))
+        .exec(db)
+        .await?;
+
+    image_files::Entity::delete_by_id(file.id).exec(db).await?;
+
+    Ok(())
+}
+
+fn get_all_models_from_metadata(metadata: &DrawThingsMetadata) -> HashSet<ModelTypeAndFile> {
+    let v2 = &metadata.v2;
+    let mut models = HashSet::new();
+
+    models.insert((v2.model.clone(), ModelType::Model));
+    if let Some(refiner) = &v2.refiner_model {
+        models.insert((refiner.clone(), ModelType::Model));
+    }
+    if let Some(upscaler) = &v2.upscaler {
+        models.insert((upscaler.clone(), ModelType::Upscaler));
+    }
+    for (entries, model_type) in [
+        (&v2.loras, ModelType::Lora),
+        (&v2.controls, ModelType::Cnet),
+    ] {
+        for file in entries.iter().filter_map(|e| e["file"].as_str()) {
+            models.insert((file.to_string(), model_type));
+        }
+    }
+
+    models.retain(|(file, _)| !file.is_empty());
+    models
+}
+
+fn get_all_models_from_generation(generation: &GenerationParams) -> HashSet<ModelTypeAndFile> {
+    let mut models: HashSet<ModelTypeAndFile> = generation
+        .loras
+        .iter()
+        .map(|lora| (lora.name.clone(), ModelType::Lora))
+        .collect();
+    if let Some(model) = &generation.model {
+        models.insert((model.clone(), ModelType::Model));
+    }
+
+    models.retain(|(file, _)| !file.is_empty());
+    models
+}
+
+fn lora_weights(
+    node_id: i64,
+    loras: &[LoraWeight],
+    models_lookup: &HashMap<ModelTypeAndFile, i64>,
+) -> Vec<NodeModelWeight> {
+    loras
+        .iter()
+        .filter_map(|lora| {
+            let model_id = models_lookup.get(&(lora.name.clone(), ModelType::Lora))?;
+            Some(NodeModelWeight {
+                node_id,
+                model_id: *model_id,
+                weight: lora.weight,
+            })
+        })
+        .collect()
+}
+
+/// Loras and controls are stored in the metadata as `{ "file": ..., "weight": ... }` objects
+fn model_weights(
+    node_id: i64,
+    entries: &[serde_json::Value],
+    model_type: ModelType,
+    models_lookup: &HashMap<ModelTypeAndFile, i64>,
+) -> Vec<NodeModelWeight> {
+    entries
+        .iter()
+        .filter_map(|entry| {
+            let file = entry["file"].as_str()?;
+            let model_id = models_lookup.get(&(file.to_string(), model_type))?;
+            Some(NodeModelWeight {
+                node_id,
+                model_id: *model_id,
+                weight: entry["weight"].as_f64().unwrap_or(1.0) as f32,
+            })
+        })
+        .collect()
+}
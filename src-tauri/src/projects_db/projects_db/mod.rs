@@ -2,29 +2,52 @@ use anyhow::{anyhow, Result};
 use migration::{Migrator, MigratorTrait};
 use once_cell::sync::Lazy;
 use sea_orm::{Database, DatabaseConnection};
+use std::path::PathBuf;
 use tokio::sync::RwLock;
 
 mod images;
 mod import;
+mod index_export;
+mod loose_images;
 mod mixed_error;
 mod models;
+mod portable;
 mod projects;
+mod stats;
 mod watchfolders;
+pub use loose_images::{list_image_files, ImageFileEntry};
 pub use mixed_error::MixedError;
+pub use portable::{PORTABLE_BOOKMARK_PREFIX, PORTABLE_LIBRARY_FILE_NAME};
 
 static PROJECTS_DB: Lazy<RwLock<Option<ProjectsDb>>> = Lazy::new(|| RwLock::new(None));
 
 #[derive(Clone, Debug)]
 pub struct ProjectsDb {
     pub db: DatabaseConnection,
+    /// Set when this is a portable library. Watch folder paths are stored relative to it.
+    pub root: Option<PathBuf>,
 }
 
 impl ProjectsDb {
     pub async fn new(db_path: &str) -> Result<Self> {
+        Self::connect(db_path, None).await
+    }
+
+    /// Opens (or creates) a portable library stored in `library_dir`. The library file lives
+    /// alongside the projects it indexes, and all watch folder paths are stored relative to
+    /// `library_dir`, so the same drive can be attached to any DTM install.
+    pub async fn new_portable(library_dir: &str) -> Result<Self> {
+        let root: PathBuf = PathBuf::from(library_dir).components().collect();
+        let db_file = root.join(PORTABLE_LIBRARY_FILE_NAME);
+        let db_path = format!("sqlite://{}?mode=rwc", db_file.to_string_lossy());
+        Self::connect(&db_path, Some(root)).await
+    }
+
+    async fn connect(db_path: &str, root: Option<PathBuf>) -> Result<Self> {
         let db = Database::connect(db_path).await?;
         Migrator::up(&db, None).await?;
 
-        let projects_db = Self { db: db };
+        let projects_db = Self { db: db, root };
 
         let mut singleton = PROJECTS_DB.write().await;
         *singleton = Some(projects_db.clone());
@@ -29,18 +29,31 @@ impl ProjectsDb {
         &self,
         histories: &[TensorHistoryNode],
     ) -> Result<HashMap<ModelTypeAndFile, i64>, MixedError> {
-        let models: Vec<models::ActiveModel> = HashSet::<ModelTypeAndFile>::from_iter(
+        self.upsert_models(HashSet::<ModelTypeAndFile>::from_iter(
             histories
                 .iter()
                 .flat_map(get_all_models_from_tensor_history),
-        )
-        .iter()
-        .map(|m| models::ActiveModel {
-            filename: Set(m.0.clone()),
-            model_type: Set(m.1),
-            ..Default::default()
-        })
-        .collect();
+        ))
+        .await
+    }
+
+    /// Inserts any models not already in the db, returning the ids of all of them
+    pub async fn upsert_models(
+        &self,
+        models: HashSet<ModelTypeAndFile>,
+    ) -> Result<HashMap<ModelTypeAndFile, i64>, MixedError> {
+        if models.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let models: Vec<models::ActiveModel> = models
+            .iter()
+            .map(|m| models::ActiveModel {
+                filename: Set(m.0.clone()),
+                model_type: Set(m.1),
+                ..Default::default()
+            })
+            .collect();
 
         let models = models::Entity::insert_many(models)
             .on_conflict(
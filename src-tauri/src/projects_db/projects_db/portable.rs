@@ -0,0 +1,85 @@
+use std::path::{Component, Path, PathBuf};
+
+use crate::{
+    bookmarks::ResolveResult,
+    projects_db::{dtos::watch_folder::WatchFolderDTO, folder_cache},
+};
+
+use super::{MixedError, ProjectsDb};
+
+pub const PORTABLE_LIBRARY_FILE_NAME: &str = "dtm-library.db";
+/// Watch folders in a portable library don't have platform bookmarks, the relative path is
+/// stored instead so the bookmark column stays unique.
+pub const PORTABLE_BOOKMARK_PREFIX: &str = "PORTABLE::";
+
+impl ProjectsDb {
+    pub fn is_portable(&self) -> bool {
+        self.root.is_some()
+    }
+
+    /// Path of the sqlite file backing a portable library
+    pub fn library_file(&self) -> Option<PathBuf> {
+        self.root
+            .as_ref()
+            .map(|root| root.join(PORTABLE_LIBRARY_FILE_NAME))
+    }
+
+    /// Converts an absolute path to the form stored in the db. Unchanged when not portable.
+    pub fn to_library_path(&self, path: &str) -> Result<String, MixedError> {
+        let Some(root) = &self.root else {
+            return Ok(path.to_string());
+        };
+
+        let normalized: PathBuf = Path::new(path).components().collect();
+        let relative = normalized.strip_prefix(root).map_err(|_| {
+            MixedError::Other(format!(
+                "{path} is outside of the library folder {}",
+                root.display()
+            ))
+        })?;
+
+        Ok(relative
+            .components()
+            .filter_map(|c| match c {
+                Component::Normal(part) => Some(part.to_string_lossy().to_string()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("/"))
+    }
+
+    /// Converts a path stored in the db to an absolute path. Unchanged when not portable.
+    pub fn from_library_path(&self, stored: &str) -> String {
+        match &self.root {
+            Some(root) if stored.is_empty() => root.to_string_lossy().to_string(),
+            Some(root) => root.join(stored).to_string_lossy().to_string(),
+            None => stored.to_string(),
+        }
+    }
+
+    pub(super) fn portable_bookmark(&self, path: &str) -> Result<String, MixedError> {
+        Ok(format!(
+            "{PORTABLE_BOOKMARK_PREFIX}{}",
+            self.to_library_path(path)?
+        ))
+    }
+
+    pub(super) fn absolutize(&self, mut folder: WatchFolderDTO) -> WatchFolderDTO {
+        folder.path = self.from_library_path(&folder.path);
+        folder
+    }
+
+    /// Resolves a watch folder's location and updates the folder cache. Portable folders are
+    /// resolved against the library root, everything else goes through the bookmark.
+    pub async fn resolve_watch_folder(
+        &self,
+        folder: &WatchFolderDTO,
+    ) -> anyhow::Result<ResolveResult> {
+        if self.is_portable() {
+            folder_cache::set_folder(folder.id, &folder.path);
+            return Ok(ResolveResult::Resolved(folder.path.clone()));
+        }
+
+        folder_cache::resolve_bookmark(folder.id, &folder.bookmark).await
+    }
+}
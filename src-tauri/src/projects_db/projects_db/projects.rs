@@ -1,6 +1,11 @@
-use crate::projects_db::{dtos::project::ProjectExtra, folder_cache, DTProject};
+use crate::projects_db::{
+    dt_project::ThnFilter,
+    dtos::project::{CompatibilityReport, ProjectExtra},
+    folder_cache, DTProject,
+};
 use dashmap::DashMap;
 use entity::{
+    enums::WatchFolderKind,
     images::{self, Entity as Images},
     projects::{self, ActiveModel, Entity as Projects},
     watch_folders,
@@ -14,7 +19,8 @@ use sea_query::{Expr, OnConflict};
 
 use super::{MixedError, ProjectsDb};
 
-static PROJECT_PATH_CACHE: Lazy<DashMap<i64, String>> = Lazy::new(DashMap::new);
+/// Full path and kind of each project, which don't change while the project is indexed
+static PROJECT_PATH_CACHE: Lazy<DashMap<i64, (String, WatchFolderKind)>> = Lazy::new(DashMap::new);
 
 impl ProjectsDb {
     pub async fn add_project(
@@ -22,15 +28,27 @@ impl ProjectsDb {
         watch_folder_id: i64,
         relative_path: &str,
     ) -> anyhow::Result<ProjectExtra> {
+        let watch_folder = watch_folders::Entity::find_by_id(watch_folder_id)
+            .one(&self.db)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Watch folder not found"))?;
         let watch_folder_path = folder_cache::get_folder(watch_folder_id)
             .ok_or_else(|| anyhow::anyhow!("Watch folder not found in cache"))?;
-        let full_path = std::path::Path::new(&watch_folder_path).join(relative_path);
-        let full_path_str = full_path
-            .to_str()
-            .ok_or_else(|| anyhow::anyhow!("Invalid path"))?;
 
-        let dt_project = DTProject::get(full_path_str).await?;
-        let fingerprint = dt_project.get_fingerprint().await?;
+        // the files of a loose images folder are tracked individually when it is scanned
+        let fingerprint = match watch_folder.kind {
+            WatchFolderKind::LooseImages => String::new(),
+            WatchFolderKind::Projects => {
+                let full_path = std::path::Path::new(&watch_folder_path).join(relative_path);
+                let full_path_str = full_path
+                    .to_str()
+                    .ok_or_else(|| anyhow::anyhow!("Invalid path"))?;
+                DTProject::get(full_path_str)
+                    .await?
+                    .get_fingerprint()
+                    .await?
+            }
+        };
 
         let project = ActiveModel {
             path: Set(relative_path.to_string()),
@@ -76,14 +94,28 @@ impl ProjectsDb {
         Ok(project)
     }
 
+    /// Project ids are only meaningful within a single library
+    pub fn clear_project_path_cache(&self) {
+        PROJECT_PATH_CACHE.clear();
+    }
+
     pub async fn get_project_path(&self, id: i64) -> Result<String, MixedError> {
-        if let Some(path) = PROJECT_PATH_CACHE.get(&id) {
-            return Ok(path.clone());
+        Ok(self.get_project_location(id).await?.0)
+    }
+
+    /// The project's full path and the kind of its watch folder
+    pub async fn get_project_location(
+        &self,
+        id: i64,
+    ) -> Result<(String, WatchFolderKind), MixedError> {
+        if let Some(location) = PROJECT_PATH_CACHE.get(&id) {
+            return Ok(location.clone());
         }
 
         let project = self.get_project(id).await?;
-        PROJECT_PATH_CACHE.insert(id, project.full_path.clone());
-        Ok(project.full_path)
+        let location = (project.full_path, project.kind);
+        PROJECT_PATH_CACHE.insert(id, location.clone());
+        Ok(location)
     }
 
     pub async fn get_project_by_path(
@@ -150,6 +182,77 @@ impl ProjectsDb {
         Ok(updated)
     }
 
+    pub async fn update_project_compatibility(
+        &self,
+        project_id: i64,
+        report: &CompatibilityReport,
+    ) -> Result<(), MixedError> {
+        let report = serde_json::to_value(report).map_err(|e| e.to_string())?;
+        let project = projects::ActiveModel {
+            id: Set(project_id),
+            compatibility: Set(Some(report)),
+            ..Default::default()
+        };
+        project.update(&self.db).await?;
+
+        Ok(())
+    }
+
+    /// Points an existing project at a new file, keeping its id and indexed images.
+    /// Used when a project file has been renamed or moved between watch folders.
+    pub async fn move_project(
+        &self,
+        project_id: i64,
+        watchfolder_id: i64,
+        relative_path: &str,
+    ) -> Result<ProjectExtra, MixedError> {
+        let project = projects::ActiveModel {
+            id: Set(project_id),
+            watchfolder_id: Set(watchfolder_id),
+            path: Set(relative_path.to_string()),
+            ..Default::default()
+        };
+        project.update(&self.db).await?;
+        PROJECT_PATH_CACHE.remove(&project_id);
+
+        self.get_project(project_id).await
+    }
+
+    /// Checks that `dt_project`, with `fingerprint`, holds the same content as an indexed
+    /// project. The fingerprint only covers the first few thumbnails, so this also compares the
+    /// most recently indexed image against the matching history node in the file.
+    pub async fn is_same_project(
+        &self,
+        project: &ProjectExtra,
+        dt_project: &DTProject,
+        fingerprint: &str,
+    ) -> anyhow::Result<bool> {
+        if project.fingerprint.is_empty() || fingerprint != project.fingerprint {
+            return Ok(false);
+        }
+
+        let Some(last_id) = project.last_id else {
+            return Ok(true);
+        };
+
+        let image = Images::find()
+            .filter(images::Column::ProjectId.eq(project.id))
+            .filter(images::Column::NodeId.eq(last_id))
+            .one(&self.db)
+            .await?;
+        let Some(image) = image else {
+            return Ok(true);
+        };
+
+        let nodes = dt_project
+            .get_tensor_history_nodes(Some(ThnFilter::Rowid(last_id)), None)
+            .await?;
+        Ok(nodes.first().map_or(false, |node| {
+            let data = node.data();
+            data.seed() as i64 == image.seed && data.preview_id() == image.preview_id
+        }))
+    }
+
     pub async fn update_exclude(&self, project_id: i64, exclude: bool) -> Result<(), MixedError> {
         let project = Projects::find_by_id(project_id)
             .one(&self.db)
@@ -231,5 +334,9 @@ fn project_query() -> sea_orm::Select<entity::prelude::Projects> {
             Expr::col((watch_folders::Entity, watch_folders::Column::IsLocked)),
             "is_locked",
         )
+        .column_as(
+            Expr::col((watch_folders::Entity, watch_folders::Column::Kind)),
+            "kind",
+        )
         .group_by(projects::Column::Id)
 }
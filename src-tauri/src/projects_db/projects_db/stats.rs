@@ -0,0 +1,287 @@
+use std::collections::HashMap;
+
+use crate::projects_db::dtos::{
+    image::ListImagesOptions,
+    stats::{
+        HourCount, LibraryStats, ModelUsage, PeriodCount, PeriodUsage, ResolutionUsage,
+        SamplerUsage, StatsSummary,
+    },
+};
+use entity::{image_controls, image_loras, images, models};
+use sea_orm::{
+    ColumnTrait, EntityTrait, ExprTrait, JoinType, QueryFilter, QueryOrder, QuerySelect,
+    RelationTrait, Select,
+};
+use sea_query::Expr;
+
+use super::{images::filtered_images_query, MixedError, ProjectsDb};
+
+const TOP_COUNT: u64 = 20;
+
+impl ProjectsDb {
+    /// Aggregate stats for the images matching `opts`. Only the filtering options are used,
+    /// so the stats follow whatever selection is being listed. Dates and hours are grouped in
+    /// local time using `utc_offset_minutes`.
+    pub async fn library_stats(
+        &self,
+        opts: ListImagesOptions,
+        utc_offset_minutes: Option<i32>,
+    ) -> Result<LibraryStats, MixedError> {
+        let Some(base) = filtered_images_query(&opts) else {
+            return Ok(LibraryStats::default());
+        };
+
+        let local_time = format!(
+            "datetime(images.wall_clock, '{} minutes')",
+            utc_offset_minutes.unwrap_or(0)
+        );
+        let day = format!("date({local_time})");
+        let week = iso_week(&local_time);
+        let hour = format!("CAST(strftime('%H', {local_time}) AS INTEGER)");
+        // sizes are stored in 64px blocks
+        let resolution = "(images.start_width * 64) || 'x' || (images.start_height * 64)";
+
+        let summary = base
+            .clone()
+            .select_only()
+            .column_as(images::Column::Id.count(), "total")
+            .column_as(images::Column::NumFrames.count(), "video_count")
+            .column_as(Expr::cust("AVG(images.steps)"), "avg_steps")
+            .column_as(Expr::cust("AVG(images.guidance_scale)"), "avg_guidance")
+            .column_as(images::Column::WallClock.min(), "first_wall_clock")
+            .column_as(images::Column::WallClock.max(), "last_wall_clock")
+            .into_model::<StatsSummary>()
+            .one(&self.db)
+            .await?
+            .ok_or_else(|| MixedError::Other("Failed to get stats".to_string()))?;
+
+        let per_day = base
+            .clone()
+            .select_only()
+            .column_as(Expr::cust(&day), "period")
+            .column_as(images::Column::Id.count(), "count")
+            .group_by(Expr::cust(&day))
+            .order_by_asc(Expr::cust("period"))
+            .into_model::<PeriodCount>()
+            .all(&self.db)
+            .await?;
+
+        let per_week = base
+            .clone()
+            .select_only()
+            .column_as(Expr::cust(&week), "period")
+            .column_as(images::Column::Id.count(), "count")
+            .group_by(Expr::cust(&week))
+            .order_by_asc(Expr::cust("period"))
+            .into_model::<PeriodCount>()
+            .all(&self.db)
+            .await?;
+
+        let hours = base
+            .clone()
+            .select_only()
+            .column_as(Expr::cust(&hour), "hour")
+            .column_as(images::Column::Id.count(), "count")
+            .group_by(Expr::cust(&hour))
+            .order_by_asc(Expr::cust("hour"))
+            .into_model::<HourCount>()
+            .all(&self.db)
+            .await?;
+
+        let model_counts: Vec<(i64, i64)> = top_counts(
+            base.clone()
+                .filter(images::Column::ModelId.is_not_null())
+                .select_only()
+                .column(images::Column::ModelId)
+                .column_as(images::Column::Id.count(), "count")
+                .group_by(images::Column::ModelId),
+        )
+        .into_tuple()
+        .all(&self.db)
+        .await?;
+
+        let lora_counts: Vec<(i64, i64)> = top_counts(
+            base.clone()
+                .join(JoinType::InnerJoin, images::Relation::ImageLoras.def())
+                .select_only()
+                .column(image_loras::Column::LoraId)
+                .column_as(images::Column::Id.count(), "count")
+                .group_by(image_loras::Column::LoraId),
+        )
+        .into_tuple()
+        .all(&self.db)
+        .await?;
+
+        let control_counts: Vec<(i64, i64)> = top_counts(
+            base.clone()
+                .join(JoinType::InnerJoin, images::Relation::ImageControls.def())
+                .select_only()
+                .column(image_controls::Column::ControlId)
+                .column_as(images::Column::Id.count(), "count")
+                .group_by(image_controls::Column::ControlId),
+        )
+        .into_tuple()
+        .all(&self.db)
+        .await?;
+
+        let samplers = top_counts(
+            base.clone()
+                .select_only()
+                .column(images::Column::Sampler)
+                .column_as(images::Column::Id.count(), "count")
+                .group_by(images::Column::Sampler),
+        )
+        .into_model::<SamplerUsage>()
+        .all(&self.db)
+        .await?;
+
+        let resolutions = top_counts(
+            base.clone()
+                .select_only()
+                .column_as(Expr::cust("images.start_width * 64"), "width")
+                .column_as(Expr::cust("images.start_height * 64"), "height")
+                .column_as(images::Column::Id.count(), "count")
+                .group_by(images::Column::StartWidth)
+                .group_by(images::Column::StartHeight),
+        )
+        .into_model::<ResolutionUsage>()
+        .all(&self.db)
+        .await?;
+
+        let models_by_week = usage_by_period(
+            base.clone().filter(images::Column::ModelId.is_not_null()),
+            &week,
+            "images.model_id",
+        )
+        .all(&self.db)
+        .await?;
+
+        let loras_by_week = usage_by_period(
+            base.clone()
+                .join(JoinType::InnerJoin, images::Relation::ImageLoras.def()),
+            &week,
+            "image_loras.lora_id",
+        )
+        .all(&self.db)
+        .await?;
+
+        let samplers_by_week = usage_by_period(base.clone(), &week, "images.sampler")
+            .all(&self.db)
+            .await?;
+
+        let resolutions_by_week = usage_by_period(base, &week, resolution)
+            .all(&self.db)
+            .await?;
+
+        let video_count = summary.video_count.unwrap_or(0);
+
+        Ok(LibraryStats {
+            total: summary.total,
+            image_count: summary.total - video_count,
+            video_count,
+            avg_steps: summary.avg_steps,
+            avg_guidance: summary.avg_guidance,
+            first_wall_clock: summary.first_wall_clock,
+            last_wall_clock: summary.last_wall_clock,
+            per_day,
+            per_week,
+            hours,
+            models: self.model_usage(model_counts).await?,
+            loras: self.model_usage(lora_counts).await?,
+            controls: self.model_usage(control_counts).await?,
+            samplers,
+            resolutions,
+            models_by_week,
+            loras_by_week,
+            samplers_by_week,
+            resolutions_by_week,
+        })
+    }
+
+    /// Attaches model names to (model_id, count) rows
+    async fn model_usage(&self, counts: Vec<(i64, i64)>) -> Result<Vec<ModelUsage>, MixedError> {
+        let ids: Vec<i64> = counts.iter().map(|(id, _)| *id).collect();
+        let models: HashMap<i64, models::Model> = models::Entity::find()
+            .filter(models::Column::Id.is_in(ids))
+            .all(&self.db)
+            .await?
+            .into_iter()
+            .map(|m| (m.id, m))
+            .collect();
+
+        Ok(counts
+            .into_iter()
+            .map(|(id, count)| {
+                let model = models.get(&id);
+                ModelUsage {
+                    id,
+                    filename: model.map(|m| m.filename.clone()),
+                    name: model.and_then(|m| m.name.clone()),
+                    count,
+                }
+            })
+            .collect())
+    }
+}
+
+/// ISO 8601 week of a date expression, as `YYYY-Www`. The week belongs to the year of its
+/// Thursday, so the first days of January can fall in the last week of the previous year.
+fn iso_week(date: &str) -> String {
+    let thursday = format!("date({date}, '-3 days', 'weekday 4')");
+    format!(
+        "(strftime('%Y', {thursday}) || '-W' || \
+         printf('%02d', (strftime('%j', {thursday}) - 1) / 7 + 1))"
+    )
+}
+
+fn top_counts(query: Select<images::Entity>) -> Select<images::Entity> {
+    query.order_by_desc(Expr::cust("count")).limit(TOP_COUNT)
+}
+
+fn usage_by_period(
+    query: Select<images::Entity>,
+    period: &str,
+    key: &str,
+) -> sea_orm::Selector<sea_orm::SelectModel<PeriodUsage>> {
+    query
+        .select_only()
+        .column_as(Expr::cust(period), "period")
+        .column_as(Expr::cust(format!("CAST({key} AS TEXT)")), "key")
+        .column_as(images::Column::Id.count(), "count")
+        .group_by(Expr::cust(period))
+        .group_by(Expr::cust(key))
+        .order_by_asc(Expr::cust("period"))
+        .into_model::<PeriodUsage>()
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::{AssertSqlSafe, Connection, Row, SqliteConnection};
+
+    use super::iso_week;
+
+    #[tokio::test]
+    async fn test_iso_week() {
+        let mut conn = SqliteConnection::connect("sqlite::memory:").await.unwrap();
+        let cases = [
+            ("2019-12-30", "2020-W01"),
+            ("2020-12-31", "2020-W53"),
+            ("2021-01-01", "2020-W53"),
+            ("2021-01-03", "2020-W53"),
+            ("2021-01-04", "2021-W01"),
+            ("2023-01-01", "2022-W52"),
+            ("2023-01-02", "2023-W01"),
+            ("2024-12-30", "2025-W01"),
+            ("2025-06-15", "2025-W24"),
+        ];
+        for (date, expected) in cases {
+            let query = format!("SELECT {}", iso_week(&format!("'{date} 12:00:00'")));
+            let row = sqlx::query(AssertSqlSafe(query))
+                .fetch_one(&mut conn)
+                .await
+                .unwrap();
+            let week: String = row.get(0);
+            assert_eq!(week, expected, "{}", date);
+        }
+    }
+}
@@ -1,5 +1,5 @@
 use crate::projects_db::dtos::watch_folder::WatchFolderDTO;
-use entity::watch_folders;
+use entity::{enums::WatchFolderKind, watch_folders};
 use sea_orm::{
     sea_query::Expr, ActiveModelBehavior, ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter,
     QueryOrder, Set,
@@ -14,7 +14,10 @@ impl ProjectsDb {
             .all(&self.db)
             .await?;
 
-        Ok(folders.into_iter().map(|f| f.into()).collect())
+        Ok(folders
+            .into_iter()
+            .map(|f| self.absolutize(f.into()))
+            .collect())
     }
 
     pub async fn add_watch_folder(
@@ -22,17 +25,23 @@ impl ProjectsDb {
         path: &str,
         bookmark: &str,
         recursive: bool,
+        kind: WatchFolderKind,
     ) -> Result<WatchFolderDTO, MixedError> {
+        let bookmark = match self.is_portable() {
+            true => self.portable_bookmark(path)?,
+            false => bookmark.to_string(),
+        };
         let model = watch_folders::ActiveModel {
-            path: Set(path.to_string()),
-            bookmark: Set(bookmark.to_string()),
+            path: Set(self.to_library_path(path)?),
+            bookmark: Set(bookmark),
             recursive: Set(Some(recursive)),
+            kind: Set(kind),
             ..Default::default()
         }
         .insert(&self.db)
         .await?;
 
-        Ok(model.into())
+        Ok(self.absolutize(model.into()))
     }
 
     pub async fn something(&self) -> anyhow::Result<()> {
@@ -75,7 +84,7 @@ impl ProjectsDb {
         }
 
         let model = model.update(&self.db).await?;
-        Ok(model.into())
+        Ok(self.absolutize(model.into()))
     }
 
     pub async fn update_bookmark_path(
@@ -90,17 +99,29 @@ impl ProjectsDb {
             .ok_or_else(|| MixedError::Other(format!("Watch folder {id} not found")))?
             .into();
 
-        model.bookmark = Set(bookmark.to_string());
-        model.path = Set(path.to_string());
+        model.bookmark = match self.is_portable() {
+            true => Set(self.portable_bookmark(path)?),
+            false => Set(bookmark.to_string()),
+        };
+        model.path = Set(self.to_library_path(path)?);
 
         let model = model.update(&self.db).await?;
-        Ok(model.into())
+        Ok(self.absolutize(model.into()))
     }
 
     pub async fn get_watch_folder_for_path(
         &self,
         path: &str,
     ) -> Result<Option<WatchFolderDTO>, MixedError> {
+        if self.is_portable() {
+            // stored paths are relative, and the root folder is stored as ""
+            let folders = self.list_watch_folders().await?;
+            return Ok(folders
+                .into_iter()
+                .filter(|f| path.starts_with(&format!("{}/", f.path)))
+                .max_by_key(|f| f.path.len()));
+        }
+
         let folder = watch_folders::Entity::find()
             .filter(Expr::cust_with_values("? LIKE path || '/%'", [path]))
             .one(&self.db)
@@ -113,11 +134,18 @@ impl ProjectsDb {
         &self,
         path: &str,
     ) -> Result<Option<WatchFolderDTO>, MixedError> {
+        let path = match self.is_portable() {
+            true => match self.to_library_path(path) {
+                Ok(p) => p,
+                Err(_) => return Ok(None),
+            },
+            false => path.to_string(),
+        };
         let folder = watch_folders::Entity::find()
             .filter(watch_folders::Column::Path.eq(path))
             .one(&self.db)
             .await?;
 
-        Ok(folder.map(|f| f.into()))
+        Ok(folder.map(|f| self.absolutize(f.into())))
     }
 }
@@ -0,0 +1,176 @@
+use anyhow::Result;
+use serde_json::json;
+
+use crate::{projects_db::dtos::export::TensorFileFormat, Tensor, TensorValue};
+
+const NPY_MAGIC: &[u8] = b"\x93NUMPY";
+/// The npy preamble and header are padded to a multiple of this
+const NPY_ALIGN: usize = 64;
+
+/// Encodes a decompressed tensor as `format`. `name` is the Draw Things tensor name, which
+/// safetensors files store the tensor under.
+pub fn encode_tensor_file(
+    name: &str,
+    tensor: &Tensor,
+    format: TensorFileFormat,
+) -> Result<Vec<u8>> {
+    match format {
+        TensorFileFormat::Npy => encode_npy(tensor),
+        TensorFileFormat::Safetensors => encode_safetensors(name, tensor),
+    }
+}
+
+/// NHWC shape of the tensor. If the data does not fill that shape (a truncated or
+/// differently laid out tensor), the data is exported flat rather than reshaped.
+pub fn tensor_shape(tensor: &Tensor) -> Vec<usize> {
+    let shape = [tensor.n, tensor.height, tensor.width, tensor.channels].map(|d| d as usize);
+    let len = value_count(&tensor.data);
+    match shape.iter().product::<usize>() == len {
+        true => shape.to_vec(),
+        false => vec![len],
+    }
+}
+
+fn value_count(data: &TensorValue) -> usize {
+    match data {
+        TensorValue::F32(values) => values.len(),
+        TensorValue::U8(values) => values.len(),
+    }
+}
+
+/// Little endian bytes of the tensor's values
+fn value_bytes(data: &TensorValue) -> Vec<u8> {
+    match data {
+        TensorValue::F32(values) => values.iter().flat_map(|v| v.to_le_bytes()).collect(),
+        TensorValue::U8(values) => values.clone(),
+    }
+}
+
+/// NumPy `.npy` (format version 1.0)
+pub fn encode_npy(tensor: &Tensor) -> Result<Vec<u8>> {
+    let descr = match tensor.data {
+        TensorValue::F32(_) => "<f4",
+        TensorValue::U8(_) => "|u1",
+    };
+    let shape = tensor_shape(tensor);
+    let shape = match shape.as_slice() {
+        [len] => format!("({},)", len),
+        dims => format!(
+            "({})",
+            dims.iter()
+                .map(|d| d.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+    let mut header = format!(
+        "{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}",
+        descr, shape
+    );
+    // magic, version and header length come before the header, which ends with a newline
+    let preamble = NPY_MAGIC.len() + 4;
+    let padding = (NPY_ALIGN - (preamble + header.len() + 1) % NPY_ALIGN) % NPY_ALIGN;
+    header.push_str(&" ".repeat(padding));
+    header.push('\n');
+    let header_len = u16::try_from(header.len())
+        .map_err(|_| anyhow::anyhow!("npy header is too long ({} bytes)", header.len()))?;
+
+    let data = value_bytes(&tensor.data);
+    let mut out = Vec::with_capacity(preamble + header.len() + data.len());
+    out.extend_from_slice(NPY_MAGIC);
+    out.extend_from_slice(&[1, 0]);
+    out.extend_from_slice(&header_len.to_le_bytes());
+    out.extend_from_slice(header.as_bytes());
+    out.extend_from_slice(&data);
+    Ok(out)
+}
+
+/// safetensors, with the tensor stored under `name` and the name and layout in the metadata
+pub fn encode_safetensors(name: &str, tensor: &Tensor) -> Result<Vec<u8>> {
+    let dtype = match tensor.data {
+        TensorValue::F32(_) => "F32",
+        TensorValue::U8(_) => "U8",
+    };
+    let data = value_bytes(&tensor.data);
+    let header = json!({
+        "__metadata__": {
+            "name": name,
+            "layout": "NHWC",
+            "source": "drawthings",
+        },
+        name: {
+            "dtype": dtype,
+            "shape": tensor_shape(tensor),
+            "data_offsets": [0, data.len()],
+        },
+    });
+    let mut header = serde_json::to_string(&header)?;
+    // the data that follows the header is 8 byte aligned
+    header.push_str(&" ".repeat((8 - header.len() % 8) % 8));
+
+    let mut out = Vec::with_capacity(8 + header.len() + data.len());
+    out.extend_from_slice(&(header.len() as u64).to_le_bytes());
+    out.extend_from_slice(header.as_bytes());
+    out.extend_from_slice(&data);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TensorDType;
+
+    fn tensor(n: u32, height: u32, width: u32, channels: u32, data: TensorValue) -> Tensor {
+        Tensor {
+            n,
+            width,
+            height,
+            channels,
+            dtype: match data {
+                TensorValue::F32(_) => TensorDType::F32,
+                TensorValue::U8(_) => TensorDType::U8,
+            },
+            data,
+        }
+    }
+
+    #[test]
+    fn test_encode_npy() {
+        let image = tensor(1, 2, 3, 3, TensorValue::F32(vec![0.5; 18]));
+        let npy = encode_npy(&image).unwrap();
+        assert_eq!(&npy[..8], b"\x93NUMPY\x01\x00");
+        let header_len = u16::from_le_bytes([npy[8], npy[9]]) as usize;
+        assert_eq!((10 + header_len) % NPY_ALIGN, 0);
+
+        let header = std::str::from_utf8(&npy[10..10 + header_len]).unwrap();
+        assert!(
+            header.starts_with("{'descr': '<f4', 'fortran_order': False, 'shape': (1, 2, 3, 3), }")
+        );
+        assert!(header.ends_with('\n'));
+        let data = &npy[10 + header_len..];
+        assert_eq!(data.len(), 18 * 4);
+        assert_eq!(&data[..4], &0.5f32.to_le_bytes());
+
+        // data that doesn't fill the shape is written flat
+        let mask = tensor(1, 4, 4, 1, TensorValue::U8(vec![1; 10]));
+        let npy = encode_npy(&mask).unwrap();
+        let header = String::from_utf8_lossy(&npy[10..]);
+        assert!(header.starts_with("{'descr': '|u1', 'fortran_order': False, 'shape': (10,), }"));
+    }
+
+    #[test]
+    fn test_encode_safetensors() {
+        let mask = tensor(1, 2, 2, 1, TensorValue::U8(vec![0, 1, 2, 3]));
+        let file = encode_safetensors("binary_mask_7", &mask).unwrap();
+        let header_len = u64::from_le_bytes(file[..8].try_into().unwrap()) as usize;
+        assert_eq!(header_len % 8, 0);
+
+        let header: serde_json::Value = serde_json::from_slice(&file[8..8 + header_len]).unwrap();
+        assert_eq!(header["__metadata__"]["name"], "binary_mask_7");
+        let entry = &header["binary_mask_7"];
+        assert_eq!(entry["dtype"], "U8");
+        assert_eq!(entry["shape"], json!([1, 2, 2, 1]));
+        assert_eq!(entry["data_offsets"], json!([0, 4]));
+        assert_eq!(&file[8 + header_len..], &[0, 1, 2, 3]);
+    }
+}
@@ -2,34 +2,36 @@ use anyhow::Result;
 use flate2::read::DeflateDecoder;
 use fpzip_sys::*;
 
-use image::GrayImage;
+use image::{DynamicImage, GrayImage, Rgb32FImage, Rgba32FImage};
 use png::{BitDepth, ColorType, Encoder};
 
+use std::borrow::Cow;
 use std::ffi::c_void;
 use std::io::Cursor;
 use std::io::Read;
 
 use crate::projects_db::dt_project::data::tensor_history_node_data::TensorHistoryNodeData;
+use crate::projects_db::dtos::export::TensorImageFormat;
 use crate::projects_db::dtos::tensor::TensorRaw;
+use crate::projects_db::image_metadata::{EXIF_IFD_POINTER, EXIF_USER_COMMENT};
 use crate::projects_db::metadata::DrawThingsMetadata;
+use crate::resize::{resize_pixels, resize_samples, ResizeOptions};
 
 pub struct DecodeTensorOptions {
     pub as_png: bool,
     pub history_node: Option<TensorHistoryNodeData>,
-    pub size: Option<u32>,
+    pub resize: Option<ResizeOptions>,
+    /// Used when `as_png` is set. The high bit depth formats are written straight from the
+    /// decompressed f32 data
+    pub format: TensorImageFormat,
 }
 
 pub fn decode_tensor(tensor: TensorRaw, options: DecodeTensorOptions) -> Result<Vec<u8>> {
-    let DecodeTensorOptions {
-        as_png,
-        history_node,
-        size,
-    } = options;
     if tensor.name.starts_with("pose") {
         return decode_pose(tensor);
     }
     if tensor.name.starts_with("binary_mask") || tensor.name.starts_with("scribble") {
-        return scribble_mask_to_png(tensor, size);
+        return scribble_mask_to_png(tensor, options.resize.as_ref());
     }
     // log::debug!(
     //     "Decoding tensor {} ({}x{}x{})",
@@ -46,62 +48,54 @@ pub fn decode_tensor(tensor: TensorRaw, options: DecodeTensorOptions) -> Result<
     //     out.len()
     // );
 
-    let (pixels, width, height) = if let Some(target_size) = size {
-        log::debug!("Scaling to {}x{}", target_size, target_size);
-        let width = tensor.width as usize;
-        let height = tensor.height as usize;
-        let channels = tensor.channels as usize;
-        let target_size = target_size as usize;
-
-        // Calculate center crop
-        let crop_size = width.min(height);
-        let start_x = (width - crop_size) / 2;
-        let start_y = (height - crop_size) / 2;
-
-        // Calculate sampling step
-        // We want to sample `target_size` pixels from `crop_size`
-        // step = crop_size / target_size
-        let step = crop_size as f32 / target_size as f32;
-
-        let mut pixels = Vec::with_capacity(target_size * target_size * channels);
-
-        for y in 0..target_size {
-            for x in 0..target_size {
-                let src_y = start_y + (y as f32 * step) as usize;
-                let src_x = start_x + (x as f32 * step) as usize;
-
-                if src_y < height && src_x < width {
-                    let pixel_idx = (src_y * width + src_x) * channels;
-                    for c in 0..channels {
-                        let v = out[pixel_idx + c];
-                        let f = v.clamp(-1.0, 1.0);
-                        pixels.push(((f * 0.5 + 0.5) * 255.0).round() as u8);
-                    }
-                } else {
-                    // Should not happen with correct math, but safe fallback
-                    for _ in 0..channels {
-                        pixels.push(0);
-                    }
-                }
-            }
-        }
-        (pixels, target_size as u32, target_size as u32)
-    } else {
-        let mut pixels = vec![0u8; out.len()];
-        for i in 0..out.len() {
-            pixels[i] = ((out[i] * 0.5 + 0.5) * 255.0).round() as u8;
+    encode_tensor_data(
+        &out,
+        tensor.width as u32,
+        tensor.height as u32,
+        tensor.channels as usize,
+        options,
+    )
+}
+
+/// Encodes decompressed f32 image data in `[-1, 1]`, as `decode_tensor` does after decoding
+pub fn encode_tensor_data(
+    out: &[f32],
+    width: u32,
+    height: u32,
+    channels: usize,
+    options: DecodeTensorOptions,
+) -> Result<Vec<u8>> {
+    let DecodeTensorOptions {
+        as_png,
+        history_node,
+        resize,
+        format,
+    } = options;
+
+    let (out, width, height) = match &resize {
+        Some(resize) => {
+            log::debug!("Resizing to {}x{}", resize.width, resize.height);
+            // the resampling filters work in [0, 1]
+            let unit: Vec<f32> = out.iter().map(|v| v * 0.5 + 0.5).collect();
+            let (resized, width, height) =
+                resize_samples(&unit, width, height, channels, resize)?;
+            let out: Vec<f32> = resized.iter().map(|v| v * 2.0 - 1.0).collect();
+            (Cow::Owned(out), width, height)
         }
-        (pixels, tensor.width as u32, tensor.height as u32)
+        None => (Cow::Borrowed(out), width, height),
     };
 
+    if as_png && format != TensorImageFormat::Png8 {
+        return encode_high_bit_depth(&out, width, height, channels, format, history_node);
+    }
+
+    let pixels: Vec<u8> = out
+        .iter()
+        .map(|v| ((v.clamp(-1.0, 1.0) * 0.5 + 0.5) * 255.0).round() as u8)
+        .collect();
+
     match as_png {
-        true => write_png_with_usercomment(
-            &pixels,
-            width,
-            height,
-            tensor.channels as usize,
-            history_node,
-        ),
+        true => write_png_with_usercomment(&pixels, width, height, channels, history_node),
         false => Ok(pixels),
     }
 }
@@ -119,6 +113,9 @@ pub fn decode_pose(tensor: TensorRaw) -> Result<Vec<u8>> {
     }
 }
 
+/// Largest tensor `decompress_fzip` will decode, 512M elements (2GB of f32)
+pub const MAX_TENSOR_VALUES: usize = 512 * 1024 * 1024;
+
 pub fn decompress_fzip(data: &Vec<u8>) -> Result<Vec<f32>> {
     let out: Vec<f32>;
 
@@ -183,7 +180,7 @@ pub fn decompress_fzip(data: &Vec<u8>) -> Result<Vec<f32>> {
         }
 
         // Guard 3: Prevent absurdly large tensor sizes (e.g. maxing out at ~2GB of memory usage)
-        let max_values = 512 * 1024 * 1024; // 512M elements (f32)
+        let max_values = MAX_TENSOR_VALUES;
         if total_values > max_values {
             fpzip_read_close(fpz);
             return Err(anyhow::anyhow!(
@@ -254,36 +251,25 @@ pub fn decompress_fzip(data: &Vec<u8>) -> Result<Vec<f32>> {
     Ok(out)
 }
 
-pub fn scribble_mask_to_png(tensor: TensorRaw, size: Option<u32>) -> Result<Vec<u8>> {
+pub fn scribble_mask_to_png(
+    tensor: TensorRaw,
+    resize: Option<&ResizeOptions>,
+) -> Result<Vec<u8>> {
     let data = inflate_deflate(&tensor.data)?;
     let bw: Vec<u8> = data.iter().map(|&x| if x > 0 { 255 } else { 0 }).collect();
 
     let height = i32::from_le_bytes(tensor.dim[0..4].try_into().unwrap_or_default()) as u32;
     let width = i32::from_le_bytes(tensor.dim[4..8].try_into().unwrap_or_default()) as u32;
 
-    let mut img = GrayImage::from_raw(width, height, bw)
+    let (bw, width, height) = match resize {
+        Some(resize) => resize_pixels(&bw, width, height, 1, resize)?,
+        None => (bw, width, height),
+    };
+    let img = GrayImage::from_raw(width, height, bw)
         .ok_or_else(|| anyhow::anyhow!("Failed to create image from raw"))?;
 
     let mut out = Vec::new();
-
-    if let Some(target_size) = size {
-        let crop_size = width.min(height);
-        let start_x = (width - crop_size) / 2;
-        let start_y = (height - crop_size) / 2;
-
-        let cropped =
-            image::imageops::crop(&mut img, start_x, start_y, crop_size, crop_size).to_image();
-        let resized = image::imageops::resize(
-            &cropped,
-            target_size,
-            target_size,
-            image::imageops::FilterType::Nearest,
-        );
-
-        resized.write_to(&mut Cursor::new(&mut out), image::ImageFormat::Png)?;
-    } else {
-        img.write_to(&mut Cursor::new(&mut out), image::ImageFormat::Png)?;
-    }
+    img.write_to(&mut Cursor::new(&mut out), image::ImageFormat::Png)?;
 
     Ok(out)
 }
@@ -312,12 +298,75 @@ pub fn write_png_with_usercomment(
     height: u32,
     channels: usize,
     history_node: Option<TensorHistoryNodeData>,
+) -> Result<Vec<u8>> {
+    write_png(pixels, width, height, channels, BitDepth::Eight, history_node)
+}
+
+/// Encodes f32 image data in `[-1, 1]` as a 16-bit png, or a 32-bit float tiff or exr.
+/// Only the png embeds metadata.
+pub fn encode_high_bit_depth(
+    data: &[f32],
+    width: u32,
+    height: u32,
+    channels: usize,
+    format: TensorImageFormat,
+    history_node: Option<TensorHistoryNodeData>,
+) -> Result<Vec<u8>> {
+    let len = (width * height) as usize * channels;
+    let data = data
+        .get(..len)
+        .ok_or_else(|| anyhow::anyhow!("Tensor data is smaller than {}x{}", width, height))?;
+    let unit = |v: &f32| (v * 0.5 + 0.5).clamp(0.0, 1.0);
+
+    if format == TensorImageFormat::Png16 {
+        // png stores 16-bit samples big endian
+        let pixels: Vec<u8> = data
+            .iter()
+            .flat_map(|v| ((unit(v) * 65535.0).round() as u16).to_be_bytes())
+            .collect();
+        return write_png(&pixels, width, height, channels, BitDepth::Sixteen, history_node);
+    }
+
+    // float tiff and exr are written as rgb(a)
+    let samples: Vec<f32> = data.iter().map(unit).collect();
+    let image = match channels {
+        1 => DynamicImage::ImageRgb32F(Rgb32FImage::from_fn(width, height, |x, y| {
+            let v = samples[(y * width + x) as usize];
+            image::Rgb([v, v, v])
+        })),
+        3 => DynamicImage::ImageRgb32F(
+            Rgb32FImage::from_raw(width, height, samples)
+                .ok_or_else(|| anyhow::anyhow!("Invalid tensor size"))?,
+        ),
+        4 => DynamicImage::ImageRgba32F(
+            Rgba32FImage::from_raw(width, height, samples)
+                .ok_or_else(|| anyhow::anyhow!("Invalid tensor size"))?,
+        ),
+        _ => return Err(anyhow::anyhow!("Unsupported channel count ({})", channels)),
+    };
+
+    let image_format = match format {
+        TensorImageFormat::Exr32 => image::ImageFormat::OpenExr,
+        _ => image::ImageFormat::Tiff,
+    };
+    let mut out = Vec::new();
+    image.write_to(&mut Cursor::new(&mut out), image_format)?;
+    Ok(out)
+}
+
+fn write_png(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    channels: usize,
+    depth: BitDepth,
+    history_node: Option<TensorHistoryNodeData>,
 ) -> Result<Vec<u8>> {
     let mut out = Vec::new();
     let cursor = Cursor::new(&mut out);
 
     let mut encoder = Encoder::new(cursor, width, height);
-    encoder.set_depth(BitDepth::Eight);
+    encoder.set_depth(depth);
     encoder.set_color(match channels {
         1 => ColorType::Grayscale,
         2 => ColorType::GrayscaleAlpha,
@@ -385,7 +434,152 @@ pub fn write_jpeg_with_metadata(
     Ok(jpeg.encoder().bytes().to_vec())
 }
 
-const XMP_APP1_HEADER: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+/// Replaces any metadata in an encoded png or jpeg with what an export keeps: the Draw Things
+/// metadata of `history`, which has already been through `MetadataPrivacy::apply`, and
+/// optionally the A1111 parameters. Without `history` the image is stripped.
+pub fn write_export_metadata(
+    image: &[u8],
+    history: Option<&TensorHistoryNodeData>,
+    a1111_parameters: bool,
+) -> Result<Vec<u8>> {
+    use img_parts::png::{Png, PngChunk};
+    use img_parts::Bytes;
+
+    let stripped = strip_image_metadata(image)?;
+    let Some(history) = history else {
+        return Ok(stripped);
+    };
+
+    let image = match Png::from_bytes(Bytes::copy_from_slice(&stripped)) {
+        Ok(mut png) => {
+            let ihdr = png
+                .chunk_by_type(*b"IHDR")
+                .ok_or_else(|| anyhow::anyhow!("Png has no IHDR chunk"))?
+                .contents()
+                .clone();
+            let width = u32::from_be_bytes(ihdr[0..4].try_into()?);
+            let height = u32::from_be_bytes(ihdr[4..8].try_into()?);
+
+            let metadata = DrawThingsMetadata::try_from(history)?;
+            let json_string = serde_json::to_string(&metadata)?;
+            let exif = build_exif_user_comment(&json_string, width, height);
+            let xmp = build_drawthings_xmp(&json_string, &build_description(&metadata));
+            let chunks = [
+                PngChunk::new(*b"eXIf", Bytes::from(exif)),
+                PngChunk::new(
+                    *b"iTXt",
+                    Bytes::from(build_itxt_chunk("XML:com.adobe.xmp", &xmp)),
+                ),
+            ];
+            let insert_at = png
+                .chunks()
+                .iter()
+                .position(|c| &c.kind() == b"IDAT")
+                .unwrap_or(png.chunks().len());
+            png.chunks_mut().splice(insert_at..insert_at, chunks);
+            png.encoder().bytes().to_vec()
+        }
+        Err(_) => write_jpeg_with_metadata(&stripped, history)?,
+    };
+
+    match a1111_parameters {
+        true => add_a1111_parameters(&image, history),
+        false => Ok(image),
+    }
+}
+
+/// Adds an A1111 style `parameters` string to an encoded png or jpeg, so the generation data
+/// is readable in Civitai and other A1111-aware tools. This goes where A1111 itself writes it:
+/// a `parameters` text chunk in pngs, and the EXIF UserComment in jpegs.
+pub fn add_a1111_parameters(image: &[u8], history: &TensorHistoryNodeData) -> Result<Vec<u8>> {
+    use img_parts::jpeg::{markers, Jpeg, JpegSegment};
+    use img_parts::png::{Png, PngChunk};
+    use img_parts::Bytes;
+
+    let metadata = DrawThingsMetadata::try_from(history)?;
+    let parameters = build_a1111_parameters(&metadata);
+
+    if let Ok(mut png) = Png::from_bytes(Bytes::copy_from_slice(image)) {
+        // tEXt is latin-1, so anything else has to go in an iTXt chunk
+        let chunk = match parameters.chars().all(|c| (c as u32) < 256) {
+            true => PngChunk::new(
+                *b"tEXt",
+                Bytes::from(build_text_chunk("parameters", &parameters)),
+            ),
+            false => PngChunk::new(
+                *b"iTXt",
+                Bytes::from(build_itxt_chunk("parameters", &parameters)),
+            ),
+        };
+        let insert_at = png
+            .chunks()
+            .iter()
+            .position(|c| &c.kind() == b"IDAT")
+            .unwrap_or(png.chunks().len());
+        png.chunks_mut().insert(insert_at, chunk);
+        return Ok(png.encoder().bytes().to_vec());
+    }
+
+    let mut jpeg = Jpeg::from_bytes(Bytes::copy_from_slice(image))?;
+    let is_exif =
+        |s: &JpegSegment| s.marker() == markers::APP1 && s.contents().starts_with(EXIF_APP1_HEADER);
+
+    // the comment joins the tags already in the EXIF segment, like the image size
+    let existing = jpeg.segments().iter().position(is_exif);
+    let tiff = existing
+        .and_then(|i| {
+            let contents = jpeg.segments()[i].contents();
+            merge_exif_user_comment(&contents[EXIF_APP1_HEADER.len()..], &parameters)
+        })
+        .unwrap_or_else(|| build_exif_unicode_user_comment(&parameters));
+
+    let mut payload = EXIF_APP1_HEADER.to_vec();
+    payload.extend(tiff);
+    let segment = JpegSegment::new_with_contents(markers::APP1, Bytes::from(payload));
+
+    // only one EXIF segment is allowed
+    jpeg.segments_mut().retain(|s| !is_exif(s));
+    let insert_at = existing.unwrap_or(jpeg.segments().len().min(1));
+    jpeg.segments_mut().insert(insert_at, segment);
+
+    Ok(jpeg.encoder().bytes().to_vec())
+}
+
+/// Removes all text and EXIF metadata from an encoded png or jpeg. Chunks and segments needed
+/// to display the image correctly (color profiles, Adobe color transforms) are kept.
+pub fn strip_image_metadata(image: &[u8]) -> Result<Vec<u8>> {
+    use img_parts::jpeg::{markers, Jpeg};
+    use img_parts::png::Png;
+    use img_parts::Bytes;
+
+    if let Ok(mut png) = Png::from_bytes(Bytes::copy_from_slice(image)) {
+        png.chunks_mut().retain(|c| {
+            !matches!(&c.kind(), b"tEXt" | b"zTXt" | b"iTXt" | b"eXIf" | b"tIME")
+        });
+        return Ok(png.encoder().bytes().to_vec());
+    }
+
+    let mut jpeg = Jpeg::from_bytes(Bytes::copy_from_slice(image))?;
+    jpeg.segments_mut().retain(|s| match s.marker() {
+        markers::APP0 | markers::APP2 | markers::APP14 => true,
+        marker => !(markers::APP1..=markers::APP15).contains(&marker) && marker != markers::COM,
+    });
+
+    Ok(jpeg.encoder().bytes().to_vec())
+}
+
+pub(crate) const XMP_APP1_HEADER: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+const EXIF_APP1_HEADER: &[u8] = b"Exif\0\0";
+
+fn build_text_chunk(keyword: &str, text: &str) -> Vec<u8> {
+    let mut out = Vec::new();
+
+    out.extend_from_slice(keyword.as_bytes());
+    out.push(0); // null-terminator for keyword
+    out.extend(text.chars().map(|c| c as u8)); // latin-1 body
+
+    out
+}
 
 fn build_itxt_chunk(keyword: &str, text: &str) -> Vec<u8> {
     let mut out = Vec::new();
@@ -458,6 +652,146 @@ fn build_exif_user_comment(_json: &str, width: u32, height: u32) -> Vec<u8> {
     exif
 }
 
+/// Builds a TIFF block with only a UserComment, encoded the way A1111 writes it
+/// (UNICODE, big endian UTF-16)
+fn build_exif_unicode_user_comment(text: &str) -> Vec<u8> {
+    use byteorder::{BigEndian, WriteBytesExt};
+
+    let mut comment = b"UNICODE\0".to_vec();
+    for unit in text.encode_utf16() {
+        comment.write_u16::<BigEndian>(unit).unwrap();
+    }
+
+    let mut exif = Vec::new();
+
+    // TIFF header (big endian)
+    exif.extend_from_slice(b"MM");
+    exif.write_u16::<BigEndian>(42).unwrap(); // magic
+    exif.write_u32::<BigEndian>(8).unwrap(); // IFD0 offset
+
+    // IFD0 with 1 entry, ExifOffset
+    exif.write_u16::<BigEndian>(1).unwrap();
+    exif.write_u16::<BigEndian>(0x8769).unwrap();
+    exif.write_u16::<BigEndian>(4).unwrap(); // LONG
+    exif.write_u32::<BigEndian>(1).unwrap();
+    exif.write_u32::<BigEndian>(26).unwrap(); // offset to SubIFD (8+2+12+4)
+    exif.write_u32::<BigEndian>(0).unwrap(); // next IFD = none
+
+    // Exif SubIFD at offset 26 with 1 entry, UserComment
+    exif.write_u16::<BigEndian>(1).unwrap();
+    exif.write_u16::<BigEndian>(0x9286).unwrap();
+    exif.write_u16::<BigEndian>(7).unwrap(); // UNDEFINED
+    exif.write_u32::<BigEndian>(comment.len() as u32).unwrap();
+    exif.write_u32::<BigEndian>(44).unwrap(); // offset to value (26+2+12+4)
+    exif.write_u32::<BigEndian>(0).unwrap(); // next IFD = none
+
+    exif.extend(comment);
+
+    exif
+}
+
+/// Adds a UserComment, encoded the way A1111 writes it, to a TIFF structured EXIF block,
+/// replacing any it already has. The block is kept byte for byte and new copies of IFD0 and
+/// the Exif IFD are appended to it, so the offsets of every other tag stay valid. Returns None
+/// if the block can't be read.
+fn merge_exif_user_comment(tiff: &[u8], text: &str) -> Option<Vec<u8>> {
+    let big_endian = match tiff.get(0..2)? {
+        b"MM" => true,
+        b"II" => false,
+        _ => return None,
+    };
+    let read_u16 = |bytes: &[u8]| -> Option<u16> {
+        let bytes: [u8; 2] = bytes.get(..2)?.try_into().ok()?;
+        Some(match big_endian {
+            true => u16::from_be_bytes(bytes),
+            false => u16::from_le_bytes(bytes),
+        })
+    };
+    let read_u32 = |bytes: &[u8]| -> Option<u32> {
+        let bytes: [u8; 4] = bytes.get(..4)?.try_into().ok()?;
+        Some(match big_endian {
+            true => u32::from_be_bytes(bytes),
+            false => u32::from_le_bytes(bytes),
+        })
+    };
+    let u16_bytes = |v: u16| match big_endian {
+        true => v.to_be_bytes(),
+        false => v.to_le_bytes(),
+    };
+    let u32_bytes = |v: u32| match big_endian {
+        true => v.to_be_bytes(),
+        false => v.to_le_bytes(),
+    };
+    // the 12 byte entries of the ifd at `offset`, and the offset of the next ifd
+    let read_ifd = |offset: usize| -> Option<(Vec<Vec<u8>>, u32)> {
+        let count = read_u16(tiff.get(offset..)?)? as usize;
+        let entries = tiff
+            .get(offset + 2..offset + 2 + count * 12)?
+            .chunks_exact(12)
+            .map(<[u8]>::to_vec)
+            .collect();
+        Some((entries, read_u32(tiff.get(offset + 2 + count * 12..)?)?))
+    };
+    let tag = |entry: &Vec<u8>| read_u16(entry).unwrap_or_default();
+    let new_entry = |tag: u16, kind: u16, count: u32, value: u32| -> Vec<u8> {
+        let mut entry = u16_bytes(tag).to_vec();
+        entry.extend(u16_bytes(kind));
+        entry.extend(u32_bytes(count));
+        entry.extend(u32_bytes(value));
+        entry
+    };
+    let write_ifd = |out: &mut Vec<u8>, mut entries: Vec<Vec<u8>>, next: u32| {
+        // entries are sorted by tag
+        entries.sort_by_key(tag);
+        out.extend(u16_bytes(entries.len() as u16));
+        out.extend(entries.concat());
+        out.extend(u32_bytes(next));
+    };
+    // values and ifds start on a word boundary
+    let align = |out: &mut Vec<u8>| {
+        if out.len() % 2 == 1 {
+            out.push(0);
+        }
+    };
+
+    let (ifd0, ifd0_next) = read_ifd(read_u32(tiff.get(4..)?)? as usize)?;
+    let exif = match ifd0.iter().find(|e| tag(e) == EXIF_IFD_POINTER) {
+        Some(pointer) => read_ifd(read_u32(&pointer[8..])? as usize)?.0,
+        None => Vec::new(),
+    };
+
+    let mut comment = b"UNICODE\0".to_vec();
+    for unit in text.encode_utf16() {
+        comment.extend(u16_bytes(unit));
+    }
+
+    let mut out = tiff.to_vec();
+    align(&mut out);
+    let comment_offset = out.len() as u32;
+    let comment_len = comment.len() as u32;
+    out.extend(comment);
+    align(&mut out);
+
+    let mut exif: Vec<Vec<u8>> = exif
+        .into_iter()
+        .filter(|e| tag(e) != EXIF_USER_COMMENT)
+        .collect();
+    exif.push(new_entry(EXIF_USER_COMMENT, 7, comment_len, comment_offset)); // UNDEFINED
+    let exif_offset = out.len() as u32;
+    write_ifd(&mut out, exif, 0);
+
+    let mut ifd0: Vec<Vec<u8>> = ifd0
+        .into_iter()
+        .filter(|e| tag(e) != EXIF_IFD_POINTER)
+        .collect();
+    ifd0.push(new_entry(EXIF_IFD_POINTER, 4, 1, exif_offset)); // LONG
+    let ifd0_offset = out.len() as u32;
+    write_ifd(&mut out, ifd0, ifd0_next);
+
+    out[4..8].copy_from_slice(&u32_bytes(ifd0_offset));
+    Some(out)
+}
+
 fn format_desc_float(f: f64) -> String {
     // Draw Things seems to use f32-like stringification for the description,
     // and always includes .0 for whole numbers.
@@ -495,6 +829,61 @@ Steps: {}, Sampler: {}, Guidance Scale: {}, Seed: {}, Size: {}, Model: {}, Stren
         )
 }
 
+/// Builds an A1111 style `parameters` string. Loras are added to the prompt as
+/// `<lora:name:weight>` tags, and model and lora names drop their file extension.
+pub fn build_a1111_parameters(metadata: &DrawThingsMetadata) -> String {
+    /*
+    sample parameters:
+    a picture of a cool dude <lora:cool_lora:0.8>
+    Negative prompt: a negative prompt
+    Steps: 8, Sampler: DPM++ 2M, Schedule type: SGM Uniform, CFG scale: 1, Seed: 3665757974, Size: 1024x1024, Model: z_image_turbo_1.0_q6p, Version: Draw Things
+    */
+    let v2 = &metadata.v2;
+    let file_stem = |file: &str| {
+        std::path::Path::new(file)
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or(file)
+            .to_string()
+    };
+
+    let mut prompt = metadata.c.clone();
+    for lora in &v2.loras {
+        if let Some(file) = lora["file"].as_str().filter(|f| !f.is_empty()) {
+            let weight = lora["weight"].as_f64().unwrap_or(1.0) as f32;
+            prompt.push_str(&format!(" <lora:{}:{}>", file_stem(file), weight));
+        }
+    }
+
+    let mut settings = vec![format!("Steps: {}", v2.steps)];
+    if let Some((sampler, schedule)) = metadata.sampler.a1111_name() {
+        settings.push(format!("Sampler: {}", sampler));
+        if let Some(schedule) = schedule {
+            settings.push(format!("Schedule type: {}", schedule));
+        }
+    }
+    settings.push(format!("CFG scale: {}", v2.guidance_scale));
+    settings.push(format!("Seed: {}", v2.seed));
+    settings.push(format!("Size: {}x{}", v2.width, v2.height));
+    if !v2.model.is_empty() {
+        settings.push(format!("Model: {}", file_stem(&v2.model)));
+    }
+    if v2.strength < 1.0 {
+        settings.push(format!("Denoising strength: {}", v2.strength));
+    }
+    if v2.clip_skip > 1 {
+        settings.push(format!("Clip skip: {}", v2.clip_skip));
+    }
+    settings.push("Version: Draw Things".to_string());
+
+    let mut parameters = prompt.trim().to_string();
+    if !metadata.uc.is_empty() {
+        parameters.push_str(&format!("\nNegative prompt: {}", metadata.uc));
+    }
+    parameters.push_str(&format!("\n{}", settings.join(", ")));
+    parameters
+}
+
 pub fn build_drawthings_xmp(json: &str, description: &str) -> String {
     let escaped_description = description.replace("\n", "&#xA;");
     format!(
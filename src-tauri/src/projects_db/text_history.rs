@@ -1,5 +1,7 @@
 use super::fbs;
-use crate::projects_db::dtos::text::{TextHistoryNode, TextModification, TextRange, TextType};
+use crate::projects_db::dtos::text::{
+    PromptRevision, PromptTimeline, TextHistoryNode, TextModification, TextRange, TextType,
+};
 use serde::Serialize;
 use std::{collections::HashMap, sync::Mutex};
 
@@ -90,14 +92,16 @@ impl TextHistory {
         }
     }
 
+    /// Returns the lineage that holds the text history for `lineage`
+    pub fn resolve_lineage(&self, lineage: i64) -> i64 {
+        *self.lineages.get(&lineage).unwrap_or(&lineage)
+    }
+
     pub fn get_edit(&self, lineage: i64, text_edits: i64) -> Option<PromptPair> {
         // 1. Find the appropriate node to start from.
         // We look for a node with the same lineage and start_edits <= text_edits.
         // We pick the one with the highest start_edits among matches.
-        let mut lineage = lineage;
-        if let Some(point_to) = self.lineages.get(&lineage) {
-            lineage = *point_to;
-        }
+        let lineage = self.resolve_lineage(lineage);
 
         let node = self
             .nodes
@@ -170,6 +174,57 @@ impl TextHistory {
 
         Some(prompts)
     }
+
+    /// Lists every revision of the prompts in a lineage, in edit order. `generations` is left
+    /// empty, see `DTProject::get_prompt_timeline`.
+    pub fn get_timeline(&self, lineage: i64) -> PromptTimeline {
+        let lineage = self.resolve_lineage(lineage);
+        let mut nodes: Vec<&TextHistoryNode> =
+            self.nodes.iter().filter(|n| n.lineage == lineage).collect();
+        nodes.sort_by_key(|n| n.start_edits);
+
+        let mut revisions: Vec<PromptRevision> = Vec::new();
+        for node in nodes {
+            let mut prompts = PromptPair {
+                positive: node.start_positive_text.clone(),
+                negative: node.start_negative_text.clone(),
+            };
+
+            // a node usually starts where the previous one ended
+            if revisions.last().map(|r| r.text_edits) != Some(node.start_edits) {
+                revisions.push(revision(node.start_edits, &prompts, None));
+            }
+
+            for (i, modification) in node.modifications.iter().enumerate() {
+                match modification.modification_type {
+                    TextType::PositiveText => {
+                        apply_modification(&mut prompts.positive, modification)
+                    }
+                    TextType::NegativeText => {
+                        apply_modification(&mut prompts.negative, modification)
+                    }
+                }
+                let text_edits = node.start_edits + i as i64 + 1;
+                revisions.push(revision(text_edits, &prompts, Some(modification.clone())));
+            }
+        }
+
+        PromptTimeline { lineage, revisions }
+    }
+}
+
+fn revision(
+    text_edits: i64,
+    prompts: &PromptPair,
+    modification: Option<TextModification>,
+) -> PromptRevision {
+    PromptRevision {
+        text_edits,
+        positive: prompts.positive.clone(),
+        negative: prompts.negative.clone(),
+        modification,
+        generations: Vec::new(),
+    }
 }
 
 fn apply_modification(text: &mut String, modification: &TextModification) {
@@ -246,4 +301,40 @@ mod tests {
         let res2_back = history.get_edit(2, 2).unwrap();
         assert!(res2_back.positive.starts_with("3d fluffy llama"));
     }
+
+    #[test]
+    fn test_text_history_timeline() {
+        let sample_path = "src/projects_db/text_history_sample.json";
+        let content = fs::read_to_string(sample_path)
+            .or_else(|_| fs::read_to_string("src-tauri/src/projects_db/text_history_sample.json"))
+            .expect("Failed to read sample file");
+
+        let nodes: Vec<TextHistoryNode> =
+            serde_json::from_str(&content).expect("Failed to parse JSON");
+        let history = TextHistory::new(nodes, Vec::new());
+
+        let timeline = history.get_timeline(2);
+        assert_eq!(timeline.lineage, 2);
+
+        // every revision matches get_edit, and edits only move forward
+        let mut last_edits = -1;
+        for revision in &timeline.revisions {
+            assert!(revision.text_edits > last_edits);
+            last_edits = revision.text_edits;
+
+            let prompts = history.get_edit(2, revision.text_edits).unwrap();
+            assert_eq!(prompts.positive, revision.positive);
+            assert_eq!(prompts.negative, revision.negative);
+        }
+
+        let first = &timeline.revisions[0];
+        assert_eq!((first.text_edits, first.modification.as_ref()), (0, None));
+        let llama = timeline
+            .revisions
+            .iter()
+            .find(|r| r.text_edits == 2)
+            .unwrap();
+        assert!(llama.modification.is_some());
+        assert!(llama.positive.starts_with("3d fluffy llama"));
+    }
 }
@@ -0,0 +1,312 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::SystemTime,
+};
+
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+
+/// Default byte budget for the thumbnail cache
+pub const DEFAULT_THUMB_CACHE_BUDGET: u64 = 512 * 1024 * 1024;
+
+/// Identifies a cached dtm:// response
+///
+/// Thumbnails and tensors are never rewritten under the same id, so an entry stays valid as long
+/// as the project does. `version` only changes when a different project takes the project id (see
+/// `project_version`), and entries for an older version are removed the next time the project is
+/// requested.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheKey {
+    pub project_id: i64,
+    pub version: String,
+    /// The requested item, e.g. `thumb/1234` or `tensor/tensor_history_1?node=2&s=256`
+    pub item: String,
+    pub content_type: &'static str,
+}
+
+impl CacheKey {
+    fn hash(&self) -> String {
+        let digest = Sha256::digest(format!(
+            "{}:{}:{}",
+            self.project_id, self.version, self.item
+        ));
+        hex::encode(&digest[..12])
+    }
+
+    /// Quoted, for the `ETag` header
+    pub fn etag(&self) -> String {
+        format!("\"{}\"", self.hash())
+    }
+
+    fn file_name(&self) -> String {
+        let extension = match self.content_type {
+            "image/jpeg" => "jpg",
+            _ => "png",
+        };
+        format!(
+            "{}_{}_{}.{}",
+            self.project_id,
+            self.version,
+            self.hash(),
+            extension
+        )
+    }
+}
+
+/// Hashes the stored project fingerprint with the project path. Writes to the project don't
+/// change it, since they only add new ids. The cache is shared between libraries, where the same
+/// project id can belong to a different project.
+pub fn project_version(fingerprint: &str, path: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(fingerprint.as_bytes());
+    hasher.update(path.as_bytes());
+    hex::encode(&hasher.finalize()[..8])
+}
+
+struct Entry {
+    size: u64,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct CacheIndex {
+    entries: HashMap<String, Entry>,
+    /// the current version of each project, as seen by `check_version`
+    versions: HashMap<i64, String>,
+    total: u64,
+    clock: u64,
+}
+
+impl CacheIndex {
+    fn touch(&mut self, file_name: &str) -> bool {
+        self.clock += 1;
+        match self.entries.get_mut(file_name) {
+            Some(entry) => {
+                entry.last_used = self.clock;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn remove(&mut self, file_name: &str) -> Option<Entry> {
+        let entry = self.entries.remove(file_name)?;
+        self.total -= entry.size;
+        Some(entry)
+    }
+}
+
+/// Least recently used disk cache for dtm:// thumbnails and resized tensors
+///
+/// Entries are files in `dir`, named by project, project version and a hash of the request. The
+/// index is rebuilt from the directory when the cache is opened, using modified times as the
+/// last use.
+pub struct ThumbCache {
+    dir: PathBuf,
+    budget: AtomicU64,
+    index: Mutex<CacheIndex>,
+}
+
+impl ThumbCache {
+    /// Reads the cache directory, so this blocks
+    pub fn open(dir: impl AsRef<Path>, budget: u64) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+
+        let mut files: Vec<(String, u64, SystemTime)> = fs::read_dir(&dir)?
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let meta = entry.metadata().ok()?;
+                let name = entry.file_name().into_string().ok()?;
+                // leftovers from an interrupted write
+                if name.ends_with(".tmp") {
+                    let _ = fs::remove_file(entry.path());
+                    return None;
+                }
+                Some((name, meta.len(), meta.modified().ok()?))
+            })
+            .collect();
+        files.sort_by_key(|(_, _, modified)| *modified);
+
+        let mut index = CacheIndex::default();
+        for (name, size, _) in files {
+            index.clock += 1;
+            index.total += size;
+            index.entries.insert(
+                name,
+                Entry {
+                    size,
+                    last_used: index.clock,
+                },
+            );
+        }
+
+        let cache = Self {
+            dir,
+            budget: AtomicU64::new(budget),
+            index: Mutex::new(index),
+        };
+        for name in cache.evict_entries() {
+            let _ = fs::remove_file(cache.dir.join(name));
+        }
+        Ok(cache)
+    }
+
+    pub fn budget(&self) -> u64 {
+        self.budget.load(Ordering::Relaxed)
+    }
+
+    /// Changes the byte budget, evicting entries if the cache is now over it
+    pub async fn set_budget(&self, budget: u64) {
+        self.budget.store(budget, Ordering::Relaxed);
+        self.evict().await;
+    }
+
+    /// Total size of the cached files
+    pub fn size(&self) -> u64 {
+        self.index.lock().unwrap().total
+    }
+
+    /// Records the current version of a project. If it changed, the entries for the previous
+    /// versions are removed.
+    pub async fn check_version(&self, project_id: i64, version: &str) {
+        let stale: Vec<String> = {
+            let mut index = self.index.lock().unwrap();
+            if index.versions.get(&project_id).map(String::as_str) == Some(version) {
+                return;
+            }
+            index.versions.insert(project_id, version.to_string());
+
+            let project_prefix = format!("{}_", project_id);
+            let current_prefix = format!("{}_{}_", project_id, version);
+            let stale: Vec<String> = index
+                .entries
+                .keys()
+                .filter(|name| {
+                    name.starts_with(&project_prefix) && !name.starts_with(&current_prefix)
+                })
+                .cloned()
+                .collect();
+            for name in &stale {
+                index.remove(name);
+            }
+            stale
+        };
+        self.remove_files(stale).await;
+    }
+
+    pub async fn get(&self, key: &CacheKey) -> Option<Vec<u8>> {
+        let file_name = key.file_name();
+        if !self.index.lock().unwrap().touch(&file_name) {
+            return None;
+        }
+        match tokio::fs::read(self.dir.join(&file_name)).await {
+            Ok(bytes) => Some(bytes),
+            Err(_) => {
+                self.index.lock().unwrap().remove(&file_name);
+                None
+            }
+        }
+    }
+
+    pub async fn put(&self, key: &CacheKey, bytes: &[u8]) -> Result<()> {
+        let file_name = key.file_name();
+        // written to a temp file first so a reader never sees a partial entry
+        let path = self.dir.join(&file_name);
+        let temp_path = self.dir.join(format!("{}.tmp", file_name));
+        tokio::fs::write(&temp_path, bytes).await?;
+        tokio::fs::rename(&temp_path, &path).await?;
+
+        {
+            let mut index = self.index.lock().unwrap();
+            index.remove(&file_name);
+            index.clock += 1;
+            let last_used = index.clock;
+            index.total += bytes.len() as u64;
+            index.entries.insert(
+                file_name,
+                Entry {
+                    size: bytes.len() as u64,
+                    last_used,
+                },
+            );
+        }
+        self.evict().await;
+        Ok(())
+    }
+
+    /// Removes every entry for a project
+    pub async fn invalidate_project(&self, project_id: i64) {
+        let names: Vec<String> = {
+            let mut index = self.index.lock().unwrap();
+            index.versions.remove(&project_id);
+            let prefix = format!("{}_", project_id);
+            let names: Vec<String> = index
+                .entries
+                .keys()
+                .filter(|name| name.starts_with(&prefix))
+                .cloned()
+                .collect();
+            for name in &names {
+                index.remove(name);
+            }
+            names
+        };
+        self.remove_files(names).await;
+    }
+
+    pub async fn clear(&self) {
+        let names: Vec<String> = {
+            let mut index = self.index.lock().unwrap();
+            let names = index.entries.keys().cloned().collect();
+            *index = CacheIndex::default();
+            names
+        };
+        self.remove_files(names).await;
+    }
+
+    /// Removes the least recently used entries until the cache fits its budget
+    async fn evict(&self) {
+        let evicted = self.evict_entries();
+        self.remove_files(evicted).await;
+    }
+
+    /// Removes the least recently used entries from the index until the cache fits its budget,
+    /// returning the names of the files to delete
+    fn evict_entries(&self) -> Vec<String> {
+        let budget = self.budget();
+        let mut index = self.index.lock().unwrap();
+        let mut evicted = Vec::new();
+        if index.total <= budget {
+            return evicted;
+        }
+
+        let mut by_use: Vec<(u64, String)> = index
+            .entries
+            .iter()
+            .map(|(name, entry)| (entry.last_used, name.clone()))
+            .collect();
+        by_use.sort();
+        for (_, name) in by_use {
+            if index.total <= budget {
+                break;
+            }
+            index.remove(&name);
+            evicted.push(name);
+        }
+        evicted
+    }
+
+    /// Deletes entry files that have already been removed from the index
+    async fn remove_files(&self, names: Vec<String>) {
+        for name in names {
+            let _ = tokio::fs::remove_file(self.dir.join(name)).await;
+        }
+    }
+}
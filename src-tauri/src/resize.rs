@@ -0,0 +1,312 @@
+use anyhow::Result;
+use image::{imageops, ImageBuffer, Luma, LumaA, Pixel, Rgb, Rgba};
+use serde::{Deserialize, Serialize};
+use strum::EnumString;
+
+/// Resampling filter used when resizing decoded tensors
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, EnumString)]
+#[serde(rename_all = "camelCase")]
+#[strum(serialize_all = "camelCase", ascii_case_insensitive)]
+pub enum ResizeFilter {
+    #[default]
+    Lanczos3,
+    CatmullRom,
+    /// Area average, sharpest for large downscales
+    Box,
+}
+
+/// How the image is fitted into the target size
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, EnumString)]
+#[serde(rename_all = "camelCase")]
+#[strum(serialize_all = "camelCase", ascii_case_insensitive)]
+pub enum ResizeFit {
+    /// Scale to fit inside the target, keeping the aspect ratio. The output may be smaller
+    /// than the target on one side.
+    #[default]
+    Contain,
+    /// Scale to fill the target, keeping the aspect ratio, and center crop the overflow
+    Cover,
+    /// Stretch to the target size
+    Exact,
+}
+
+/// Target size and resampling for decoded tensors
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ResizeOptions {
+    pub width: u32,
+    pub height: u32,
+    #[serde(default)]
+    pub fit: ResizeFit,
+    #[serde(default)]
+    pub filter: ResizeFilter,
+}
+
+impl ResizeOptions {
+    /// A `size` x `size` box with the default fit and filter
+    pub fn square(size: u32) -> Self {
+        Self {
+            width: size,
+            height: size,
+            fit: ResizeFit::default(),
+            filter: ResizeFilter::default(),
+        }
+    }
+
+    /// Parses a size as used by the dtm protocol, either `256` or `256x128`
+    pub fn parse_size(size: &str) -> Result<Self> {
+        let (width, height) = match size.split_once('x') {
+            Some((width, height)) => (width.parse::<u32>()?, height.parse::<u32>()?),
+            None => {
+                let size = size.parse::<u32>()?;
+                (size, size)
+            }
+        };
+        if width == 0 || height == 0 {
+            anyhow::bail!("Invalid size: {}", size);
+        }
+        Ok(Self {
+            width,
+            height,
+            ..Self::square(0)
+        })
+    }
+
+    /// The size of an image of `width` x `height` after resizing
+    pub fn output_size(&self, width: u32, height: u32) -> (u32, u32) {
+        match self.fit {
+            ResizeFit::Contain if width > 0 && height > 0 => {
+                let scale =
+                    (self.width as f64 / width as f64).min(self.height as f64 / height as f64);
+                (
+                    ((width as f64 * scale).round() as u32).clamp(1, self.width),
+                    ((height as f64 * scale).round() as u32).clamp(1, self.height),
+                )
+            }
+            _ => (self.width, self.height),
+        }
+    }
+
+    /// The centered region of the source that is resized, as (x, y, width, height)
+    fn source_region(&self, width: u32, height: u32) -> (u32, u32, u32, u32) {
+        if self.fit != ResizeFit::Cover {
+            return (0, 0, width, height);
+        }
+        let target_aspect = self.width as f64 / self.height as f64;
+        let crop_width = ((height as f64 * target_aspect).round() as u32).clamp(1, width);
+        let crop_height = ((width as f64 / target_aspect).round() as u32).clamp(1, height);
+        (
+            (width - crop_width) / 2,
+            (height - crop_height) / 2,
+            crop_width,
+            crop_height,
+        )
+    }
+}
+
+/// Resizes interleaved 8-bit pixels. Returns the pixels and their size.
+pub fn resize_pixels(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    channels: usize,
+    options: &ResizeOptions,
+) -> Result<(Vec<u8>, u32, u32)> {
+    let samples: Vec<f32> = pixels.iter().map(|&v| v as f32 / 255.0).collect();
+    let (samples, width, height) = resize_samples(&samples, width, height, channels, options)?;
+    let pixels = samples
+        .into_iter()
+        .map(|v| (v * 255.0).round().clamp(0.0, 255.0) as u8)
+        .collect();
+    Ok((pixels, width, height))
+}
+
+/// Resizes interleaved f32 samples in `[0, 1]` (the resampling filters clamp to that range).
+/// Only the first `width * height * channels` samples are used. Returns the samples and their
+/// size.
+pub fn resize_samples(
+    samples: &[f32],
+    width: u32,
+    height: u32,
+    channels: usize,
+    options: &ResizeOptions,
+) -> Result<(Vec<f32>, u32, u32)> {
+    if width == 0 || height == 0 {
+        anyhow::bail!("Image has no size");
+    }
+    let (x0, y0, crop_width, crop_height) = options.source_region(width, height);
+    let (out_width, out_height) = options.output_size(crop_width, crop_height);
+
+    let row_len = width as usize * channels;
+    let samples = samples
+        .get(..row_len * height as usize)
+        .ok_or_else(|| anyhow::anyhow!("Image data is smaller than {}x{}", width, height))?;
+    let cropped: Vec<f32> = samples
+        .chunks_exact(row_len)
+        .skip(y0 as usize)
+        .take(crop_height as usize)
+        .flat_map(|row| {
+            let start = x0 as usize * channels;
+            &row[start..start + crop_width as usize * channels]
+        })
+        .copied()
+        .collect();
+
+    let filter = match options.filter {
+        ResizeFilter::Lanczos3 => imageops::FilterType::Lanczos3,
+        ResizeFilter::CatmullRom => imageops::FilterType::CatmullRom,
+        ResizeFilter::Box => {
+            let resized = box_resample(
+                &cropped,
+                (crop_width, crop_height),
+                (out_width, out_height),
+                channels,
+            );
+            return Ok((resized, out_width, out_height));
+        }
+    };
+
+    let size = (crop_width, crop_height, out_width, out_height);
+    let resized = match channels {
+        1 => resize_buffer::<Luma<f32>>(cropped, size, filter)?,
+        2 => resize_buffer::<LumaA<f32>>(cropped, size, filter)?,
+        3 => resize_buffer::<Rgb<f32>>(cropped, size, filter)?,
+        4 => resize_buffer::<Rgba<f32>>(cropped, size, filter)?,
+        _ => anyhow::bail!("Unsupported channel count ({})", channels),
+    };
+    Ok((resized, out_width, out_height))
+}
+
+fn resize_buffer<P>(
+    samples: Vec<f32>,
+    (width, height, out_width, out_height): (u32, u32, u32, u32),
+    filter: imageops::FilterType,
+) -> Result<Vec<f32>>
+where
+    P: Pixel<Subpixel = f32> + 'static,
+{
+    let image = ImageBuffer::<P, Vec<f32>>::from_raw(width, height, samples)
+        .ok_or_else(|| anyhow::anyhow!("Invalid image size"))?;
+    Ok(imageops::resize(&image, out_width, out_height, filter).into_raw())
+}
+
+/// Averages the source pixels covered by each output pixel. When upscaling, each output pixel
+/// covers less than one source pixel, so this becomes nearest neighbor.
+fn box_resample(
+    samples: &[f32],
+    (width, height): (u32, u32),
+    (out_width, out_height): (u32, u32),
+    channels: usize,
+) -> Vec<f32> {
+    // the source range covered by output index i, always at least one pixel
+    let span = |i: u32, out: u32, src: u32| {
+        let start = (i as u64 * src as u64 / out as u64) as usize;
+        let end = ((i as u64 + 1) * src as u64).div_ceil(out as u64) as usize;
+        start..end.max(start + 1).min(src as usize)
+    };
+
+    let mut out = Vec::with_capacity((out_width * out_height) as usize * channels);
+    let mut sum = vec![0.0f32; channels];
+    for oy in 0..out_height {
+        let rows = span(oy, out_height, height);
+        for ox in 0..out_width {
+            let cols = span(ox, out_width, width);
+            sum.fill(0.0);
+            for y in rows.clone() {
+                for x in cols.clone() {
+                    let base = (y * width as usize + x) * channels;
+                    for (s, v) in sum.iter_mut().zip(&samples[base..base + channels]) {
+                        *s += v;
+                    }
+                }
+            }
+            let count = (rows.len() * cols.len()) as f32;
+            out.extend(sum.iter().map(|s| s / count));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(width: u32, height: u32, fit: ResizeFit, filter: ResizeFilter) -> ResizeOptions {
+        ResizeOptions {
+            width,
+            height,
+            fit,
+            filter,
+        }
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(
+            ResizeOptions::parse_size("256").unwrap(),
+            ResizeOptions::square(256)
+        );
+        let options = ResizeOptions::parse_size("256x128").unwrap();
+        assert_eq!((options.width, options.height), (256, 128));
+        assert!(ResizeOptions::parse_size("0").is_err());
+        assert!(ResizeOptions::parse_size("wide").is_err());
+        assert_eq!(
+            "catmullrom".parse::<ResizeFilter>().unwrap(),
+            ResizeFilter::CatmullRom
+        );
+        assert_eq!("cover".parse::<ResizeFit>().unwrap(), ResizeFit::Cover);
+    }
+
+    #[test]
+    fn test_output_size() {
+        let filter = ResizeFilter::Lanczos3;
+        let contain = options(256, 256, ResizeFit::Contain, filter);
+        assert_eq!(contain.output_size(1024, 512), (256, 128));
+        assert_eq!(contain.output_size(512, 1024), (128, 256));
+        assert_eq!(contain.output_size(4096, 1), (256, 1));
+
+        let cover = options(256, 256, ResizeFit::Cover, filter);
+        assert_eq!(cover.source_region(1024, 512), (256, 0, 512, 512));
+        assert_eq!(cover.output_size(1024, 512), (256, 256));
+
+        let exact = options(100, 50, ResizeFit::Exact, filter);
+        assert_eq!(exact.source_region(1024, 512), (0, 0, 1024, 512));
+        assert_eq!(exact.output_size(1024, 512), (100, 50));
+    }
+
+    #[test]
+    fn test_resize_pixels() {
+        // left half black, right half white
+        let pixels: Vec<u8> = (0..8 * 4)
+            .flat_map(|i| match i % 8 < 4 {
+                true => [0u8, 0, 0],
+                false => [255u8, 255, 255],
+            })
+            .collect();
+
+        let box_contain = options(4, 4, ResizeFit::Contain, ResizeFilter::Box);
+        let (out, width, height) = resize_pixels(&pixels, 8, 4, 3, &box_contain).unwrap();
+        assert_eq!((width, height), (4, 2));
+        assert_eq!(
+            &out[..12],
+            &[0, 0, 0, 0, 0, 0, 255, 255, 255, 255, 255, 255]
+        );
+
+        // cover keeps the middle, which is split between black and white
+        let box_cover = options(2, 2, ResizeFit::Cover, ResizeFilter::Box);
+        let (out, width, height) = resize_pixels(&pixels, 8, 4, 3, &box_cover).unwrap();
+        assert_eq!((width, height), (2, 2));
+        assert_eq!(&out[..6], &[0, 0, 0, 255, 255, 255]);
+
+        for filter in [ResizeFilter::Lanczos3, ResizeFilter::CatmullRom] {
+            let exact = options(16, 16, ResizeFit::Exact, filter);
+            let (out, width, height) = resize_pixels(&pixels, 8, 4, 3, &exact).unwrap();
+            assert_eq!((width, height), (16, 16));
+            assert_eq!(out.len(), 16 * 16 * 3);
+            assert_eq!(out[0], 0);
+            assert_eq!(out[out.len() - 1], 255);
+        }
+
+        assert!(resize_pixels(&pixels, 16, 16, 3, &box_contain).is_err());
+    }
+}
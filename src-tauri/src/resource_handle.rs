@@ -1,6 +1,7 @@
 use anyhow::Result;
+use std::sync::Arc;
 
-use crate::Tensor;
+use crate::{ResizeOptions, Tensor};
 
 /// Resolves a resource handle into concrete bytes/tensors.
 ///
@@ -10,12 +11,12 @@ use crate::Tensor;
 /// DB-specific error types (e.g. `sqlx::Error`) out of the trait surface so
 /// non-DB backends can implement it too.
 #[async_trait::async_trait]
-pub trait ResourceHandle {
-    /// Decompressed tensor + header.
-    async fn get_tensor(&self) -> Result<Option<Tensor>>;
+pub trait ResourceHandle: Send + Sync {
+    /// Decompressed tensor + header, shared rather than copied.
+    async fn get_tensor(&self) -> Result<Option<Arc<Tensor>>>;
 
-    /// PNG bytes from the highest-quality source available.
-    async fn get_lossless(&self, size: Option<u32>) -> Result<Option<Vec<u8>>>;
+    /// PNG bytes from the highest-quality source available, optionally resized.
+    async fn get_lossless(&self, resize: Option<ResizeOptions>) -> Result<Option<Vec<u8>>>;
 
     /// Preview-quality image bytes.
     /// If half is true, half-size preview is returned if available, falling back to full-size
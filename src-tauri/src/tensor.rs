@@ -5,6 +5,7 @@ use crate::projects_db::{
     decompress_fzip, dt_project::TensorHistoryNode, dtos::tensor::TensorRaw, inflate_deflate,
     write_png_with_usercomment,
 };
+use crate::resize::{resize_pixels, ResizeOptions};
 
 /// A decompressed Draw Things tensor.
 ///
@@ -65,123 +66,57 @@ impl Tensor {
         }
     }
 
-    pub fn to_pixel_data(&self, size: Option<u32>) -> anyhow::Result<Vec<u8>> {
-        let w = self.width as usize;
-        let h = self.height as usize;
-        let c = self.channels as usize;
+    /// 8-bit pixels of the first frame, optionally resized. Masks and scribbles are thresholded
+    /// to 0 or 255.
+    pub fn to_pixel_data(&self, resize: Option<&ResizeOptions>) -> anyhow::Result<Vec<u8>> {
+        Ok(self.resized_pixel_data(resize)?.0)
+    }
 
-        let size = match size {
-            Some(s) => s as usize,
-            None => {
-                // full-res fast path
-                let mut out = vec![0u8; w * h * c];
+    pub fn to_png(
+        &self,
+        history_node: Option<&TensorHistoryNode>,
+        resize: Option<&ResizeOptions>,
+    ) -> Result<Vec<u8>> {
+        let (pixels, width, height) = self.resized_pixel_data(resize)?;
+        let channels = self.channels;
 
-                match &self.data {
-                    TensorValue::U8(src) => {
-                        let mut i = 0usize;
-                        while i < out.len().min(src.len()) {
-                            out[i] = if src[i] > 0 { 255 } else { 0 };
-                            i += 1;
-                        }
-                    }
-                    TensorValue::F32(src) => {
-                        let mut i = 0usize;
-                        while i < src.len() {
-                            let v = src[i];
-                            let v = ((v + 1.0) * 0.5 * 255.0).clamp(0.0, 255.0);
-                            out[i] = v as u8;
-                            i += 1;
-                        }
-                    }
-                }
+        let metadata = history_node.map(|n| n.node_data());
 
-                return Ok(out);
-            }
-        };
+        let png = write_png_with_usercomment(&pixels, width, height, channels as usize, metadata)?;
 
-        let side = w.min(h);
-        let x0 = (w - side) / 2;
-        let y0 = (h - side) / 2;
+        Ok(png)
+    }
 
-        let mut out = vec![0u8; size * size * c];
+    fn resized_pixel_data(&self, resize: Option<&ResizeOptions>) -> Result<(Vec<u8>, u32, u32)> {
+        let w = self.width as usize;
+        let h = self.height as usize;
+        let c = self.channels as usize;
+
+        let mut out = vec![0u8; w * h * c];
 
         match &self.data {
             TensorValue::U8(src) => {
-                let src_ptr = src.as_ptr();
-
-                for oy in 0..size {
-                    let sy = (oy * side) / size + y0;
-                    let row_base_out = oy * size * c;
-
-                    for ox in 0..size {
-                        let sx = (ox * side) / size + x0;
-
-                        let src_base = (sy * w + sx) * c;
-                        let dst_base = row_base_out + ox * c;
-
-                        unsafe {
-                            std::ptr::copy_nonoverlapping(
-                                src_ptr.add(src_base),
-                                out.as_mut_ptr().add(dst_base),
-                                c,
-                            );
-                        }
-
-                        // threshold in-place (small loop, still cheap)
-                        let dst_slice = &mut out[dst_base..dst_base + c];
-                        for v in dst_slice {
-                            *v = if *v > 0 { 255 } else { 0 };
-                        }
-                    }
+                let mut i = 0usize;
+                while i < out.len().min(src.len()) {
+                    out[i] = if src[i] > 0 { 255 } else { 0 };
+                    i += 1;
                 }
             }
-
             TensorValue::F32(src) => {
-                let src_ptr = src.as_ptr();
-
-                for oy in 0..size {
-                    let sy = (oy * side) / size + y0;
-                    let row_base_out = oy * size * c;
-
-                    for ox in 0..size {
-                        let sx = (ox * side) / size + x0;
-
-                        let src_base = (sy * w + sx) * c;
-                        let dst_base = row_base_out + ox * c;
-
-                        for ch in 0..c {
-                            unsafe {
-                                let v = *src_ptr.add(src_base + ch);
-                                let v = ((v + 1.0) * 0.5 * 255.0).clamp(0.0, 255.0);
-                                *out.get_unchecked_mut(dst_base + ch) = v as u8;
-                            }
-                        }
-                    }
+                let mut i = 0usize;
+                while i < out.len().min(src.len()) {
+                    let v = src[i];
+                    let v = ((v + 1.0) * 0.5 * 255.0).clamp(0.0, 255.0);
+                    out[i] = v as u8;
+                    i += 1;
                 }
             }
         }
 
-        Ok(out)
-    }
-
-    pub fn to_png(
-        &self,
-        history_node: Option<&TensorHistoryNode>,
-        size: Option<u32>,
-    ) -> Result<Vec<u8>> {
-        let pixels = self.to_pixel_data(size)?;
-        let (width, height) = if let Some(target_size) = size {
-            (target_size, target_size)
-        } else {
-            (self.width, self.height)
-        };
-        let channels = self.channels;
-
-        let metadata = history_node.map(|n| n.node_data());
-
-        let png = write_png_with_usercomment(&pixels, width, height, channels as usize, metadata)?;
-
-        Ok(png)
+        match resize {
+            Some(resize) => resize_pixels(&out, self.width, self.height, c, resize),
+            None => Ok((out, self.width, self.height)),
+        }
     }
 }
 
@@ -0,0 +1,194 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+
+use crate::{projects_db::MAX_TENSOR_VALUES, Tensor, TensorValue};
+
+/// Default memory budget for decoded tensors
+pub const DEFAULT_TENSOR_CACHE_BUDGET: u64 = 512 * 1024 * 1024;
+
+/// Tensors larger than this fraction of the budget are not cached, so a single large tensor
+/// (up to `MAX_TENSOR_VALUES` f32s, 2GB) can't flush everything else
+const MAX_ENTRY_FRACTION: u64 = 4;
+
+/// Decoded tensors shared by resource handles, the dtm:// protocol and exports
+pub static TENSOR_CACHE: Lazy<TensorCache> =
+    Lazy::new(|| TensorCache::new(DEFAULT_TENSOR_CACHE_BUDGET));
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TensorCacheStats {
+    pub entries: usize,
+    pub bytes: u64,
+    pub budget: u64,
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    /// tensors that were decoded but too large to cache
+    pub skipped: u64,
+}
+
+struct Entry {
+    tensor: Arc<Tensor>,
+    bytes: u64,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct CacheState {
+    /// keyed by project path and tensor name
+    entries: HashMap<(String, String), Entry>,
+    bytes: u64,
+    clock: u64,
+}
+
+/// Least recently used cache of decoded tensors, bounded by the memory used by their data
+pub struct TensorCache {
+    budget: AtomicU64,
+    state: Mutex<CacheState>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+    skipped: AtomicU64,
+}
+
+/// Memory used by a tensor's data
+pub fn tensor_bytes(tensor: &Tensor) -> u64 {
+    match &tensor.data {
+        TensorValue::F32(data) => (data.len() * std::mem::size_of::<f32>()) as u64,
+        TensorValue::U8(data) => data.len() as u64,
+    }
+}
+
+impl TensorCache {
+    pub fn new(budget: u64) -> Self {
+        Self {
+            budget: AtomicU64::new(budget),
+            state: Mutex::new(CacheState::default()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+            skipped: AtomicU64::new(0),
+        }
+    }
+
+    pub fn get(&self, project_path: &str, name: &str) -> Option<Arc<Tensor>> {
+        let mut state = self.state.lock().unwrap();
+        state.clock += 1;
+        let clock = state.clock;
+        let key = (project_path.to_string(), name.to_string());
+        match state.entries.get_mut(&key) {
+            Some(entry) => {
+                entry.last_used = clock;
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                Some(entry.tensor.clone())
+            }
+            None => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+
+    /// Adds a decoded tensor, evicting the least recently used tensors to stay in budget.
+    /// Returns false if the tensor is too large to cache.
+    pub fn insert(&self, project_path: &str, name: &str, tensor: Arc<Tensor>) -> bool {
+        let bytes = tensor_bytes(&tensor);
+        let budget = self.budget();
+        // the largest tensor decompress_fzip allows is never worth caching
+        let max_entry = (budget / MAX_ENTRY_FRACTION)
+            .min((MAX_TENSOR_VALUES * std::mem::size_of::<f32>()) as u64);
+        if bytes > max_entry {
+            self.skipped.fetch_add(1, Ordering::Relaxed);
+            return false;
+        }
+
+        let mut state = self.state.lock().unwrap();
+        state.clock += 1;
+        let last_used = state.clock;
+        let key = (project_path.to_string(), name.to_string());
+        if let Some(previous) = state.entries.insert(
+            key,
+            Entry {
+                tensor,
+                bytes,
+                last_used,
+            },
+        ) {
+            state.bytes -= previous.bytes;
+        }
+        state.bytes += bytes;
+        self.evict(&mut state, budget);
+        true
+    }
+
+    pub fn budget(&self) -> u64 {
+        self.budget.load(Ordering::Relaxed)
+    }
+
+    /// Changes the memory budget, evicting tensors if the cache is now over it
+    pub fn set_budget(&self, budget: u64) {
+        self.budget.store(budget, Ordering::Relaxed);
+        let mut state = self.state.lock().unwrap();
+        self.evict(&mut state, budget);
+    }
+
+    /// Removes every tensor of a project
+    pub fn invalidate_project(&self, project_path: &str) {
+        let mut state = self.state.lock().unwrap();
+        let mut removed = 0;
+        state.entries.retain(|(path, _), entry| {
+            let keep = path != project_path;
+            if !keep {
+                removed += entry.bytes;
+            }
+            keep
+        });
+        state.bytes -= removed;
+    }
+
+    pub fn clear(&self) {
+        *self.state.lock().unwrap() = CacheState::default();
+    }
+
+    pub fn stats(&self) -> TensorCacheStats {
+        let state = self.state.lock().unwrap();
+        TensorCacheStats {
+            entries: state.entries.len(),
+            bytes: state.bytes,
+            budget: self.budget(),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            skipped: self.skipped.load(Ordering::Relaxed),
+        }
+    }
+
+    fn evict(&self, state: &mut CacheState, budget: u64) {
+        if state.bytes <= budget {
+            return;
+        }
+        let mut by_use: Vec<(u64, (String, String))> = state
+            .entries
+            .iter()
+            .map(|(key, entry)| (entry.last_used, key.clone()))
+            .collect();
+        by_use.sort();
+        for (_, key) in by_use {
+            if state.bytes <= budget {
+                break;
+            }
+            if let Some(entry) = state.entries.remove(&key) {
+                state.bytes -= entry.bytes;
+                self.evictions.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+}
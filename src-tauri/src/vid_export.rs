@@ -1,15 +1,12 @@
 use regex::Regex;
-use sea_orm::{EntityTrait, QuerySelect};
 use serde::{Deserialize, Serialize};
 use std::io::{BufRead, BufReader};
 use std::process::{Command, Stdio};
 use std::{fs, path::PathBuf};
-use tauri::{Emitter, Manager, State};
+use tauri::{Emitter, Manager};
 
-use crate::dtp_service::DTPService;
 use crate::projects_db::{
-    decode_tensor, DTProject, DecodeTensorOptions, DrawThingsMetadata, DtProjectRef,
-    DtResourceHandle, DtResourceRef,
+    dtos::export::MetadataPrivacy, write_export_metadata, DrawThingsMetadata, DtResourceHandle,
 };
 use crate::{IntoTAResult, ResourceHandle, TAResult};
 
@@ -22,119 +19,103 @@ pub struct FramesExportOpts {
     pub filename_pattern: String,
     pub clip_number: Option<u32>,
     pub start_frame: Option<u32>,
+    /// applied to the clip's metadata before it is written to each frame
+    #[serde(default)]
+    pub privacy: MetadataPrivacy,
 }
 
 #[tauri::command]
 pub async fn save_all_clip_frames(
     app: tauri::AppHandle,
-    dtp: State<'_, DTPService>,
     opts: FramesExportOpts,
 ) -> TAResult<(usize, String)> {
-    let projects_db = dtp.get_db().await.unwrap();
-
-    let result: Option<(i64, i64)> = entity::images::Entity::find_by_id(opts.image_id)
-        .select_only()
-        .column(entity::images::Column::NodeId)
-        .column(entity::images::Column::ProjectId)
-        .into_tuple()
-        .one(&projects_db.db)
+    let handle = DtResourceHandle::from_image_id(opts.image_id)
         .await
-        .into_ta_result()?;
+        .into_ta_result()?
+        .ok_or(anyhow::anyhow!("Image or Project not found"))?;
+
+    let total = export_clip_frames(&handle, &opts, |current, total| {
+        let _ = app.emit(
+            "export_frames_progress",
+            ExportProgress {
+                current,
+                total,
+                msg: "Extracting frames...".to_string(),
+            },
+        );
+    })
+    .await
+    .into_ta_result()?;
 
-    let (node_id, project_id) = result.ok_or(anyhow::anyhow!("Image or Project not found"))?;
+    let _ = app.emit(
+        "export_frames_progress",
+        ExportProgress {
+            current: total,
+            total,
+            msg: "Done".to_string(),
+        },
+    );
 
-    let project = projects_db.get_project(project_id).await.unwrap();
+    Ok((total, opts.output_dir))
+}
 
-    // 2. Fetch Clip Frames
-    let dt_project = DTProject::open(&project.full_path).await.into_ta_result()?;
-    let frames = dt_project
-        .get_histories_from_clip(node_id)
-        .await
-        .into_ta_result()?;
+/// Writes every frame of the clip at `handle` to `opts.output_dir`, returning the frame count.
+/// Each frame carries the clip's metadata, filtered through `opts.privacy` like an image export.
+pub async fn export_clip_frames(
+    handle: &DtResourceHandle,
+    opts: &FramesExportOpts,
+    on_progress: impl Fn(usize, usize),
+) -> anyhow::Result<usize> {
+    let frames = handle
+        .get_frames(!opts.use_tensor)
+        .await?
+        .unwrap_or_default();
 
     if frames.is_empty() {
-        return anyhow::anyhow!("No frames found for this clip").into_ta_result();
+        anyhow::bail!("No frames found for this clip");
     }
 
+    let node_data = handle
+        .get_history_node()
+        .await?
+        .and_then(|node| opts.privacy.apply(node.node_data()));
+
     let output_dir = PathBuf::from(&opts.output_dir);
-    fs::create_dir_all(&output_dir).into_ta_result()?;
+    fs::create_dir_all(&output_dir)?;
 
     let mut name_gen = NameGen::new(NameOpts {
-        pattern: opts.filename_pattern,
+        pattern: opts.filename_pattern.clone(),
         clip_number: opts.clip_number,
         first: opts.start_frame,
         count: frames.len() as u32,
     });
 
     let total = frames.len();
-    match opts.use_tensor {
-        true => {
-            for (i, frame) in frames.iter().enumerate() {
-                let name = name_gen.next().unwrap();
-                let tensor = dt_project
-                    .get_tensor_raw(&frame.tensor_id)
-                    .await
-                    .into_ta_result()?;
-                let png = decode_tensor(
-                    tensor,
-                    DecodeTensorOptions {
-                        as_png: true,
-                        history_node: None,
-                        size: None,
-                    },
-                )
-                .into_ta_result()?;
-                let file_path = output_dir.join(name);
-                fs::write(&file_path, png).into_ta_result()?;
-
-                let _ = app.emit(
-                    "export_frames_progress",
-                    ExportProgress {
-                        current: i + 1,
-                        total,
-                        msg: "Extracting frames...".to_string(),
-                    },
-                );
-            }
-        }
-        false => {
-            for (i, frame) in frames.iter().enumerate() {
-                let name = name_gen.next().unwrap();
-                let handle = DtResourceHandle::new(
-                    DtProjectRef::Id(project_id),
-                    DtResourceRef::Thumb(frame.preview_id),
-                );
-                let thumb_data = handle
-                    .get_preview(false)
-                    .await
-                    .into_ta_result()?
-                    .ok_or(anyhow::anyhow!("Failed to get preview"))?;
+    for (i, frame) in frames.iter().enumerate() {
+        let name = name_gen.next().unwrap();
+        let data = match opts.use_tensor {
+            true => frame
+                .get_lossless(None)
+                .await?
+                .ok_or(anyhow::anyhow!("Failed to get tensor"))?,
+            false => frame
+                .get_preview(false)
+                .await?
+                .ok_or(anyhow::anyhow!("Failed to get preview"))?,
+        };
 
-                let file_path = output_dir.join(name);
-                fs::write(&file_path, thumb_data).into_ta_result()?;
+        let file_path = output_dir.join(name);
+        let node_data = node_data.clone();
+        tokio::task::spawn_blocking(move || -> anyhow::Result<()> {
+            let data = write_export_metadata(&data, node_data.as_ref(), false)?;
+            fs::write(&file_path, data).map_err(anyhow::Error::from)
+        })
+        .await??;
 
-                let _ = app.emit(
-                    "export_frames_progress",
-                    ExportProgress {
-                        current: i + 1,
-                        total,
-                        msg: "Extracting frames...".to_string(),
-                    },
-                );
-            }
-        }
+        on_progress(i + 1, total);
     }
 
-    let _ = app.emit(
-        "export_frames_progress",
-        ExportProgress {
-            current: total,
-            total,
-            msg: "Done".to_string(),
-        },
-    );
-
-    Ok((total, output_dir.to_str().unwrap().to_string()))
+    Ok(total)
 }
 
 #[derive(Clone, Serialize, Debug)]
@@ -160,7 +141,6 @@ pub struct VideoExportOpts {
 #[tauri::command]
 pub async fn create_video_from_frames(
     app: tauri::AppHandle,
-    dtp: State<'_, DTPService>,
     opts: VideoExportOpts,
 ) -> TAResult<String> {
     // -------------------------------------------------
@@ -195,7 +175,6 @@ pub async fn create_video_from_frames(
     // -------------------------------------------------
     let (frame_count, _) = save_all_clip_frames(
         app.clone(),
-        dtp.clone(),
         FramesExportOpts {
             image_id: opts.image_id,
             output_dir: temp_dir.to_str().unwrap().to_string(),
@@ -203,6 +182,11 @@ pub async fn create_video_from_frames(
             filename_pattern: format!("frame_####.{}", extension),
             clip_number: None,
             start_frame: None,
+            // the frames are only read by ffmpeg
+            privacy: MetadataPrivacy {
+                strip: true,
+                ..Default::default()
+            },
         },
     )
     .await?;
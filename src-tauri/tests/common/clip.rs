@@ -0,0 +1,95 @@
+use dtm_lib::projects_db::tensor_history_generated::{
+    root_as_tensor_history_node, TensorHistoryNode, TensorHistoryNodeArgs,
+};
+use flatbuffers::FlatBufferBuilder;
+use sqlx::{Connection, Row, SqliteConnection};
+use tempfile::TempDir;
+
+pub const CLIP_PROMPT: &str = "a lighthouse at dusk, clip fixture";
+
+/// A copy of the test project where a run of consecutive generated images has been turned
+/// into a clip, since the project itself has no video
+pub struct ClipFixture {
+    pub dir: TempDir,
+    pub path: String,
+    /// rowid of the clip's first frame
+    pub node_id: i64,
+    pub num_frames: u32,
+}
+
+impl ClipFixture {
+    pub async fn new(num_frames: u32) -> Self {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("clip-project.sqlite3");
+        std::fs::copy("test_data/projects/test-project-a2.sqlite3", &path).unwrap();
+        let path = path.to_str().unwrap().to_string();
+
+        let mut conn = SqliteConnection::connect(&format!("sqlite://{}", path))
+            .await
+            .unwrap();
+
+        // frames need a tensor and a preview, and must have consecutive rowids
+        let rows = sqlx::query(
+            "SELECT DISTINCT thn.rowid, thn.p FROM tensorhistorynode AS thn
+            JOIN tensordata AS td ON td.__pk0 = thn.__pk0 AND td.__pk1 = thn.__pk1
+            JOIN tensordata__f20 AS td_f20 ON td_f20.rowid = td.rowid
+            ORDER BY thn.rowid",
+        )
+        .fetch_all(&mut conn)
+        .await
+        .unwrap();
+        let nodes: Vec<(i64, Vec<u8>)> = rows
+            .iter()
+            .map(|row| (row.get(0), row.get(1)))
+            .filter(|(_, p): &(i64, Vec<u8>)| {
+                root_as_tensor_history_node(p).unwrap().preview_id() > 0
+            })
+            .collect();
+        let run = nodes
+            .windows(num_frames as usize)
+            .find(|w| w.windows(2).all(|pair| pair[1].0 == pair[0].0 + 1))
+            .expect("test project has no run of consecutive images");
+
+        for (index, (rowid, p)) in run.iter().enumerate() {
+            let node = root_as_tensor_history_node(p).unwrap();
+            let mut fbb = FlatBufferBuilder::new();
+            let text_prompt = fbb.create_string(CLIP_PROMPT);
+            let model = node.model().map(|model| fbb.create_string(model));
+            let args = TensorHistoryNodeArgs {
+                lineage: node.lineage(),
+                logical_time: node.logical_time(),
+                start_width: node.start_width(),
+                start_height: node.start_height(),
+                seed: node.seed(),
+                steps: node.steps(),
+                guidance_scale: node.guidance_scale(),
+                model,
+                tensor_id: node.tensor_id(),
+                generated: true,
+                preview_id: node.preview_id(),
+                text_prompt: Some(text_prompt),
+                num_frames,
+                clip_id: 1,
+                index_in_a_clip: index as i32,
+                ..Default::default()
+            };
+            let offset = TensorHistoryNode::create(&mut fbb, &args);
+            fbb.finish(offset, None);
+
+            sqlx::query("UPDATE tensorhistorynode SET p = ?1 WHERE rowid = ?2")
+                .bind(fbb.finished_data())
+                .bind(rowid)
+                .execute(&mut conn)
+                .await
+                .unwrap();
+        }
+        conn.close().await.unwrap();
+
+        Self {
+            path,
+            node_id: run[0].0,
+            num_frames,
+            dir,
+        }
+    }
+}
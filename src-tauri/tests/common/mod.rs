@@ -15,6 +15,8 @@ use tempfile::TempDir;
 
 use crate::common::projects::{WatchFolderHelper, Watchfolder};
 
+pub mod clip;
+pub mod moodboard;
 pub mod projects;
 pub mod util;
 
@@ -202,4 +204,3 @@ pub async fn test_fixture(
 
     (dtps, event_helper, wfh, db_path)
 }
-
@@ -0,0 +1,90 @@
+use dtm_lib::projects_db::{
+    fbs::{TensorMoodboardData, TensorMoodboardDataArgs},
+    tensor_history_generated::root_as_tensor_history_node,
+};
+use flatbuffers::FlatBufferBuilder;
+use sqlx::{Connection, Row, SqliteConnection};
+use tempfile::TempDir;
+
+pub const MOODBOARD_SHUFFLE_ID: i64 = 900_001;
+pub const MOODBOARD_WEIGHT: f32 = 0.75;
+
+/// A copy of the test project where a generated image has been added to a node's moodboard,
+/// since the project itself has no moodboard
+pub struct MoodboardFixture {
+    pub dir: TempDir,
+    pub path: String,
+    /// rowid of the node with the moodboard
+    pub node_id: i64,
+}
+
+impl MoodboardFixture {
+    pub async fn new() -> Self {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("moodboard-project.sqlite3");
+        std::fs::copy("test_data/projects/test-project-a2.sqlite3", &path).unwrap();
+        let path = path.to_str().unwrap().to_string();
+
+        let mut conn = SqliteConnection::connect(&format!("sqlite://{}", path))
+            .await
+            .unwrap();
+
+        let rows = sqlx::query("SELECT rowid, p FROM tensorhistorynode ORDER BY rowid")
+            .fetch_all(&mut conn)
+            .await
+            .unwrap();
+        let (node_id, p): (i64, Vec<u8>) = rows
+            .iter()
+            .map(|row| (row.get(0), row.get(1)))
+            .find(|(_, p): &(i64, Vec<u8>)| root_as_tensor_history_node(p).unwrap().tensor_id() > 0)
+            .expect("test project has no generated images");
+        let node = root_as_tensor_history_node(&p).unwrap();
+
+        // the moodboard image is a copy of the node's own image
+        sqlx::query(
+            "INSERT INTO tensors (name, type, format, datatype, dim, data)
+            SELECT ?1, type, format, datatype, dim, data FROM tensors WHERE name = ?2",
+        )
+        .bind(format!("shuffle_{}", MOODBOARD_SHUFFLE_ID))
+        .bind(format!("tensor_history_{}", node.tensor_id()))
+        .execute(&mut conn)
+        .await
+        .unwrap();
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS tensormoodboarddata (
+                rowid INTEGER PRIMARY KEY AUTOINCREMENT,
+                __pk0 INTEGER, __pk1 INTEGER, __pk2 INTEGER, p BLOB,
+                UNIQUE(__pk0, __pk1, __pk2)
+            )",
+        )
+        .execute(&mut conn)
+        .await
+        .unwrap();
+
+        let mut fbb = FlatBufferBuilder::new();
+        let args = TensorMoodboardDataArgs {
+            lineage: node.lineage(),
+            logical_time: node.logical_time(),
+            index: 0,
+            shuffle_id: MOODBOARD_SHUFFLE_ID,
+            weight: MOODBOARD_WEIGHT,
+        };
+        let offset = TensorMoodboardData::create(&mut fbb, &args);
+        fbb.finish(offset, None);
+
+        sqlx::query(
+            "INSERT OR REPLACE INTO tensormoodboarddata (__pk0, __pk1, __pk2, p)
+            VALUES (?1, ?2, 0, ?3)",
+        )
+        .bind(node.lineage())
+        .bind(node.logical_time())
+        .bind(fbb.finished_data())
+        .execute(&mut conn)
+        .await
+        .unwrap();
+        conn.close().await.unwrap();
+
+        Self { dir, path, node_id }
+    }
+}
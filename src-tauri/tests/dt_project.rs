@@ -2,7 +2,10 @@ mod common;
 
 #[cfg(test)]
 mod tests {
-    use dtm_lib::projects_db::dt_project::{DTProject, ThnData, ThnFilter};
+    use dtm_lib::projects_db::dt_project::{
+        explorer::MAX_TABLE_PAGE, DTProject, DTProjectTable, TdFilter, ThnData, ThnFilter,
+    };
+    use sqlx::{Connection, SqliteConnection};
 
     #[tokio::test]
     async fn test_tensor_history_node() -> Result<(), Box<dyn std::error::Error>> {
@@ -19,4 +22,172 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_node_lineage() -> Result<(), Box<dyn std::error::Error>> {
+        let dt_project = DTProject::open("test_data/projects/test-project-a2.sqlite3").await?;
+
+        let nodes = dt_project.get_tensor_history_nodes(None, None).await?;
+        let last = nodes.last().unwrap();
+        let lineage = dt_project.get_node_lineage(last.rowid).await?;
+        assert_eq!(lineage.node.rowid, last.rowid);
+
+        // each ancestor is one step earlier in logical time, ending at the root
+        let mut child = &lineage.node;
+        for ancestor in &lineage.ancestors {
+            assert_eq!(child.parent, Some(ancestor.rowid));
+            assert_eq!(ancestor.logical_time, child.logical_time - 1);
+            assert_eq!(child.branch, ancestor.lineage != child.lineage);
+            child = ancestor;
+        }
+        assert_eq!(child.parent, None);
+
+        // and the node is a descendant of its root
+        if let Some(root) = lineage.ancestors.last() {
+            let root_lineage = dt_project.get_node_lineage(root.rowid).await?;
+            assert!(root_lineage.ancestors.is_empty());
+            assert!(root_lineage
+                .descendants
+                .iter()
+                .any(|node| node.rowid == last.rowid));
+        }
+
+        // the graph is reused while the project is unchanged
+        let graph = dt_project.get_lineage_graph().await?;
+        assert!(std::sync::Arc::ptr_eq(
+            &graph,
+            &dt_project.get_lineage_graph().await?
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_prompt_timeline() -> Result<(), Box<dyn std::error::Error>> {
+        let dt_project = DTProject::open("test_data/projects/test-project-a2.sqlite3").await?;
+
+        let nodes = dt_project.get_tensor_history_nodes(None, None).await?;
+        let last = nodes.last().unwrap();
+        let timeline = dt_project
+            .get_prompt_timeline(last.data().text_lineage())
+            .await?;
+        assert!(!timeline.revisions.is_empty());
+
+        // the node is listed under the revision it was generated with
+        let revision = timeline
+            .revisions
+            .iter()
+            .find(|r| r.generations.contains(&last.rowid))
+            .expect("node not linked to a revision");
+        assert_eq!(revision.text_edits, last.data().text_edits());
+
+        // and every linked generation was made with its revision
+        for revision in &timeline.revisions {
+            for rowid in &revision.generations {
+                let node = nodes.iter().find(|n| n.rowid == *rowid).unwrap();
+                assert_eq!(node.data().text_edits(), revision.text_edits);
+            }
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_table_explorer() -> Result<(), Box<dyn std::error::Error>> {
+        let dt_project = DTProject::open("test_data/projects/test-project-a2.sqlite3").await?;
+
+        let tables = dt_project.list_tables().await?;
+        assert_eq!(tables.len(), DTProjectTable::ALL.len());
+        let nodes = tables
+            .iter()
+            .find(|t| t.table == DTProjectTable::TensorHistoryNode)
+            .unwrap();
+        assert!(nodes.present);
+        assert!(nodes.row_count > 0);
+
+        let page = dt_project
+            .get_table_rows(DTProjectTable::TensorHistoryNode, 0, 3)
+            .await?;
+        assert_eq!(page.row_count, nodes.row_count);
+        assert!(page.rows.len() <= 3);
+        assert!(page.rows.windows(2).all(|w| w[0].rowid < w[1].rowid));
+        for row in &page.rows {
+            assert!(row.columns.contains_key("__pk0"));
+            assert!(row.data_error.is_none());
+            assert!(row.data.as_ref().unwrap().get("seed").is_some());
+        }
+
+        // pages are capped
+        let page = dt_project
+            .get_table_rows(DTProjectTable::TensorHistoryNode, 0, MAX_TABLE_PAGE + 100)
+            .await?;
+        assert_eq!(page.take, MAX_TABLE_PAGE);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_table_status() -> Result<(), Box<dyn std::error::Error>> {
+        let path = "test_data/projects/test-project-a2.sqlite3";
+        let dt_project = DTProject::open(path).await?;
+
+        // each table is detected under the name it has in the project file
+        let mut conn = SqliteConnection::connect(&format!("sqlite://{}?mode=ro", path)).await?;
+        let in_file: Vec<String> =
+            sqlx::query_scalar("SELECT name FROM sqlite_master WHERE type = 'table'")
+                .fetch_all(&mut conn)
+                .await?;
+        for table in dt_project.list_tables().await? {
+            let expected = in_file.iter().any(|name| name == table.name);
+            assert_eq!(table.present, expected, "{:?}", table.table);
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_check_compatibility() -> Result<(), Box<dyn std::error::Error>> {
+        let dt_project = DTProject::open("test_data/projects/test-project-a2.sqlite3").await?;
+
+        let report = dt_project.check_compatibility().await?;
+        assert!(report.tables.iter().any(|t| t == "tensorhistorynode"));
+        assert!(report.unknown_tables.is_empty());
+
+        let nodes = report
+            .flatbuffers
+            .iter()
+            .find(|fb| fb.table == DTProjectTable::TensorHistoryNode)
+            .unwrap();
+        assert!(nodes.rows_checked > 0);
+        assert_eq!(nodes.invalid_rows, 0);
+        assert!(!nodes.fields_present.is_empty());
+        assert!(nodes.fields_present.iter().all(|f| *f < nodes.known_fields));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_check_health() -> Result<(), Box<dyn std::error::Error>> {
+        let dt_project = DTProject::open("test_data/projects/test-project-a2.sqlite3").await?;
+        let total = dt_project.get_info().await?._history_count as u64;
+
+        let progress = std::sync::Mutex::new(Vec::new());
+        let report = dt_project
+            .check_health(|checked, of| progress.lock().unwrap().push((checked, of)))
+            .await?;
+
+        assert!(report.healthy, "{:?}", report.failures);
+        assert!(report.failures.is_empty());
+        assert_eq!(report.nodes.checked, total);
+        let tensor_data = dt_project.get_tensor_data(TdFilter::None).await?;
+        assert_eq!(report.tensor_data.checked, tensor_data.len() as u64);
+        assert!(report.tensors.checked > 0);
+        assert!(report.thumbnails.checked > 0);
+
+        let progress = progress.into_inner().unwrap();
+        assert_eq!(progress.last(), Some(&(total, total)));
+        assert!(progress.windows(2).all(|w| w[0].0 < w[1].0));
+
+        Ok(())
+    }
 }
@@ -0,0 +1,412 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use dtm_lib::projects_db::{
+        add_a1111_parameters, build_description, build_drawthings_xmp,
+        dt_project::{data::tensor_history_node_data::TensorHistoryNodeData, DTProject, ThnFilter},
+        dtos::export::{MetadataPrivacy, TensorImageFormat},
+        dtos::image_metadata::{ImageFileFormat, LoraWeight, MetadataSource},
+        encode_high_bit_depth,
+        foreign_metadata::{
+            parse_a1111_parameters, parse_comfyui_prompt, parse_comfyui_workflow,
+            parse_invokeai_metadata,
+        },
+        image_metadata::read_image_metadata,
+        strip_image_metadata, write_jpeg_with_metadata, write_png_with_usercomment,
+        DrawThingsMetadata,
+    };
+
+    async fn node_metadata() -> (TensorHistoryNodeData, DrawThingsMetadata) {
+        let dt_project = DTProject::open("test_data/projects/test-project-a2.sqlite3")
+            .await
+            .unwrap();
+        let node = dt_project
+            .get_tensor_history_nodes(Some(ThnFilter::SkipAndTake(0, 1)), None)
+            .await
+            .unwrap()
+            .remove(0);
+        let node_data = node.node_data();
+        let metadata = DrawThingsMetadata::try_from(&node_data).unwrap();
+        (node_data, metadata)
+    }
+
+    fn assert_metadata(actual: &Option<DrawThingsMetadata>, expected: &DrawThingsMetadata) {
+        let actual = actual.as_ref().expect("metadata should be found");
+        assert_eq!(actual.c, expected.c);
+        assert_eq!(actual.uc, expected.uc);
+        assert_eq!(actual.seed, expected.seed);
+        assert_eq!(actual.v2.model, expected.v2.model);
+    }
+
+    #[tokio::test]
+    async fn read_png_metadata() {
+        let (node_data, expected) = node_metadata().await;
+        let pixels = vec![128u8; 64 * 64 * 3];
+        let png = write_png_with_usercomment(&pixels, 64, 64, 3, Some(node_data)).unwrap();
+
+        let result = read_image_metadata(&png).unwrap();
+        assert_eq!(result.format, ImageFileFormat::Png);
+        assert!(!result.chunks.is_empty());
+        assert_metadata(&result.metadata, &expected);
+    }
+
+    #[tokio::test]
+    async fn read_jpeg_metadata() {
+        let (node_data, expected) = node_metadata().await;
+        let mut jpg = Vec::new();
+        image::RgbImage::from_raw(64, 64, vec![128u8; 64 * 64 * 3])
+            .unwrap()
+            .write_to(&mut Cursor::new(&mut jpg), image::ImageFormat::Jpeg)
+            .unwrap();
+        let jpg = write_jpeg_with_metadata(&jpg, &node_data).unwrap();
+
+        let result = read_image_metadata(&jpg).unwrap();
+        assert_eq!(result.format, ImageFileFormat::Jpeg);
+        assert!(result.chunks.iter().any(|c| c.kind == "XMP"));
+        assert_metadata(&result.metadata, &expected);
+    }
+
+    #[tokio::test]
+    async fn read_webp_metadata() {
+        let (_, expected) = node_metadata().await;
+        let json = serde_json::to_string(&expected).unwrap();
+        let xmp = build_drawthings_xmp(&json, &build_description(&expected));
+
+        // only the metadata chunk is needed, the image data isn't read
+        let mut chunk = b"XMP ".to_vec();
+        chunk.extend((xmp.len() as u32).to_le_bytes());
+        chunk.extend(xmp.as_bytes());
+        if xmp.len() % 2 == 1 {
+            chunk.push(0);
+        }
+        let mut webp = b"RIFF".to_vec();
+        webp.extend((chunk.len() as u32 + 4).to_le_bytes());
+        webp.extend(b"WEBP");
+        webp.extend(chunk);
+
+        let result = read_image_metadata(&webp).unwrap();
+        assert_eq!(result.format, ImageFileFormat::Webp);
+        assert_eq!(result.chunks.len(), 1);
+        assert_metadata(&result.metadata, &expected);
+    }
+
+    #[test]
+    fn read_unsupported_format() {
+        assert!(read_image_metadata(b"GIF89a").is_err());
+    }
+
+    #[test]
+    fn parse_a1111() {
+        let text = "a cat, <lora:fluffy:0.8> <lora:shiny>\nwearing a hat\nNegative prompt: blurry, dog\nSteps: 25, Sampler: DPM++ 2M, Schedule type: Karras, CFG scale: 6.5, Seed: 4294967300, Size: 832x1216, Model hash: abc123, Model: sd_xl_base_1.0, Lora hashes: \"fluffy: 1a2b, shiny: 3c4d\", Version: v1.10.1";
+        let params = parse_a1111_parameters(text).unwrap();
+
+        assert_eq!(params.source, MetadataSource::A1111);
+        assert_eq!(
+            params.prompt.as_deref(),
+            Some("a cat, <lora:fluffy:0.8> <lora:shiny>\nwearing a hat")
+        );
+        assert_eq!(params.negative_prompt.as_deref(), Some("blurry, dog"));
+        assert_eq!(params.steps, Some(25));
+        assert_eq!(params.sampler.as_deref(), Some("DPM++ 2M Karras"));
+        assert_eq!(params.cfg_scale, Some(6.5));
+        assert_eq!(params.seed, Some(4294967300));
+        assert_eq!((params.width, params.height), (Some(832), Some(1216)));
+        assert_eq!(params.model.as_deref(), Some("sd_xl_base_1.0"));
+        assert_eq!(
+            params.loras,
+            vec![
+                LoraWeight {
+                    name: "fluffy".to_string(),
+                    weight: 0.8
+                },
+                LoraWeight {
+                    name: "shiny".to_string(),
+                    weight: 1.0
+                },
+            ]
+        );
+
+        assert!(parse_a1111_parameters("just a prompt").is_none());
+    }
+
+    #[test]
+    fn parse_comfyui() {
+        let prompt = r#"{
+            "3": { "class_type": "KSampler", "inputs": {
+                "seed": 42, "steps": 20, "cfg": 8, "sampler_name": "euler", "scheduler": "karras",
+                "denoise": 1, "model": ["10", 0], "positive": ["6", 0], "negative": ["7", 0],
+                "latent_image": ["5", 0] } },
+            "4": { "class_type": "CheckpointLoaderSimple", "inputs": { "ckpt_name": "v1-5.safetensors" } },
+            "5": { "class_type": "EmptyLatentImage", "inputs": { "width": 512, "height": 768, "batch_size": 1 } },
+            "6": { "class_type": "CLIPTextEncode", "inputs": { "text": "a cat", "clip": ["10", 1] } },
+            "7": { "class_type": "CLIPTextEncode", "inputs": { "text": "a dog", "clip": ["10", 1] } },
+            "10": { "class_type": "LoraLoader", "inputs": { "lora_name": "fluffy.safetensors",
+                "strength_model": 0.7, "strength_clip": 1, "model": ["4", 0], "clip": ["4", 1] } }
+        }"#;
+        let params = parse_comfyui_prompt(prompt).unwrap();
+
+        assert_eq!(params.source, MetadataSource::ComfyUI);
+        assert_eq!(params.prompt.as_deref(), Some("a cat"));
+        assert_eq!(params.negative_prompt.as_deref(), Some("a dog"));
+        assert_eq!(params.seed, Some(42));
+        assert_eq!(params.steps, Some(20));
+        assert_eq!(params.cfg_scale, Some(8.0));
+        assert_eq!(params.sampler.as_deref(), Some("euler karras"));
+        assert_eq!((params.width, params.height), (Some(512), Some(768)));
+        assert_eq!(params.model.as_deref(), Some("v1-5.safetensors"));
+        assert_eq!(params.loras.len(), 1);
+        assert_eq!(params.loras[0].name, "fluffy.safetensors");
+
+        let workflow = r#"{
+            "nodes": [
+                { "id": 3, "type": "KSampler", "mode": 0,
+                  "inputs": [{ "name": "positive", "link": 4 }, { "name": "negative", "link": 6 },
+                             { "name": "latent_image", "link": 2 }],
+                  "widgets_values": [42, "fixed", 20, 8, "euler", "normal", 1] },
+                { "id": 4, "type": "CheckpointLoaderSimple", "mode": 0, "widgets_values": ["v1-5.safetensors"] },
+                { "id": 5, "type": "EmptyLatentImage", "mode": 0, "widgets_values": [512, 768, 1] },
+                { "id": 6, "type": "CLIPTextEncode", "mode": 0, "widgets_values": ["a cat"] },
+                { "id": 7, "type": "CLIPTextEncode", "mode": 0, "widgets_values": ["a dog"] }
+            ],
+            "links": [[2, 5, 0, 3, 3, "LATENT"], [4, 6, 0, 3, 1, "CONDITIONING"], [6, 7, 0, 3, 2, "CONDITIONING"]]
+        }"#;
+        let params = parse_comfyui_workflow(workflow).unwrap();
+
+        assert_eq!(params.prompt.as_deref(), Some("a cat"));
+        assert_eq!(params.negative_prompt.as_deref(), Some("a dog"));
+        assert_eq!(params.seed, Some(42));
+        assert_eq!(params.sampler.as_deref(), Some("euler"));
+        assert_eq!((params.width, params.height), (Some(512), Some(768)));
+        assert_eq!(params.model.as_deref(), Some("v1-5.safetensors"));
+    }
+
+    #[test]
+    fn parse_invokeai() {
+        let metadata = r#"{
+            "positive_prompt": "a cat", "negative_prompt": "a dog", "seed": 123, "steps": 30,
+            "cfg_scale": 7.5, "scheduler": "dpmpp_2m_k", "width": 1024, "height": 1024,
+            "model": { "key": "abc", "name": "Juggernaut XL", "base": "sdxl", "type": "main" },
+            "loras": [{ "model": { "key": "def", "name": "fluffy" }, "weight": 0.75 }]
+        }"#;
+        let params = parse_invokeai_metadata(metadata).unwrap();
+
+        assert_eq!(params.source, MetadataSource::InvokeAI);
+        assert_eq!(params.prompt.as_deref(), Some("a cat"));
+        assert_eq!(params.negative_prompt.as_deref(), Some("a dog"));
+        assert_eq!(params.seed, Some(123));
+        assert_eq!(params.sampler.as_deref(), Some("dpmpp_2m_k"));
+        assert_eq!(params.model.as_deref(), Some("Juggernaut XL"));
+        assert_eq!(params.loras[0].name, "fluffy");
+        assert_eq!(params.loras[0].weight, 0.75);
+    }
+
+    #[tokio::test]
+    async fn write_a1111_parameters() {
+        let (node_data, expected) = node_metadata().await;
+        let pixels = vec![128u8; 64 * 64 * 3];
+        let png = write_png_with_usercomment(&pixels, 64, 64, 3, Some(node_data.clone())).unwrap();
+        let mut jpg = Vec::new();
+        image::RgbImage::from_raw(64, 64, pixels)
+            .unwrap()
+            .write_to(&mut Cursor::new(&mut jpg), image::ImageFormat::Jpeg)
+            .unwrap();
+        let jpg = write_jpeg_with_metadata(&jpg, &node_data).unwrap();
+
+        for image in [png, jpg] {
+            let image = add_a1111_parameters(&image, &node_data).unwrap();
+            let result = read_image_metadata(&image).unwrap();
+
+            // the draw things metadata is still preferred
+            assert_metadata(&result.metadata, &expected);
+
+            let parameters = result
+                .chunks
+                .iter()
+                .find(|c| c.keyword.as_deref() == Some("parameters") || c.kind == "UserComment")
+                .expect("parameters should be written");
+            let params = parse_a1111_parameters(&parameters.text).unwrap();
+            // lora tags are appended to the prompt
+            assert!(params.prompt.unwrap().starts_with(expected.c.trim()));
+            assert_eq!(params.seed, Some(expected.seed as u64));
+            assert_eq!(params.steps, Some(expected.steps));
+            assert_eq!(
+                params.width.zip(params.height),
+                Some((expected.v2.width, expected.v2.height))
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn write_a1111_parameters_keeps_exif() {
+        use img_parts::{
+            jpeg::{markers, Jpeg, JpegSegment},
+            Bytes,
+        };
+
+        let (node_data, expected) = node_metadata().await;
+        let mut jpg = Vec::new();
+        image::RgbImage::from_raw(64, 48, vec![128u8; 64 * 48 * 3])
+            .unwrap()
+            .write_to(&mut Cursor::new(&mut jpg), image::ImageFormat::Jpeg)
+            .unwrap();
+
+        // little endian EXIF with the image size and a comment from another tool
+        let entry = |tag: u16, kind: u16, count: u32, value: u32| {
+            [
+                &tag.to_le_bytes()[..],
+                &kind.to_le_bytes(),
+                &count.to_le_bytes(),
+                &value.to_le_bytes(),
+            ]
+            .concat()
+        };
+        let mut exif = b"Exif\0\0II".to_vec();
+        exif.extend(42u16.to_le_bytes());
+        exif.extend(8u32.to_le_bytes());
+        exif.extend(1u16.to_le_bytes());
+        exif.extend(entry(0x8769, 4, 1, 26));
+        exif.extend(0u32.to_le_bytes());
+        exif.extend(3u16.to_le_bytes());
+        exif.extend(entry(0x9286, 7, 11, 68));
+        exif.extend(entry(0xa002, 4, 1, 64));
+        exif.extend(entry(0xa003, 4, 1, 48));
+        exif.extend(0u32.to_le_bytes());
+        exif.extend(b"ASCII\0\0\0old");
+
+        let mut jpeg = Jpeg::from_bytes(Bytes::from(jpg)).unwrap();
+        let segment = JpegSegment::new_with_contents(markers::APP1, Bytes::from(exif));
+        jpeg.segments_mut().insert(1, segment);
+        let jpg = add_a1111_parameters(&jpeg.encoder().bytes(), &node_data).unwrap();
+
+        let jpeg = Jpeg::from_bytes(Bytes::copy_from_slice(&jpg)).unwrap();
+        let exif_segments: Vec<_> = jpeg
+            .segments()
+            .iter()
+            .filter(|s| s.marker() == markers::APP1 && s.contents().starts_with(b"Exif\0\0"))
+            .collect();
+        assert_eq!(exif_segments.len(), 1);
+        let tiff = &exif_segments[0].contents()[6..];
+        let u16_at =
+            |offset: usize| u16::from_le_bytes(tiff[offset..offset + 2].try_into().unwrap());
+        let u32_at =
+            |offset: usize| u32::from_le_bytes(tiff[offset..offset + 4].try_into().unwrap());
+        let find = |ifd: usize, tag: u16| {
+            (0..u16_at(ifd) as usize)
+                .map(|i| ifd + 2 + i * 12)
+                .find(|&entry| u16_at(entry) == tag)
+                .map(|entry| u32_at(entry + 8))
+        };
+        let exif_ifd = find(u32_at(4) as usize, 0x8769).unwrap() as usize;
+        assert_eq!(find(exif_ifd, 0xa002), Some(64));
+        assert_eq!(find(exif_ifd, 0xa003), Some(48));
+
+        let result = read_image_metadata(&jpg).unwrap();
+        let comment = result
+            .chunks
+            .iter()
+            .find(|c| c.kind == "UserComment")
+            .expect("parameters should be written");
+        let params = parse_a1111_parameters(&comment.text).unwrap();
+        assert_eq!(params.seed, Some(expected.seed as u64));
+        assert!(image::load_from_memory(&jpg).is_ok());
+    }
+
+    #[tokio::test]
+    async fn write_high_bit_depth() {
+        let (node_data, expected) = node_metadata().await;
+        let data: Vec<f32> = (0..32 * 16).map(|i| i as f32 / 256.0 - 1.0).collect();
+
+        let png = encode_high_bit_depth(
+            &data,
+            32,
+            16,
+            1,
+            TensorImageFormat::Png16,
+            Some(node_data.clone()),
+        )
+        .unwrap();
+        // ihdr bit depth
+        assert_eq!(png[24], 16);
+        let result = read_image_metadata(&png).unwrap();
+        assert_metadata(&result.metadata, &expected);
+        let image = image::load_from_memory(&png).unwrap().to_luma16();
+        assert_eq!(image.get_pixel(0, 0).0, [0]);
+        assert_eq!(image.get_pixel(0, 8).0, [32768]);
+
+        for format in [TensorImageFormat::Tiff32, TensorImageFormat::Exr32] {
+            let bytes =
+                encode_high_bit_depth(&data, 32, 16, 1, format, Some(node_data.clone())).unwrap();
+            let image = image::load_from_memory(&bytes).unwrap().to_rgb32f();
+            assert_eq!(image.dimensions(), (32, 16));
+            assert_eq!(image.get_pixel(0, 8).0, [0.5, 0.5, 0.5]);
+        }
+
+        // the data must cover the image
+        assert!(encode_high_bit_depth(&data, 64, 64, 1, TensorImageFormat::Png16, None).is_err());
+    }
+
+    /// 4x2 float rgb images written by other tools, with r = x / 4, g = y / 2 and b = 0.25
+    #[test]
+    fn read_float_tiff_and_exr() {
+        let data: Vec<f32> = (0..2)
+            .flat_map(|y| (0..4).flat_map(move |x| [x as f32 / 4.0, y as f32 / 2.0, 0.25]))
+            // tensors are in [-1, 1]
+            .map(|v| v * 2.0 - 1.0)
+            .collect();
+
+        for (fixture, format) in [
+            ("tests/fixtures/float32_rgb.tiff", TensorImageFormat::Tiff32),
+            ("tests/fixtures/float32_rgb.exr", TensorImageFormat::Exr32),
+        ] {
+            let bytes = std::fs::read(fixture).unwrap();
+            let image = image::load_from_memory(&bytes).unwrap();
+            assert_eq!(image.color(), image::ColorType::Rgb32F, "{}", fixture);
+            let image = image.to_rgb32f();
+            assert_eq!(image.dimensions(), (4, 2));
+            assert_eq!(image.get_pixel(3, 0).0, [0.75, 0.0, 0.25]);
+            assert_eq!(image.get_pixel(1, 1).0, [0.25, 0.5, 0.25]);
+
+            // and the same pixels are exported from a tensor
+            let exported = encode_high_bit_depth(&data, 4, 2, 3, format, None).unwrap();
+            let exported = image::load_from_memory(&exported).unwrap().to_rgb32f();
+            assert_eq!(exported, image, "{}", fixture);
+        }
+    }
+
+    #[tokio::test]
+    async fn strip_and_redact_metadata() {
+        let (node_data, expected) = node_metadata().await;
+        let pixels = vec![128u8; 64 * 64 * 3];
+        let png = write_png_with_usercomment(&pixels, 64, 64, 3, Some(node_data.clone())).unwrap();
+        let png = add_a1111_parameters(&png, &node_data).unwrap();
+
+        let stripped = strip_image_metadata(&png).unwrap();
+        let result = read_image_metadata(&stripped).unwrap();
+        assert!(result.chunks.is_empty());
+        assert!(result.metadata.is_none());
+        assert!(image::load_from_memory(&stripped).is_ok());
+
+        let strip = MetadataPrivacy {
+            strip: true,
+            ..Default::default()
+        };
+        assert!(strip.apply(node_data.clone()).is_none());
+
+        let redact = MetadataPrivacy {
+            redact_prompts: true,
+            redact_models: true,
+            ..Default::default()
+        };
+        let redacted = redact.apply(node_data).unwrap();
+        let png = write_png_with_usercomment(&pixels, 64, 64, 3, Some(redacted)).unwrap();
+        let metadata = read_image_metadata(&png).unwrap().metadata.unwrap();
+        assert_eq!(metadata.c, "");
+        assert_eq!(metadata.uc, "");
+        assert_eq!(metadata.v2.model, "");
+        assert_eq!(metadata.seed, expected.seed);
+        assert_eq!(metadata.steps, expected.steps);
+    }
+}
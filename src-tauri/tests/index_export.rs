@@ -0,0 +1,119 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use dtm_lib::{
+        dtp_service::export::IndexExportOptions, projects_db::dtos::index::IndexExportFormat,
+    };
+    use serde_json::Value;
+    use sqlx::{Connection, Row, SqliteConnection};
+    use tempfile::TempDir;
+
+    use crate::common::*;
+
+    fn options(output_folder: &str, format: IndexExportFormat) -> IndexExportOptions {
+        IndexExportOptions {
+            output_folder: output_folder.to_string(),
+            format,
+            project_ids: None,
+            search: None,
+            filters: None,
+            show_video: None,
+            show_image: None,
+            show_disconnected: None,
+        }
+    }
+
+    async fn image_total(dtps: &dtm_lib::dtp_service::DTPService) -> u64 {
+        dtps.list_images(
+            None, None, None, None, None, None, None, None, None, None, None,
+        )
+        .await
+        .unwrap()
+        .total
+    }
+
+    #[tokio::test]
+    async fn export_index_jsonl() {
+        let (dtps, _event_helper, _wfh, _db_path) = test_fixture(false, true).await;
+        let out = TempDir::new_in("test_data/temp").unwrap();
+        let out_path = out.path().to_str().unwrap();
+
+        let total = image_total(&dtps).await;
+        let path = dtps
+            .export_index(options(out_path, IndexExportFormat::Jsonl))
+            .await
+            .unwrap();
+        assert!(path.ends_with("dtm-index.jsonl"));
+
+        let content = fs::read_to_string(&path).unwrap();
+        let rows: Vec<Value> = content
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(rows.len() as u64, total);
+        assert!(rows[0]["prompt"].is_string());
+        assert!(rows[0]["loras"].is_array());
+        // sizes are in pixels, not 64px blocks
+        for row in &rows {
+            let width = row["width"].as_i64().unwrap();
+            let height = row["height"].as_i64().unwrap();
+            assert!(width >= 64 && width % 64 == 0, "width {}", width);
+            assert!(height >= 64 && height % 64 == 0, "height {}", height);
+        }
+
+        // a second export doesn't overwrite the first
+        let second = dtps
+            .export_index(options(out_path, IndexExportFormat::Jsonl))
+            .await
+            .unwrap();
+        assert!(second.ends_with("dtm-index_1.jsonl"));
+        assert_eq!(fs::read_to_string(&path).unwrap(), content);
+        assert_eq!(fs::read_to_string(&second).unwrap(), content);
+        assert_eq!(fs::read_dir(out.path()).unwrap().count(), 2);
+
+        let csv = dtps
+            .export_index(options(out_path, IndexExportFormat::Csv))
+            .await
+            .unwrap();
+        assert!(csv.ends_with("dtm-index.csv"));
+        let header = fs::read_to_string(&csv).unwrap();
+        assert!(header.starts_with("id,project_id,project_path,"));
+
+        dtps.stop().await;
+    }
+
+    #[tokio::test]
+    async fn export_index_sqlite() {
+        let (dtps, _event_helper, _wfh, _db_path) = test_fixture(false, true).await;
+        let out = TempDir::new_in("test_data/temp").unwrap();
+        let out_path = out.path().to_str().unwrap();
+
+        let total = image_total(&dtps).await;
+        let path = dtps
+            .export_index(options(out_path, IndexExportFormat::Sqlite))
+            .await
+            .unwrap();
+
+        let mut conn = SqliteConnection::connect(&format!("sqlite:{}?mode=ro", path))
+            .await
+            .unwrap();
+        let row = sqlx::query("SELECT COUNT(*) FROM images")
+            .fetch_one(&mut conn)
+            .await
+            .unwrap();
+        let count: i64 = row.get(0);
+        assert_eq!(count as u64, total);
+
+        let row = sqlx::query("SELECT MIN(width), MIN(height) FROM images")
+            .fetch_one(&mut conn)
+            .await
+            .unwrap();
+        let (width, height): (i64, i64) = (row.get(0), row.get(1));
+        assert!(width >= 64 && height >= 64);
+
+        dtps.stop().await;
+    }
+}
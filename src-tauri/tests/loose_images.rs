@@ -0,0 +1,133 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, io::Cursor, path::Path};
+
+    use dtm_lib::projects_db::{
+        dt_project::{DTProject, ThnFilter},
+        write_jpeg_with_metadata, write_png_with_usercomment,
+    };
+
+    use crate::common::*;
+
+    /// Writes a png and a jpg with the metadata of a node from a test project
+    async fn write_images(folder: &str) -> String {
+        let dt_project = DTProject::open("test_data/projects/test-project-a2.sqlite3")
+            .await
+            .unwrap();
+        let node = dt_project
+            .get_tensor_history_nodes(Some(ThnFilter::SkipAndTake(0, 1)), None)
+            .await
+            .unwrap()
+            .remove(0);
+        let node_data = node.node_data();
+
+        let pixels = vec![128u8; 128 * 128 * 3];
+        let png =
+            write_png_with_usercomment(&pixels, 128, 128, 3, Some(node_data.clone())).unwrap();
+        fs::write(Path::new(folder).join("image.png"), png).unwrap();
+
+        let mut jpg = Vec::new();
+        image::RgbImage::from_raw(128, 128, pixels)
+            .unwrap()
+            .write_to(&mut Cursor::new(&mut jpg), image::ImageFormat::Jpeg)
+            .unwrap();
+        let jpg = write_jpeg_with_metadata(&jpg, &node_data).unwrap();
+        fs::write(Path::new(folder).join("image.jpg"), jpg).unwrap();
+
+        node.prompt().unwrap_or("").trim().to_string()
+    }
+
+    #[tokio::test]
+    async fn index_loose_images() {
+        let (dtps, event_helper, wfh, _) = test_fixture(false, false).await;
+        let prompt = write_images(&wfh.watchfolder_path).await;
+        // project files are ignored in a loose images folder
+        wfh.copy_all();
+
+        dtps.add_loose_images_folder(wfh.watchfolder_path.clone(), wfh.bookmark.clone())
+            .await
+            .unwrap();
+
+        event_helper.assert_count("folder_sync_complete", 1).await;
+        event_helper.assert_count("project_updated", 1).await;
+        // the folder is indexed as a single project
+        let projects = dtps.list_projects(None).await.unwrap();
+        assert_eq!(projects.len(), 1);
+        assert_eq!(projects[0].image_count, Some(2));
+
+        let images = dtps
+            .list_images(
+                None, None, None, None, None, None, None, None, None, None, None,
+            )
+            .await
+            .unwrap()
+            .images
+            .unwrap();
+        assert_eq!(images.len(), 2);
+        for image in &images {
+            assert_eq!(image.prompt, prompt);
+            let metadata = dtps.get_metadata(image.id).await.unwrap();
+            assert_eq!(metadata.c, prompt);
+        }
+        event_helper.reset_counts();
+
+        // removing a file removes its image, and the unchanged file isn't indexed again
+        fs::remove_file(Path::new(&wfh.watchfolder_path).join("image.jpg")).unwrap();
+        let _ = dtps.sync().await;
+
+        event_helper.assert_count("project_updated", 1).await;
+        let projects = dtps.list_projects(None).await.unwrap();
+        assert_eq!(projects.len(), 1);
+        assert_eq!(projects[0].image_count, Some(1));
+
+        let remaining = dtps
+            .list_images(
+                None, None, None, None, None, None, None, None, None, None, None,
+            )
+            .await
+            .unwrap()
+            .images
+            .unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert!(images.iter().any(|image| image.id == remaining[0].id));
+
+        dtps.stop().await;
+    }
+
+    #[tokio::test]
+    async fn index_image_without_metadata() {
+        let (dtps, event_helper, wfh, _) = test_fixture(false, false).await;
+        let mut png = Vec::new();
+        image::RgbImage::from_raw(128, 64, vec![128u8; 128 * 64 * 3])
+            .unwrap()
+            .write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        fs::write(Path::new(&wfh.watchfolder_path).join("plain.png"), png).unwrap();
+
+        dtps.add_loose_images_folder(wfh.watchfolder_path.clone(), wfh.bookmark.clone())
+            .await
+            .unwrap();
+
+        event_helper.assert_count("folder_sync_complete", 1).await;
+        let images = dtps
+            .list_images(
+                None, None, None, None, None, None, None, None, None, None, None,
+            )
+            .await
+            .unwrap()
+            .images
+            .unwrap();
+        assert_eq!(images.len(), 1);
+        // settings that can't be read aren't made up
+        let image = &images[0];
+        assert_eq!(image.prompt, "");
+        assert_eq!((image.start_width, image.start_height), (2, 1));
+        assert_eq!(image.strength, None);
+        assert_eq!(image.shift, None);
+        assert_eq!(image.refiner_start, None);
+
+        dtps.stop().await;
+    }
+}
@@ -0,0 +1,89 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use dtm_lib::projects_db::{
+        dt_project::{DTProject, ThnFilter},
+        dtos::metadata_diff::DiffOp,
+        metadata_diff::diff_metadata,
+        DrawThingsMetadata,
+    };
+
+    use crate::common::*;
+
+    #[tokio::test]
+    async fn diff_images() {
+        let (dtps, _event_helper, _wfh, _db_path) = test_fixture(false, true).await;
+
+        let images = dtps
+            .list_images(
+                None, None, None, None, None, None, None, None, None, None, None,
+            )
+            .await
+            .unwrap()
+            .images
+            .unwrap();
+        let (a, b) = (&images[0], &images[1]);
+
+        let same = dtps.diff_metadata(a.id, a.id).await.unwrap();
+        assert!(same.fields.iter().all(|f| !f.changed));
+        assert!(same.prompt.iter().all(|w| w.op == DiffOp::Equal));
+        assert!(same.loras.iter().all(|l| l.op == DiffOp::Equal));
+
+        let diff = dtps.diff_metadata(a.id, b.id).await.unwrap();
+        assert_eq!(diff.fields.len(), same.fields.len());
+        let metadata_a = dtps.get_metadata(a.id).await.unwrap();
+        let metadata_b = dtps.get_metadata(b.id).await.unwrap();
+        let seed = diff.fields.iter().find(|f| f.field == "seed").unwrap();
+        assert_eq!(seed.changed, metadata_a.seed != metadata_b.seed);
+
+        dtps.stop().await;
+    }
+
+    #[tokio::test]
+    async fn diff_prompt_and_loras() {
+        let dt_project = DTProject::open("test_data/projects/test-project-a2.sqlite3")
+            .await
+            .unwrap();
+        let node_data = dt_project
+            .get_tensor_history_nodes(Some(ThnFilter::SkipAndTake(0, 1)), None)
+            .await
+            .unwrap()
+            .remove(0)
+            .node_data();
+
+        let mut node_a = node_data.clone();
+        node_a.text_prompt = Some("a red car on a street".to_string());
+        node_a.seed = 1;
+        let mut node_b = node_data;
+        node_b.text_prompt = Some("a blue car on a wet street".to_string());
+        node_b.seed = 2;
+
+        let a = DrawThingsMetadata::try_from(&node_a).unwrap();
+        let b = DrawThingsMetadata::try_from(&node_b).unwrap();
+        let diff = diff_metadata(&a, &b);
+
+        let seed = diff.fields.iter().find(|f| f.field == "seed").unwrap();
+        assert!(seed.changed);
+        assert_eq!((seed.a.as_u64(), seed.b.as_u64()), (Some(1), Some(2)));
+        let steps = diff.fields.iter().find(|f| f.field == "steps").unwrap();
+        assert!(!steps.changed);
+
+        let prompt: Vec<(DiffOp, &str)> = diff
+            .prompt
+            .iter()
+            .map(|w| (w.op, w.text.as_str()))
+            .collect();
+        assert_eq!(
+            prompt,
+            vec![
+                (DiffOp::Equal, "a"),
+                (DiffOp::Removed, "red"),
+                (DiffOp::Added, "blue"),
+                (DiffOp::Equal, "car on a"),
+                (DiffOp::Added, "wet"),
+                (DiffOp::Equal, "street"),
+            ]
+        );
+    }
+}
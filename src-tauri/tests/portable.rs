@@ -0,0 +1,63 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+
+    use std::{fs, path::Path};
+
+    use dtm_lib::dtp_service::{AppHandleWrapper, DTPService};
+    use tempfile::TempDir;
+
+    use crate::common::{
+        projects::{WatchFolderHelper, Watchfolder},
+        *,
+    };
+
+    #[tokio::test]
+    async fn portable_library_survives_move() {
+        let temp_dir = TempDir::new_in("test_data/temp").unwrap();
+        let wfh = WatchFolderHelper::get(Watchfolder::A, temp_dir);
+        wfh.copy_all();
+
+        let dtps = DTPService::new(AppHandleWrapper::new(None));
+        let (event_helper, channel) = EventHelper::new();
+        dtps.connect_portable(channel, false, wfh.watchfolder_path.clone())
+            .await
+            .unwrap();
+
+        event_helper.assert_count("folder_sync_complete", 1).await;
+        event_helper.assert_count("project_added", 2).await;
+        assert!(Path::new(&wfh.watchfolder_path)
+            .join("dtm-library.db")
+            .exists());
+
+        let folders = dtps.list_watch_folders().await.unwrap();
+        assert_eq!(folders.len(), 1);
+        assert_eq!(folders[0].path, wfh.watchfolder_path);
+        dtps.stop().await;
+
+        // move the whole library, as if the drive was mounted somewhere else
+        let moved_path = format!("{}_moved", wfh.watchfolder_path);
+        fs::rename(&wfh.watchfolder_path, &moved_path).unwrap();
+
+        let (event_helper, channel) = EventHelper::new();
+        dtps.connect_portable(channel, false, moved_path.clone())
+            .await
+            .unwrap();
+
+        event_helper.assert_count("folder_sync_complete", 1).await;
+        event_helper.assert_count("project_added", 0).await;
+
+        let folders = dtps.list_watch_folders().await.unwrap();
+        assert_eq!(folders.len(), 1);
+        assert_eq!(folders[0].path, moved_path);
+
+        let projects = dtps.list_projects(None).await.unwrap();
+        assert_eq!(projects.len(), 2);
+        for project in projects {
+            assert!(project.full_path.starts_with(&moved_path));
+        }
+
+        dtps.stop().await;
+    }
+}
@@ -2,13 +2,20 @@ mod common;
 
 #[cfg(test)]
 mod tests {
-    use crate::common::util::assert_vec_eq;
+    use crate::common::{
+        clip::ClipFixture,
+        moodboard::{MoodboardFixture, MOODBOARD_SHUFFLE_ID, MOODBOARD_WEIGHT},
+        util::assert_vec_eq,
+    };
 
     use dtm_lib::{
         projects_db::{
+            dt_project::{ThnData, ThnFilter},
+            dtos::export::TensorFileFormat,
+            inpaint::InpaintViewOptions,
             DTProject, DtProjectRef, DtResourceHandle, DtResourceRef, ThnRef, ThnResource,
         },
-        ResourceHandle, TensorValue,
+        ResizeFit, ResizeOptions, ResourceHandle, TensorValue,
     };
 
     fn project_ref() -> DtProjectRef {
@@ -283,6 +290,127 @@ mod tests {
         assert!(result.is_none());
     }
 
+    #[tokio::test]
+    async fn test_node_reference_constructors() {
+        let canvas = project_ref().node(2).sub().unwrap().Canvas(0);
+        assert!(matches!(
+            canvas.resource,
+            DtResourceRef::TensorHistoryNode(ThnRef::RowId(2), ThnResource::Canvas(0))
+        ));
+        assert!(canvas.get_tensor().await.unwrap().is_some());
+        assert_eq!(canvas.get_moodboard_weight().await.unwrap(), None);
+
+        let mask = project_ref().node(2).sub().unwrap().Mask(0);
+        assert!(matches!(
+            mask.resource,
+            DtResourceRef::TensorHistoryNode(ThnRef::RowId(2), ThnResource::Mask(0))
+        ));
+
+        // moodboard images resolve to their tensor, and the node carries their weight
+        let fixture = MoodboardFixture::new().await;
+        let fixture_ref = DtProjectRef::from(fixture.path.as_str());
+        let dtp = DTProject::open(&fixture.path).await.unwrap();
+        let node = dtp
+            .get_tensor_history_nodes(
+                Some(ThnFilter::Rowid(fixture.node_id)),
+                Some(ThnData::moodboard()),
+            )
+            .await
+            .unwrap()
+            .into_iter()
+            .next()
+            .expect("fixture node");
+        let entry = node
+            .moodboard
+            .as_ref()
+            .and_then(|m| m.first())
+            .expect("fixture node has a moodboard");
+        assert_eq!(
+            entry.tensor_name,
+            format!("shuffle_{}", MOODBOARD_SHUFFLE_ID)
+        );
+        let payload = serde_json::to_value(&node).unwrap();
+        assert_eq!(
+            payload["moodboard"][0]["weight"].as_f64(),
+            Some(MOODBOARD_WEIGHT as f64)
+        );
+
+        let moodboard = fixture_ref
+            .node(fixture.node_id)
+            .sub()
+            .unwrap()
+            .Moodboard(entry.idx as usize);
+        assert_eq!(
+            moodboard.get_moodboard_weight().await.unwrap(),
+            Some(MOODBOARD_WEIGHT)
+        );
+        assert!(moodboard.get_tensor().await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_frames() {
+        let thumb = DtResourceHandle::new(project_ref(), DtResourceRef::Thumb(209719244));
+        assert!(thumb.get_frames(true).await.unwrap().is_none());
+
+        let dtp = DTProject::open("test_data/projects/test-project-a2.sqlite3")
+            .await
+            .unwrap();
+        let nodes = dtp.get_tensor_history_nodes(None, None).await.unwrap();
+
+        let image = nodes
+            .iter()
+            .find(|node| node.data().clip_id() <= 0)
+            .expect("test project should have a still image");
+        let handle = project_ref().node(image.rowid);
+        assert!(handle.get_frames(true).await.unwrap().is_none());
+
+        // the test project has no video, so a clip is made from some of its images
+        let clip = ClipFixture::new(3).await;
+        let handle = DtProjectRef::from(clip.path.as_str()).node(clip.node_id);
+
+        let previews = handle
+            .get_frames(true)
+            .await
+            .unwrap()
+            .expect("clip should have frames");
+        let tensors = handle.get_frames(false).await.unwrap().unwrap();
+        assert_eq!(previews.len(), clip.num_frames as usize);
+        assert_eq!(tensors.len(), clip.num_frames as usize);
+
+        let preview = previews[0].get_preview(false).await.unwrap().unwrap();
+        assert_eq!(&preview[0..3], &[0xFF, 0xD8, 0xFF]);
+        let lossless = tensors[0].get_lossless(None).await.unwrap().unwrap();
+        assert_eq!(
+            &lossless[0..8],
+            &[0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pose_png() {
+        // only pose tensors are rendered
+        let canvas = project_ref().node(2).sub().unwrap().Canvas(0);
+        assert!(canvas.get_pose_png(false).await.unwrap().is_none());
+
+        let dtp = DTProject::open("test_data/projects/test-project-a2.sqlite3")
+            .await
+            .unwrap();
+        let nodes = dtp
+            .get_tensor_history_nodes(None, Some(ThnData::tensordata()))
+            .await
+            .unwrap();
+        let with_pose = nodes.iter().find(|node| {
+            node.tensordata
+                .as_ref()
+                .is_some_and(|td| td.iter().any(|t| t.data().pose_id() > 0))
+        });
+        if let Some(node) = with_pose {
+            let pose = project_ref().node(node.rowid).sub().unwrap().Pose();
+            let png = pose.get_pose_png(true).await.unwrap().unwrap();
+            assert_eq!(&png[0..8], &[0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A]);
+        }
+    }
+
     /*
      * get_lossless() tests
      */
@@ -328,6 +456,145 @@ mod tests {
         assert_eq!(&lossless[0..8], &[0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A]);
     }
 
+    #[tokio::test]
+    async fn test_lossless_resized() {
+        let resource_handle = DtResourceHandle::new(
+            project_ref(),
+            DtResourceRef::Tensor("tensor_history_265054268".to_string()),
+        );
+        let tensor = resource_handle.get_tensor().await.unwrap().unwrap();
+
+        // contain keeps the aspect ratio of the tensor
+        let contain = ResizeOptions::square(64);
+        let (width, height) = contain.output_size(tensor.width, tensor.height);
+        assert_eq!(width.max(height), 64);
+        let png = resource_handle.get_lossless(Some(contain)).await.unwrap().unwrap();
+        let image = image::load_from_memory(&png).unwrap();
+        assert_eq!((image.width(), image.height()), (width, height));
+
+        let cover = ResizeOptions {
+            width: 48,
+            height: 32,
+            fit: ResizeFit::Cover,
+            ..contain
+        };
+        let png = resource_handle.get_lossless(Some(cover)).await.unwrap().unwrap();
+        let image = image::load_from_memory(&png).unwrap();
+        assert_eq!((image.width(), image.height()), (48, 32));
+    }
+
+    #[tokio::test]
+    async fn test_composite() {
+        let dtp = DTProject::get("test_data/projects/test-project-a2.sqlite3")
+            .await
+            .unwrap();
+        let placements = dtp.get_canvas_placements(2).await.unwrap();
+        // the node's own canvas layers are drawn, each tensor once
+        assert!(placements.iter().any(|p| p.node_id == 2));
+        let mut names: Vec<&str> = placements.iter().map(|p| p.tensor_name.as_str()).collect();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), placements.len());
+
+        let node = project_ref().node(2);
+        let png = node.get_composite_png(None).await.unwrap().unwrap();
+        let image = image::load_from_memory(&png).unwrap();
+        assert!(image.width() > 0 && image.height() > 0);
+        assert!(image.color().has_alpha());
+
+        let png = node
+            .get_composite_png(Some(ResizeOptions::square(64)))
+            .await
+            .unwrap()
+            .unwrap();
+        let resized = image::load_from_memory(&png).unwrap();
+        assert_eq!(resized.width().max(resized.height()), 64);
+    }
+
+    #[tokio::test]
+    async fn test_inpaint_view() {
+        let dtp = DTProject::get("test_data/projects/test-project-a2.sqlite3")
+            .await
+            .unwrap();
+        let nodes = dtp
+            .get_tensor_history_nodes(None, Some(ThnData::tensordata()))
+            .await
+            .unwrap();
+        let has_mask = |node: &&dtm_lib::projects_db::dt_project::TensorHistoryNode| {
+            node.data().tensor_id() > 0 && node.has_mask()
+        };
+
+        // nodes without a mask have no inpaint view
+        let plain = nodes.iter().find(|node| !has_mask(node)).unwrap();
+        let view = project_ref()
+            .node(plain.rowid)
+            .get_inpaint_png(InpaintViewOptions::default(), None)
+            .await
+            .unwrap();
+        assert!(view.is_none());
+
+        let Some(masked) = nodes.iter().find(has_mask) else {
+            return;
+        };
+        let node = project_ref().node(masked.rowid);
+        let Some(png) = node
+            .get_inpaint_png(InpaintViewOptions::default(), None)
+            .await
+            .unwrap()
+        else {
+            return;
+        };
+        // three panels side by side
+        let image = image::load_from_memory(&png).unwrap();
+        assert!(image.width() > image.height());
+
+        let png = node
+            .get_inpaint_png(InpaintViewOptions::default(), Some(ResizeOptions::square(128)))
+            .await
+            .unwrap()
+            .unwrap();
+        let resized = image::load_from_memory(&png).unwrap();
+        assert_eq!(resized.width(), 128);
+    }
+
+    #[tokio::test]
+    async fn test_tensor_file() {
+        let resource_handle = DtResourceHandle::new(
+            project_ref(),
+            DtResourceRef::Tensor("tensor_history_265054268".to_string()),
+        );
+        let tensor = resource_handle.get_tensor().await.unwrap().unwrap();
+        let shape = [tensor.n, tensor.height, tensor.width, tensor.channels];
+
+        let (name, npy) = resource_handle
+            .get_tensor_file(TensorFileFormat::Npy)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(name, "tensor_history_265054268");
+        assert_eq!(&npy[..6], b"\x93NUMPY");
+        let header_len = u16::from_le_bytes([npy[8], npy[9]]) as usize;
+        let header = std::str::from_utf8(&npy[10..10 + header_len]).unwrap();
+        let expected = format!("'shape': ({}, {}, {}, {})", shape[0], shape[1], shape[2], shape[3]);
+        assert!(header.contains("'descr': '<f4'"));
+        assert!(header.contains(&expected));
+        assert_eq!(npy.len() - 10 - header_len, shape.iter().product::<u32>() as usize * 4);
+
+        let (_, file) = resource_handle
+            .get_tensor_file(TensorFileFormat::Safetensors)
+            .await
+            .unwrap()
+            .unwrap();
+        let header_len = u64::from_le_bytes(file[..8].try_into().unwrap()) as usize;
+        let header: serde_json::Value = serde_json::from_slice(&file[8..8 + header_len]).unwrap();
+        assert_eq!(header[&name]["dtype"], "F32");
+        assert_eq!(header[&name]["shape"], serde_json::json!(shape));
+
+        // thumbnails have no tensor
+        let thumb = DtResourceHandle::new(project_ref(), DtResourceRef::Thumb(209719244));
+        assert!(thumb.get_tensor_file(TensorFileFormat::Npy).await.unwrap().is_none());
+    }
+
     #[tokio::test]
     async fn test_lossless_from_thumb() {
         let resource_handle = DtResourceHandle::new(project_ref(), DtResourceRef::Thumb(209719244));
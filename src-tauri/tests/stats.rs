@@ -0,0 +1,70 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use crate::common::*;
+
+    #[tokio::test]
+    async fn library_stats_follow_search() {
+        let (dtps, _event_helper, _wfh, _db_path) = test_fixture(false, true).await;
+
+        let all = dtps
+            .library_stats(None, None, None, None, None, None, None)
+            .await
+            .unwrap();
+        assert!(all.total > 0);
+        assert_eq!(all.image_count + all.video_count, all.total);
+        assert_eq!(all.per_day.iter().map(|d| d.count).sum::<i64>(), all.total);
+        assert_eq!(all.hours.iter().map(|h| h.count).sum::<i64>(), all.total);
+
+        // resolutions are in pixels, not 64px blocks
+        assert!(!all.resolutions.is_empty());
+        for r in &all.resolutions {
+            assert!(r.width >= 64 && r.width % 64 == 0, "width {}", r.width);
+            assert!(r.height >= 64 && r.height % 64 == 0, "height {}", r.height);
+        }
+        for usage in &all.resolutions_by_week {
+            let (width, _) = usage.key.split_once('x').unwrap();
+            assert!(width.parse::<i32>().unwrap() >= 64, "key {}", usage.key);
+        }
+
+        let listed = dtps
+            .list_images(
+                None,
+                Some("skyscraper".to_string()),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        let searched = dtps
+            .library_stats(
+                None,
+                Some("skyscraper".to_string()),
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(searched.total as u64, listed.total);
+        assert!(searched.total <= all.total);
+
+        let none = dtps
+            .library_stats(None, None, None, Some(false), Some(false), None, None)
+            .await
+            .unwrap();
+        assert_eq!(none.total, 0);
+
+        dtps.stop().await;
+    }
+}
@@ -3,6 +3,8 @@ mod common;
 #[cfg(test)]
 mod tests {
 
+    use std::fs;
+
     use crate::common::*;
 
     #[tokio::test]
@@ -98,4 +100,133 @@ mod tests {
 
         dtps.stop().await;
     }
+
+    #[tokio::test]
+    async fn sync_renamed_project() {
+        let (dtps, event_helper, wfh, _) = test_fixture(false, false).await;
+
+        wfh.copy_all();
+        dtps.add_watchfolder(wfh.watchfolder_path.clone(), wfh.bookmark.clone())
+            .await
+            .unwrap();
+
+        event_helper.assert_count("folder_sync_complete", 1).await;
+        event_helper.assert_count("project_updated", 2).await;
+        let before = dtps.list_projects(None).await.unwrap();
+        let original = before
+            .iter()
+            .find(|p| p.path == wfh.projects[0].filename)
+            .unwrap()
+            .clone();
+        event_helper.reset_counts();
+
+        // rename one project
+        let renamed_path = format!("{}/renamed.sqlite3", wfh.watchfolder_path);
+        fs::rename(wfh.projects[0].get_dest_path(), &renamed_path).unwrap();
+        let _ = dtps.sync().await;
+
+        event_helper.assert_count("folder_sync_complete", 1).await;
+        event_helper.assert_count("project_updated", 1).await;
+        event_helper.assert_count("project_removed", 0).await;
+        event_helper.assert_count("project_added", 0).await;
+
+        let projects = dtps.list_projects(None).await.unwrap();
+        assert_eq!(projects.len(), 2);
+        let renamed = projects.iter().find(|p| p.id == original.id).unwrap();
+        assert_eq!(renamed.path, "renamed.sqlite3");
+        assert_eq!(renamed.full_path, renamed_path);
+        assert_eq!(renamed.image_count, original.image_count);
+
+        dtps.stop().await;
+    }
+
+    #[tokio::test]
+    async fn sync_project_moved_between_folders() {
+        let (dtps, event_helper, wfh, _) = test_fixture(false, false).await;
+
+        let folder_b = wfh.temp_dir.path().join("watchfolder_b");
+        fs::create_dir_all(&folder_b).unwrap();
+        let folder_b = folder_b.to_str().unwrap().to_string();
+
+        wfh.copy_all();
+        dtps.add_watchfolder(wfh.watchfolder_path.clone(), wfh.bookmark.clone())
+            .await
+            .unwrap();
+        event_helper.assert_count("folder_sync_complete", 1).await;
+        event_helper.assert_count("project_updated", 2).await;
+
+        dtps.add_watchfolder(folder_b.clone(), format!("TESTBOOKMARK::{}", folder_b))
+            .await
+            .unwrap();
+        event_helper.assert_count("folder_sync_complete", 3).await;
+
+        let folders = dtps.list_watch_folders().await.unwrap();
+        let folder_b_id = folders.iter().find(|f| f.path == folder_b).unwrap().id;
+        let before = dtps.list_projects(None).await.unwrap();
+        let original = before
+            .iter()
+            .find(|p| p.path == wfh.projects[0].filename)
+            .unwrap()
+            .clone();
+        event_helper.reset_counts();
+
+        // move one project to the other folder
+        let moved_path = format!("{}/{}", folder_b, wfh.projects[0].filename);
+        fs::rename(wfh.projects[0].get_dest_path(), &moved_path).unwrap();
+        let _ = dtps.sync().await;
+
+        event_helper.assert_count("folder_sync_complete", 2).await;
+        event_helper.assert_count("project_updated", 1).await;
+        event_helper.assert_count("project_removed", 0).await;
+        event_helper.assert_count("project_added", 0).await;
+
+        let projects = dtps.list_projects(None).await.unwrap();
+        assert_eq!(projects.len(), 2);
+        let moved = projects.iter().find(|p| p.id == original.id).unwrap();
+        assert_eq!(moved.watchfolder_id, folder_b_id);
+        assert_eq!(moved.full_path, moved_path);
+        assert_eq!(moved.image_count, original.image_count);
+
+        dtps.stop().await;
+    }
+
+    #[tokio::test]
+    async fn sync_rechecks_compatibility() {
+        use sqlx::{Connection, SqliteConnection};
+
+        let (dtps, event_helper, wfh, _) = test_fixture(false, false).await;
+        wfh.copy_all();
+        dtps.add_watchfolder(wfh.watchfolder_path.clone(), wfh.bookmark.clone())
+            .await
+            .unwrap();
+        event_helper.assert_count("folder_sync_complete", 1).await;
+
+        let path = wfh.projects[0].get_dest_path();
+        let find_project = |projects: Vec<dtm_lib::projects_db::dtos::project::ProjectExtra>| {
+            projects.into_iter().find(|p| p.full_path == path).unwrap()
+        };
+        let project = find_project(dtps.list_projects(None).await.unwrap());
+        let report = project.compatibility.expect("project should be checked");
+        assert!(report.unknown_tables.is_empty());
+        event_helper.reset_counts();
+
+        // a newer Draw Things adds a table
+        let mut conn = SqliteConnection::connect(&format!("sqlite://{}", path))
+            .await
+            .unwrap();
+        sqlx::query("CREATE TABLE newfeature (rowid INTEGER PRIMARY KEY, p BLOB)")
+            .execute(&mut conn)
+            .await
+            .unwrap();
+        conn.close().await.unwrap();
+        let _ = dtps.sync().await;
+
+        event_helper.assert_count("project_updated", 1).await;
+        let project = find_project(dtps.list_projects(None).await.unwrap());
+        let report = project.compatibility.unwrap();
+        assert!(!report.compatible);
+        assert_eq!(report.unknown_tables, vec!["newfeature".to_string()]);
+
+        dtps.stop().await;
+    }
 }
@@ -0,0 +1,81 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use dtm_lib::{
+        projects_db::DTProject,
+        tensor_cache::{tensor_bytes, TensorCache, TENSOR_CACHE},
+        Tensor, TensorDType, TensorValue,
+    };
+
+    fn tensor(values: usize) -> Arc<Tensor> {
+        Arc::new(Tensor {
+            n: 1,
+            width: values as u32,
+            height: 1,
+            channels: 1,
+            dtype: TensorDType::F32,
+            data: TensorValue::F32(vec![0.0; values]),
+        })
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        // room for four tensors of 200 bytes
+        let cache = TensorCache::new(800);
+        assert_eq!(tensor_bytes(&tensor(50)), 200);
+
+        assert!(cache.insert("project", "a", tensor(50)));
+        assert!(cache.insert("project", "b", tensor(50)));
+        assert!(cache.get("project", "a").is_some());
+        assert!(cache.insert("project", "c", tensor(50)));
+        assert!(cache.insert("project", "d", tensor(50)));
+        assert!(cache.insert("project", "e", tensor(50)));
+
+        // b was least recently used
+        assert!(cache.get("project", "b").is_none());
+        assert!(cache.get("project", "a").is_some());
+        assert!(cache.get("other", "a").is_none());
+
+        let stats = cache.stats();
+        assert_eq!(stats.entries, 4);
+        assert_eq!(stats.bytes, 800);
+        assert_eq!(stats.evictions, 1);
+        assert_eq!((stats.hits, stats.misses), (2, 2));
+
+        // a and e are the most recently used
+        cache.set_budget(400);
+        assert_eq!(cache.stats().entries, 2);
+        assert!(cache.get("project", "e").is_some());
+        cache.invalidate_project("project");
+        assert_eq!(cache.stats().bytes, 0);
+    }
+
+    #[test]
+    fn skips_large_tensors() {
+        let cache = TensorCache::new(1000);
+        // more than a quarter of the budget
+        assert!(!cache.insert("project", "a", tensor(100)));
+        assert!(cache.get("project", "a").is_none());
+        assert_eq!(cache.stats().skipped, 1);
+    }
+
+    #[tokio::test]
+    async fn decoded_tensors_are_shared() {
+        let dt_project = DTProject::get("test_data/projects/test-project-a2.sqlite3")
+            .await
+            .unwrap();
+        let first = dt_project
+            .get_tensor_decoded("tensor_history_265054268")
+            .await
+            .unwrap();
+        let second = dt_project
+            .get_tensor_decoded("tensor_history_265054268")
+            .await
+            .unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+        assert!(TENSOR_CACHE.stats().hits > 0);
+    }
+}
@@ -0,0 +1,102 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use dtm_lib::projects_db::{
+        thumb_cache::{project_version, CacheKey},
+        ThumbCache,
+    };
+    use tempfile::TempDir;
+
+    fn key(project_id: i64, version: &str, item: &str) -> CacheKey {
+        CacheKey {
+            project_id,
+            version: version.to_string(),
+            item: item.to_string(),
+            content_type: "image/jpeg",
+        }
+    }
+
+    #[tokio::test]
+    async fn get_and_put() {
+        let temp_dir = TempDir::new_in("test_data/temp").unwrap();
+        let cache = ThumbCache::open(temp_dir.path(), 1024).unwrap();
+
+        let thumb = key(1, "a", "/thumb/1/100");
+        assert!(cache.get(&thumb).await.is_none());
+        cache.put(&thumb, &[1, 2, 3]).await.unwrap();
+        assert_eq!(cache.get(&thumb).await, Some(vec![1, 2, 3]));
+        assert_eq!(cache.size(), 3);
+
+        // the etag changes with the project version
+        assert_ne!(thumb.etag(), key(1, "b", "/thumb/1/100").etag());
+
+        // entries survive reopening the cache
+        drop(cache);
+        let cache = ThumbCache::open(temp_dir.path(), 1024).unwrap();
+        assert_eq!(cache.get(&thumb).await, Some(vec![1, 2, 3]));
+    }
+
+    #[tokio::test]
+    async fn evicts_least_recently_used() {
+        let temp_dir = TempDir::new_in("test_data/temp").unwrap();
+        let cache = ThumbCache::open(temp_dir.path(), 250).unwrap();
+
+        let (a, b, c) = (key(1, "a", "a"), key(1, "a", "b"), key(1, "a", "c"));
+        cache.put(&a, &[0; 100]).await.unwrap();
+        cache.put(&b, &[0; 100]).await.unwrap();
+        // a is now more recently used than b
+        assert!(cache.get(&a).await.is_some());
+        cache.put(&c, &[0; 100]).await.unwrap();
+
+        assert!(cache.get(&b).await.is_none());
+        assert!(cache.get(&a).await.is_some());
+        assert!(cache.get(&c).await.is_some());
+        assert_eq!(cache.size(), 200);
+
+        cache.set_budget(100).await;
+        assert_eq!(cache.size(), 100);
+        assert!(cache.get(&c).await.is_some());
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn invalidates_on_project_change() {
+        let temp_dir = TempDir::new_in("test_data/temp").unwrap();
+        let cache = ThumbCache::open(temp_dir.path(), 1024).unwrap();
+
+        let old = key(1, "a", "/thumb/1/100");
+        let other_project = key(2, "a", "/thumb/2/100");
+        cache.check_version(1, "a").await;
+        cache.put(&old, &[1]).await.unwrap();
+        cache.put(&other_project, &[2]).await.unwrap();
+
+        cache.check_version(1, "b").await;
+        assert!(cache.get(&old).await.is_none());
+        assert!(cache.get(&other_project).await.is_some());
+
+        cache.invalidate_project(2).await;
+        assert!(cache.get(&other_project).await.is_none());
+        assert_eq!(cache.size(), 0);
+    }
+
+    #[test]
+    fn project_version_ignores_writes() {
+        let temp_dir = TempDir::new_in("test_data/temp").unwrap();
+        let path = temp_dir.path().join("project.sqlite3");
+        let path = path.to_str().unwrap();
+        fs::write(path, [0; 10]).unwrap();
+
+        let version = project_version("fingerprint", path);
+        assert_eq!(version, project_version("fingerprint", path));
+        assert_ne!(version, project_version("other", path));
+        assert_ne!(version, project_version("fingerprint", "other.sqlite3"));
+
+        // new thumbnails get new ids, so writes keep the cached ones
+        fs::write(path, [0; 20]).unwrap();
+        fs::write(format!("{}-wal", path), [0; 10]).unwrap();
+        assert_eq!(version, project_version("fingerprint", path));
+    }
+}
@@ -0,0 +1,66 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use dtm_lib::{
+        projects_db::{
+            dtos::export::MetadataPrivacy, image_metadata::read_image_metadata, DtProjectRef,
+        },
+        vid_export::{export_clip_frames, FramesExportOpts},
+    };
+    use tempfile::TempDir;
+
+    use crate::common::clip::{ClipFixture, CLIP_PROMPT};
+
+    #[tokio::test]
+    async fn export_clip_frames_redacts_prompts() {
+        let clip = ClipFixture::new(3).await;
+        let handle = DtProjectRef::from(clip.path.as_str()).node(clip.node_id);
+
+        for use_tensor in [false, true] {
+            let output_dir = TempDir::new().unwrap();
+            let opts = FramesExportOpts {
+                image_id: 0,
+                output_dir: output_dir.path().to_str().unwrap().to_string(),
+                use_tensor,
+                filename_pattern: match use_tensor {
+                    true => "frame_###.png".to_string(),
+                    false => "frame_###.jpg".to_string(),
+                },
+                clip_number: None,
+                start_frame: None,
+                privacy: MetadataPrivacy {
+                    redact_prompts: true,
+                    ..Default::default()
+                },
+            };
+
+            let count = export_clip_frames(&handle, &opts, |_, _| {}).await.unwrap();
+            assert_eq!(count, clip.num_frames as usize);
+
+            let files: Vec<_> = fs::read_dir(output_dir.path())
+                .unwrap()
+                .map(|entry| entry.unwrap().path())
+                .collect();
+            assert_eq!(files.len(), count);
+
+            for file in files {
+                let bytes = fs::read(&file).unwrap();
+                let metadata = read_image_metadata(&bytes)
+                    .unwrap()
+                    .metadata
+                    .expect("frames keep the clip's metadata");
+                assert_eq!(metadata.c, "");
+                assert!(
+                    !bytes
+                        .windows(CLIP_PROMPT.len())
+                        .any(|w| w == CLIP_PROMPT.as_bytes()),
+                    "{:?} contains the prompt",
+                    file
+                );
+            }
+        }
+    }
+}
@@ -69,10 +69,62 @@ async function getTensorHistory(projectId: number, rowId: number) {
     return rows[0]
 }
 
+export type ProjectTableKind =
+    | "TensorHistoryNode"
+    | "TensorData"
+    | "TextHistory"
+    | "TextLineage"
+    | "TensorMoodboardData"
+    | "Tensors"
+    | "Thumbs"
+    | "Clip"
+
+export interface ProjectTable {
+    table: ProjectTableKind
+    name: string
+    present: boolean
+    rowCount: number
+}
+
+export interface ProjectTableRow {
+    rowid: number
+    columns: Record<string, unknown>
+    data?: Record<string, unknown> | null
+    dataError?: string | null
+}
+
+export interface ProjectTablePage {
+    table: ProjectTableKind
+    rowCount: number
+    skip: number
+    take: number
+    rows: ProjectTableRow[]
+}
+
+type ProjectOpts = {
+    projectId?: number
+    projectPath?: string
+}
+
+async function listTables(opts: ProjectOpts): Promise<ProjectTable[]> {
+    if (!opts.projectId && !opts.projectPath) throw new Error("projectId or projectPath is required")
+    return await invoke<ProjectTable[]>("dtp_dt_list_tables", { ...opts })
+}
+
+async function getTableRows(
+    table: ProjectTableKind,
+    opts: ProjectOpts & { skip?: number; take?: number },
+): Promise<ProjectTablePage> {
+    if (!opts.projectId && !opts.projectPath) throw new Error("projectId or projectPath is required")
+    return await invoke<ProjectTablePage>("dtp_dt_get_table_rows", { ...opts, table })
+}
+
 const DTProject = {
     listTensorHistoryNodes,
     tensorData,
     getTensorHistory,
+    listTables,
+    getTableRows,
 }
 
 export default DTProject
@@ -6,11 +6,16 @@ import type {
     ListImagesResult,
     Model,
     ModelType,
+    NodeLineage,
     ProjectExtra,
+    ProjectHealthReport,
+    PromptTimeline,
+    TensorCacheStats,
     TensorHistoryExtra,
     TensorSize,
     WatchFolder,
 } from "./DtpServiceTypes"
+import type { ResizeFilter, ResizeFit } from "./urls"
 
 type MaybeReadonly<T> = T | Readonly<T>
 
@@ -25,6 +30,24 @@ async function lockFolder(watchfolderId: number) {
     await invoke("dtp_lock_folder", { watchfolderId })
 }
 
+/** byte budget for the dtm:// thumbnail cache, 512MB by default */
+async function setThumbCacheBudget(bytes: number) {
+    await invoke("dtp_set_thumb_cache_budget", { bytes })
+}
+
+async function clearThumbCache() {
+    await invoke("dtp_clear_thumb_cache")
+}
+
+/** memory budget for decoded tensors, 512MB by default */
+async function setTensorCacheBudget(bytes: number) {
+    await invoke("dtp_set_tensor_cache_budget", { bytes })
+}
+
+async function getTensorCacheStats(): Promise<TensorCacheStats> {
+    return await invoke("dtp_get_tensor_cache_stats")
+}
+
 async function listProjects(watchfolderId?: number): Promise<ProjectExtra[]> {
     return await invoke("dtp_list_projects", { watchfolderId })
 }
@@ -100,12 +123,14 @@ async function decodeTensor(
     tensorId: string,
     asPng: boolean,
     nodeId?: number | null,
+    format?: TensorImageFormat,
 ): Promise<Uint8Array<ArrayBuffer>> {
     const opts = {
         tensorId,
         projectId,
         asPng,
         nodeId,
+        format,
     }
     return new Uint8Array(await invoke("dtp_decode_tensor", opts))
 }
@@ -113,17 +138,24 @@ async function decodeTensor(
 async function findPredecessor(
     projectId: number,
     rowId: number,
-    lineage: number,
-    logicalTime: number,
 ): Promise<TensorHistoryExtra[]> {
     return await invoke("dtp_find_predecessor", {
         projectId,
         rowId,
-        lineage,
-        logicalTime,
     })
 }
 
+async function getLineage(projectId: number, rowId: number): Promise<NodeLineage> {
+    return await invoke("dtp_get_lineage", { projectId, rowId })
+}
+
+async function getPromptTimeline(
+    projectId: number,
+    textLineage: number,
+): Promise<PromptTimeline> {
+    return await invoke("dtp_get_prompt_timeline", { projectId, textLineage })
+}
+
 async function sync() {
     await invoke("dtp_sync")
 }
@@ -132,9 +164,33 @@ async function syncProjects(projectIds: number[]) {
     await invoke("dtp_sync_projects", { projectIds, checkDeletions: true })
 }
 
+/** Checks a project for corrupt or missing data, progress is sent as project_check_progress */
+async function checkProject(projectId: number): Promise<ProjectHealthReport> {
+    return await invoke("dtp_check_project", { projectId })
+}
+
+/** png16, tiff32 and exr32 keep the full precision of the tensor, only png embeds metadata */
+export type TensorImageFormat = "png8" | "png16" | "tiff32" | "exr32"
+
 export interface ProjectExportOptions {
     outputFolder: string
     useTensor: boolean
+    format?: TensorImageFormat
+    /** resizes images decoded from tensors */
+    resize?: {
+        width: number
+        height: number
+        fit?: ResizeFit
+        filter?: ResizeFilter
+    }
+    a1111Parameters?: boolean
+    privacy?: MetadataPrivacy
+}
+
+export interface MetadataPrivacy {
+    strip?: boolean
+    redactPrompts?: boolean
+    redactModels?: boolean
 }
 
 async function exportProjects(
@@ -144,6 +200,44 @@ async function exportProjects(
     return await invoke("dtp_export_projects", { projectIds, options })
 }
 
+export type TensorFileFormat = "npy" | "safetensors"
+
+/** writes a decompressed tensor as stored in the project, keeping its shape and dtype */
+async function exportTensorFile(
+    projectId: number,
+    tensorId: string,
+    format: TensorFileFormat,
+    outputFolder: string,
+    nodeId?: number | null,
+): Promise<string> {
+    return await invoke("dtp_export_tensor_file", {
+        projectId,
+        nodeId,
+        tensorId,
+        format,
+        outputFolder,
+    })
+}
+
+export interface InpaintViewOptions {
+    /** color drawn over the masked area, [r, g, b] */
+    tint?: [number, number, number]
+    /** 0 to 1, defaults to 0.5 */
+    opacity?: number
+    /** soften the mask edge with the node's mask blur, defaults to true */
+    blur?: boolean
+}
+
+/** writes the source, masked source and result of an inpainting node side by side as a png */
+async function exportInpaintView(
+    projectId: number,
+    nodeId: number,
+    outputFolder: string,
+    options?: InpaintViewOptions,
+): Promise<string> {
+    return await invoke("dtp_export_inpaint_view", { projectId, nodeId, outputFolder, options })
+}
+
 const DTPService = {
     connect,
     listProjects,
@@ -160,10 +254,19 @@ const DTPService = {
     getTensorSize,
     decodeTensor,
     findPredecessor,
+    getLineage,
+    getPromptTimeline,
     sync,
     syncProjects,
+    checkProject,
     exportProjects,
+    exportInpaintView,
+    exportTensorFile,
     lockFolder,
+    setThumbCacheBudget,
+    clearThumbCache,
+    setTensorCacheBudget,
+    getTensorCacheStats,
 }
 
 export default DTPService
@@ -28,6 +28,25 @@ export interface ProjectExtra {
     is_missing: boolean
     is_locked: boolean
     is_ready: boolean
+    kind: "projects" | "looseImages"
+    compatibility: CompatibilityReport | null
+}
+
+export interface CompatibilityReport {
+    compatible: boolean
+    tables: string[]
+    missing_tables: string[]
+    unknown_tables: string[]
+    flatbuffers: FlatbufferSchema[]
+}
+
+export interface FlatbufferSchema {
+    table: string
+    rows_checked: number
+    invalid_rows: number
+    known_fields: number
+    fields_present: number[]
+    unknown_fields: number[]
 }
 
 export interface ImageExtra {
@@ -61,8 +80,8 @@ export interface ImageExtra {
     sampler: number
     steps: number
     guidance_scale: number
-    strength: number
-    shift: number
+    strength: number | null
+    shift: number | null
     hires_fix: boolean
     tiled_decoding: boolean
     tiled_diffusion: boolean
@@ -157,6 +176,17 @@ export interface Clip {
     audioId: number
 }
 
+export interface TensorCacheStats {
+    entries: number
+    bytes: number
+    budget: number
+    hits: number
+    misses: number
+    evictions: number
+    /** tensors that were decoded but too large to cache */
+    skipped: number
+}
+
 export interface TensorSize {
     width: number
     height: number
@@ -334,6 +364,45 @@ export interface TensorHistoryExtra {
     clip: Clip | null | undefined
 }
 
+export interface LineageNode {
+    rowid: number
+    lineage: number
+    logicalTime: number
+    parent: number | null
+    branch: boolean
+    textLineage: number
+    tensorId: number
+    maskId: number
+    previewId: number
+    generated: boolean
+    strength: number
+}
+
+export interface NodeLineage {
+    node: LineageNode
+    ancestors: LineageNode[]
+    descendants: LineageNode[]
+}
+
+export interface TextModification {
+    modification_type: "PositiveText" | "NegativeText"
+    range: { location: number; length: number }
+    text: string
+}
+
+export interface PromptRevision {
+    text_edits: number
+    positive: string
+    negative: string
+    modification: TextModification | null
+    generations: number[]
+}
+
+export interface PromptTimeline {
+    lineage: number
+    revisions: PromptRevision[]
+}
+
 export interface TensorHistoryNodeRow {
     projectId: number
     rowid: number
@@ -348,3 +417,36 @@ export type ScanProgress = {
     images_found: number
     images_scanned: number
 }
+
+export type ProjectCheckProgress = {
+    project_id: number
+    nodes_checked: number
+    nodes_total: number
+}
+
+export type HealthCheck = "node" | "tensorData" | "tensor" | "thumbnail" | "clip"
+
+export interface HealthCount {
+    checked: number
+    failed: number
+}
+
+export interface HealthFailure {
+    check: HealthCheck
+    nodeId: number | null
+    /** tensor name, thumbnail or clip id */
+    name: string | null
+    message: string
+}
+
+export interface ProjectHealthReport {
+    path: string
+    healthy: boolean
+    nodes: HealthCount
+    tensorData: HealthCount
+    tensors: HealthCount
+    thumbnails: HealthCount
+    clips: HealthCount
+    /** the first failures found, the counts include the rest */
+    failures: HealthFailure[]
+}
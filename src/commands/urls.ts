@@ -16,20 +16,84 @@ function thumbHalf(arg?: ImageExtra | number, previewId?: number): string {
     return `dtm://dtproject/thumbhalf/${arg.project_id}/${arg.preview_id}`
 }
 
+export type ResizeFit = "contain" | "cover" | "exact"
+export type ResizeFilter = "lanczos3" | "catmullRom" | "box"
+
+interface ResizeOpts {
+    /** a square box, or [width, height] */
+    size?: number | [number, number] | null
+    /** defaults to contain, keeping the whole image */
+    fit?: ResizeFit
+    /** defaults to lanczos3 */
+    filter?: ResizeFilter
+}
+
+function setResize(url: URL, opts?: ResizeOpts) {
+    if (!opts?.size) return
+    const size = Array.isArray(opts.size) ? opts.size.join("x") : opts.size.toString()
+    url.searchParams.set("s", size)
+    if (opts.fit) url.searchParams.set("fit", opts.fit)
+    if (opts.filter) url.searchParams.set("filter", opts.filter)
+}
+
 const urls = {
     thumb,
     thumbHalf,
     tensor: (
         projectId: number,
         name: string,
-        opts?: {
+        opts?: ResizeOpts & {
             nodeId?: number | null
-            size?: number | null
+            /** for pose tensors, draw the skeleton over the generated image */
+            overlay?: boolean
         },
     ) => {
         const url = new URL(`dtm://dtproject/tensor/${projectId}/${name}`)
         if (opts?.nodeId) url.searchParams.set("node", opts.nodeId.toString())
-        if (opts?.size) url.searchParams.set("s", opts.size.toString())
+        setResize(url, opts)
+        if (opts?.overlay) url.searchParams.set("overlay", "1")
+        return url.toString()
+    },
+    /**
+     * canvas layers and masks are indexed from the top, moodboard images by position.
+     * moodboard weights come with the node, in its moodboard entries
+     */
+    nodeReference: (
+        type: "canvas" | "mask" | "moodboard",
+        projectId: number,
+        nodeId: number,
+        index: number,
+        opts?: ResizeOpts,
+    ) => {
+        const url = new URL(`dtm://dtproject/${type}/${projectId}/${index}`)
+        url.searchParams.set("node", nodeId.toString())
+        setResize(url, opts)
+        return url.toString()
+    },
+    /** the canvas as it was at the node, rebuilt from the layers and images of its lineage */
+    composite: (projectId: number, nodeId: number, opts?: ResizeOpts) => {
+        const url = new URL(`dtm://dtproject/composite/${projectId}/${nodeId}`)
+        setResize(url, opts)
+        return url.toString()
+    },
+    /** source, source with the mask drawn over it, and result of an inpainting node */
+    inpaint: (
+        projectId: number,
+        nodeId: number,
+        opts?: ResizeOpts & {
+            tint?: [number, number, number]
+            opacity?: number
+            blur?: boolean
+        },
+    ) => {
+        const url = new URL(`dtm://dtproject/inpaint/${projectId}/${nodeId}`)
+        setResize(url, opts)
+        if (opts?.tint) {
+            const hex = opts.tint.map((c) => c.toString(16).padStart(2, "0")).join("")
+            url.searchParams.set("tint", hex)
+        }
+        if (opts?.opacity !== undefined) url.searchParams.set("opacity", opts.opacity.toString())
+        if (opts?.blur !== undefined) url.searchParams.set("blur", opts.blur ? "1" : "0")
         return url.toString()
     },
     audio: (projectId: number, nodeId: number) => {
@@ -1,4 +1,5 @@
 import { invoke } from "@tauri-apps/api/core"
+import type { MetadataPrivacy } from "./DtpService"
 
 export interface FramesExportOpts {
     imageId: number
@@ -7,6 +8,7 @@ export interface FramesExportOpts {
     filenamePattern: string
     clipNumber?: number
     startFrame?: number
+    privacy?: MetadataPrivacy
 }
 
 export interface VideoExportOpts {
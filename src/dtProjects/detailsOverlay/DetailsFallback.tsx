@@ -89,10 +89,10 @@ function DetailsFallback(props: DetailsFallbackProps) {
                             <DataItem.Steps value={item.steps} />
                         </Row>
                         <Row>
-                            <DataItem.Strength value={item.strength} />
+                            <DataItem.Strength value={item.strength ?? undefined} />
                             <DataItem.GuidanceScale value={item.guidance_scale} />
                             <DataItem.Shift
-                                value={{ value: item.shift, resDependentShift: false }}
+                                value={{ value: item.shift ?? undefined, resDependentShift: false }}
                             />
                         </Row>
                         <DataItem label={"Prompt"} data={item.prompt} maxLines={6} />
@@ -1,5 +1,5 @@
 import type { ProjectExtra } from "@/commands"
-import type { ScanProgress } from "@/commands/DtpServiceTypes"
+import type { ProjectCheckProgress, ScanProgress } from "@/commands/DtpServiceTypes"
 import type { IContainer } from "@/utils/container/interfaces"
 import { Service } from "@/utils/container/Service"
 import { StateController } from "@/utils/container/StateController"
@@ -86,6 +86,10 @@ export type DTPEvents = {
     project_sync_started: (payload: number) => void
     project_sync_complete: (payload: number) => void
 
+    project_check_started: (payload: number) => void
+    project_check_progress: (payload: ProjectCheckProgress) => void
+    project_check_complete: (payload: number) => void
+
     dtp_service_ready: () => void
     projectsLoaded: (payload?: undefined) => void
     watchFoldersLoaded: (payload?: { foldersCount: number }) => void